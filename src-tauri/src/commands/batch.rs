@@ -1,18 +1,26 @@
 // src-tauri/src/commands/batch.rs
 use base64::Engine;
+use crate::commands::{active_profile_id, check_budget, transition_batch, transition_job};
 use crate::db::get_db;
-use crate::models::BatchStatus;
-use crate::paths::{get_api_key, get_results_dir, mime_from_ext, validate_batch_name};
-use reqwest::Client;
-use rusqlite::params;
+use crate::error::{AppError, AppResult};
+use crate::events;
+use crate::gemini::{
+    self, BatchLimits, GeminiClient, JsonlChunk, JsonlChunker, JsonlLines, UploadProgress,
+};
+use crate::models::{
+    BatchSettlement, BatchState, BatchStatus, ItemStatus, JobDelivery, JobMode, JobStatus,
+    KeyValidation, ModelAccess, SafetyRatings, TokenUsage,
+};
+use crate::paths::{get_default_model, get_results_dir, mime_from_ext, validate_batch_name};
+use crate::pricing;
+use futures_util::future::join_all;
+use futures_util::StreamExt;
+use rusqlite::{params, OptionalExtension};
 use serde_json::{json, Value};
 use std::fs;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Manager};
-
-const GEMINI_BASE: &str = "https://generativelanguage.googleapis.com";
-const MODEL: &str = "gemini-3.1-pro-preview";
+use tauri::{AppHandle, Manager, Runtime};
 
 /// Minimum interval between `validate_api_key` calls, process-wide.
 /// Keeps a buggy or hostile frontend from using the validation endpoint
@@ -22,560 +30,1448 @@ const VALIDATE_API_KEY_MIN_INTERVAL: Duration = Duration::from_secs(2);
 
 static VALIDATE_API_KEY_LAST: Mutex<Option<Instant>> = Mutex::new(None);
 
-fn get_app_data_dir(app: &AppHandle) -> Result<std::path::PathBuf, String> {
-    app.path().app_data_dir().map_err(|e| e.to_string())
+fn get_app_data_dir<R: Runtime>(app: &AppHandle<R>) -> AppResult<std::path::PathBuf> {
+    Ok(app.path().app_data_dir()?)
 }
 
+/// Submits a job as batches. Refused with `AppError::Budget` when the
+/// estimate exceeds the remaining daily or monthly budget, unless
+/// `override_budget` is set.
 #[tauri::command]
-pub async fn submit_batch(app: AppHandle, job_id: String) -> Result<(), String> {
-    let api_key = get_api_key(&app)?;
-    let app_data_dir = get_app_data_dir(&app)?;
+pub async fn submit_batch<R: Runtime>(
+    app: AppHandle<R>,
+    job_id: String,
+    override_budget: Option<bool>,
+) -> AppResult<()> {
+    if !override_budget.unwrap_or(false) {
+        check_budget(&app, &job_id, JobDelivery::Batch)?;
+    }
+    submit_job(&app, &job_id, BatchLimits::default()).await
+}
+
+/// Submits a job's pending items, split into as many batches as `limits`
+/// requires. Each chunk is built, uploaded and recorded before the next
+/// is started, so only one chunk's JSONL is ever held in memory.
+pub(crate) async fn submit_job<R: Runtime>(
+    app: &AppHandle<R>,
+    job_id: &str,
+    limits: BatchLimits,
+) -> AppResult<()> {
+    // A job sticks to the key profile that first submitted it.
+    let client = GeminiClient::for_job(app, job_id)?;
+    let app_data_dir = get_app_data_dir(app)?;
+
+    // Finish the chunk an earlier attempt was uploading before anything
+    // else, so its items are in a batch and not chunked a second time.
+    let resumed = resume_upload(app, &client, job_id).await?;
 
     // Read job metadata + items needing submission (pending or failed).
     // Using a transaction to also reset any failed items back to pending
     // and zero out the failed_items counter, so a retry submits only the
     // subset that didn't succeed the first time (completed items are not
     // re-billed, and download_results won't overwrite their saved output).
-    let (mode, temperature, prompt, output_size, aspect_ratio, items) = {
-        let db = get_db(&app);
-        let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
-        let tx = conn.transaction().map_err(|e| e.to_string())?;
-
-        let status: String = tx
-            .query_row(
-                "SELECT status FROM jobs WHERE id = ?1",
-                params![job_id],
-                |row| row.get(0),
-            )
-            .map_err(|e| e.to_string())?;
+    // After a resume the job is already processing; the claim then just
+    // picks up the pending items no batch has taken yet.
+    let (status, mode, model, temperature, prompt, image_config, items, batch_count) = {
+        let db = get_db(app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+
+        let status: JobStatus = tx.query_row(
+            "SELECT status FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )?;
 
-        if status == "processing" || status == "downloading" {
-            return Err(format!(
+        let continuing = resumed && status == JobStatus::Processing;
+        if !continuing && !status.can_transition_to(JobStatus::Processing) {
+            return Err(AppError::Conflict(format!(
                 "Cannot submit: job is already {}. Wait for it to finish.",
                 status
-            ));
+            )));
         }
 
-        let (mode, temperature, prompt, output_size, aspect_ratio): (
+        let (mode, model, temperature, prompt, output_size, aspect_ratio): (
+            JobMode,
             String,
             f64,
             String,
             String,
             String,
-        ) = tx
-            .query_row(
-                "SELECT mode, temperature, prompt, output_size, aspect_ratio FROM jobs WHERE id = ?1",
-                params![job_id],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
-            )
-            .map_err(|e| e.to_string())?;
-
-        let now = chrono::Utc::now().to_rfc3339();
-        tx.execute(
-            "UPDATE job_items SET status = 'pending', error = NULL, updated_at = ?1
-             WHERE job_id = ?2 AND status = 'failed'",
-            params![now, job_id],
-        )
-        .map_err(|e| e.to_string())?;
-
-        tx.execute(
-            "UPDATE jobs SET failed_items = 0, updated_at = ?1 WHERE id = ?2",
-            params![now, job_id],
-        )
-        .map_err(|e| e.to_string())?;
+        ) = tx.query_row(
+            "SELECT mode, model, temperature, prompt, output_size, aspect_ratio
+                 FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            },
+        )?;
+        // Validate size/ratio before any writes, so a bad job is refused
+        // untouched.
+        let image_config = gemini::image_config(&output_size, &aspect_ratio)?;
+
+        if !continuing {
+            let now = chrono::Utc::now().to_rfc3339();
+            tx.execute(
+                "UPDATE job_items SET status = ?1, error = NULL, response_text = NULL,
+                   finish_reason = NULL, block_reason = NULL, safety_ratings = '[]',
+                   updated_at = ?2
+                 WHERE job_id = ?3 AND status = ?4",
+                params![ItemStatus::Pending, now, job_id, ItemStatus::Failed],
+            )?;
+
+            tx.execute(
+                "UPDATE jobs SET failed_items = 0, profile_id = COALESCE(profile_id, ?1),
+                   updated_at = ?2
+                 WHERE id = ?3",
+                params![active_profile_id(&tx)?, now, job_id],
+            )?;
+        }
 
-        let mut stmt = tx
-            .prepare(
-                "SELECT id, input_prompt, input_image_path FROM job_items
-                 WHERE job_id = ?1 AND status = 'pending'",
-            )
-            .map_err(|e| e.to_string())?;
+        // Pending items of an in-flight batch are already submitted.
+        let mut stmt = tx.prepare(
+            "SELECT id, input_prompt, input_image_path FROM job_items
+                 WHERE job_id = ?1 AND status = ?2
+                   AND (batch_name IS NULL OR batch_name NOT IN (
+                     SELECT batch_name FROM job_batches WHERE status IN (?3, ?4)))",
+        )?;
 
         let items: Vec<(String, Option<String>, Option<String>)> = stmt
-            .query_map(params![job_id], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, Option<String>>(1)?,
-                    row.get::<_, Option<String>>(2)?,
-                ))
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
+            .query_map(
+                params![job_id, ItemStatus::Pending, JobStatus::Processing, JobStatus::Downloading],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
 
         drop(stmt);
-        tx.commit().map_err(|e| e.to_string())?;
+
+        let batch_count: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM job_batches WHERE job_id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )?;
+        tx.commit()?;
 
         if items.is_empty() {
-            return Err("Nothing to submit — all items already completed.".to_string());
+            if continuing {
+                return Ok(());
+            }
+            return Err(AppError::Conflict(
+                "Nothing to submit — all items already completed.".to_string(),
+            ));
         }
 
-        (mode, temperature, prompt, output_size, aspect_ratio, items)
+        (status, mode, model, temperature, prompt, image_config, items, batch_count)
     }; // lock dropped here
+    let client = client.with_model(model);
 
-    // Build JSONL
-    let mut jsonl_lines = Vec::new();
-    for (item_id, item_prompt, item_image_path) in &items {
-        let prompt_text = if mode == "text-to-image" {
-            let p = item_prompt.as_deref().unwrap_or(&prompt);
-            format!("Generate a {} {} image of: {}", output_size, aspect_ratio, p)
-        } else {
-            format!(
-                "Transform this image ({} {}) with: {}",
-                output_size, aspect_ratio, prompt
-            )
-        };
+    let temp_dir = app_data_dir.join("temp");
+    fs::create_dir_all(&temp_dir)
+        .map_err(|e| AppError::Io(format!("Failed to create temp dir: {}", e)))?;
+    let mut target = ChunkTarget {
+        client: &client,
+        job_id,
+        temp_dir: &temp_dir,
+        mode,
+        job_status: status,
+        next_index: batch_count,
+        submitted: 0,
+    };
 
-        let mut parts: Vec<Value> = vec![json!({"text": prompt_text})];
-
-        // For I2I, add image data
-        if mode == "image-to-image" {
-            if let Some(img_path) = item_image_path {
-                let img_data = fs::read(img_path).map_err(|_| {
-                    let name = std::path::Path::new(img_path)
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| "(invalid)".to_string());
-                    format!("Failed to read image: {}", name)
-                })?;
-                let b64 = base64::engine::general_purpose::STANDARD.encode(&img_data);
-                let ext = std::path::Path::new(img_path)
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("png");
-                let mime = mime_from_ext(ext);
-                parts.push(json!({
-                    "inline_data": {
-                        "mime_type": mime,
-                        "data": b64
-                    }
-                }));
+    let total = items.len();
+    let mut chunker = JsonlChunker::new(limits);
+    let result: AppResult<()> = async {
+        for (item_id, item_prompt, item_image_path) in &items {
+            let image = match (mode, item_image_path) {
+                (JobMode::ImageToImage, Some(path)) => {
+                    Some(input_file_part(app, &client, job_id, path).await?)
+                }
+                _ => None,
+            };
+            let line = request_line(
+                mode,
+                &prompt,
+                item_id,
+                item_prompt.as_deref(),
+                image,
+                temperature,
+                &image_config,
+            )?;
+            if let Some(chunk) = chunker.push(item_id.clone(), line) {
+                submit_chunk(app, &mut target, chunk).await?;
             }
         }
+        if let Some(chunk) = chunker.finish() {
+            submit_chunk(app, &mut target, chunk).await?;
+        }
+        Ok(())
+    }
+    .await;
 
-        let line = json!({
-            "key": item_id,
-            "request": {
-                "contents": [{"parts": parts}],
-                "generation_config": {
-                    "temperature": temperature,
-                    "responseModalities": ["TEXT", "IMAGE"]
-                }
-            }
-        });
-        jsonl_lines.push(serde_json::to_string(&line).map_err(|e| e.to_string())?);
+    if target.submitted > 0 {
+        events::emit_job(app, job_id);
     }
+    result.map_err(|e| match target.submitted {
+        0 => e,
+        n => e.map_message(|m| format!("Submitted {} of {} items before failing: {}", n, total, m)),
+    })
+}
 
-    let jsonl_content = jsonl_lines.join("\n");
-    let temp_dir = app_data_dir.join("temp");
-    fs::create_dir_all(&temp_dir).map_err(|e| format!("Failed to create temp dir: {}", e))?;
-    let jsonl_path = temp_dir.join(format!("batch-{}-{}.jsonl", mode, chrono::Utc::now().timestamp()));
-    fs::write(&jsonl_path, &jsonl_content).map_err(|e| format!("Failed to write JSONL: {}", e))?;
-
-    let client = app.state::<Client>();
-
-    // Step 1: Initiate resumable upload
-    let jsonl_bytes = jsonl_content.as_bytes();
-    let init_resp = client
-        .post(format!("{}/upload/v1beta/files", GEMINI_BASE))
-        .header("x-goog-api-key", &api_key)
-        .header("X-Goog-Upload-Protocol", "resumable")
-        .header("X-Goog-Upload-Command", "start")
-        .header("X-Goog-Upload-Header-Content-Length", jsonl_bytes.len().to_string())
-        .header("X-Goog-Upload-Header-Content-Type", "application/jsonl")
-        .header("Content-Type", "application/json")
-        .json(&json!({"file": {"display_name": format!("batch-{}", job_id)}}))
-        .send()
-        .await
-        .map_err(|e| format!("Upload init failed: {}", e))?;
-
-    if !init_resp.status().is_success() {
-        let status = init_resp.status();
-        let body = init_resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
-    }
-
-    let upload_url = init_resp
-        .headers()
-        .get("x-goog-upload-url")
-        .ok_or("No upload URL in response")?
-        .to_str()
-        .map_err(|e| e.to_string())?
-        .to_string();
-
-    // Step 2: Upload file content
-    let upload_resp = client
-        .put(&upload_url)
-        .header("X-Goog-Upload-Command", "upload, finalize")
-        .header("X-Goog-Upload-Offset", "0")
-        .header("Content-Length", jsonl_bytes.len().to_string())
-        .body(jsonl_content.clone())
-        .send()
-        .await
-        .map_err(|e| format!("Upload failed: {}", e))?;
-
-    if !upload_resp.status().is_success() {
-        let status = upload_resp.status();
-        let body = upload_resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
-    }
-
-    let upload_result: Value = upload_resp.json().await.map_err(|e| e.to_string())?;
-    let file_name = upload_result["file"]["name"]
-        .as_str()
-        .ok_or("No file name in upload response")?
-        .to_string();
-
-    // Step 3: Submit batch
-    let batch_resp = client
-        .post(format!(
-            "{}/v1beta/models/{}:batchGenerateContent",
-            GEMINI_BASE, MODEL
-        ))
-        .header("x-goog-api-key", &api_key)
-        .header("Content-Type", "application/json")
-        .json(&json!({
-            "batch": {
-                "display_name": format!("nanobanana-{}", job_id),
-                "input_config": {
-                    "requests": {
-                        "file_name": file_name
-                    }
-                }
-            }
-        }))
-        .send()
-        .await
-        .map_err(|e| format!("Batch submit failed: {}", e))?;
+/// Builds the JSONL line for one item. `image` is the I2I input part.
+fn request_line(
+    mode: JobMode,
+    prompt: &str,
+    item_id: &str,
+    item_prompt: Option<&str>,
+    image: Option<Value>,
+    temperature: f64,
+    image_config: &Value,
+) -> AppResult<String> {
+    let parts = request_parts(mode, prompt, item_prompt, image);
+    let line = gemini::batch_request_line(item_id, parts, temperature, image_config);
+    Ok(serde_json::to_string(&line)?)
+}
 
-    if !batch_resp.status().is_success() {
-        let status = batch_resp.status();
-        let body = batch_resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
-    }
+/// Content parts for one item: its prompt (T2I items carry their own),
+/// followed by the I2I input part if any.
+pub(crate) fn request_parts(
+    mode: JobMode,
+    prompt: &str,
+    item_prompt: Option<&str>,
+    image: Option<Value>,
+) -> Vec<Value> {
+    let prompt_text = if mode == JobMode::TextToImage {
+        item_prompt.unwrap_or(prompt)
+    } else {
+        prompt
+    };
 
-    let batch_result: Value = batch_resp.json().await.map_err(|e| e.to_string())?;
-    let batch_name = batch_result["name"]
-        .as_str()
-        .ok_or("No batch name in response")?
-        .to_string();
+    let mut parts: Vec<Value> = vec![json!({"text": prompt_text})];
+    parts.extend(image);
+    parts
+}
 
-    // Update job with batch name (scoped to drop lock)
-    {
-        let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+/// A cached upload is reused only if it outlives the longest a batch can
+/// take to run (24h), so it can't expire while the batch still needs it.
+const INPUT_FILE_MIN_REMAINING_HOURS: i64 = 24;
+
+/// `file_data` part for an I2I input. Each local image is uploaded to the
+/// Files API once per key profile; the resulting URI is cached in
+/// `gemini_files` under the profile that submitted `job_id` and reused by
+/// its later items, retries and re-runs until it nears expiry.
+pub(crate) async fn input_file_part<R: Runtime>(
+    app: &AppHandle<R>,
+    client: &GeminiClient,
+    job_id: &str,
+    path: &str,
+) -> AppResult<Value> {
+    let (profile, cached) = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        let profile: Option<String> = conn
+            .query_row(
+                "SELECT profile_id FROM jobs WHERE id = ?1",
+                params![job_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        let cached: Option<(String, String, Option<String>)> = conn
+            .query_row(
+                "SELECT file_uri, mime_type, expires_at FROM gemini_files
+                 WHERE profile_id = ?1 AND local_path = ?2",
+                params![profile, path],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        (profile, cached)
+    };
+    let min_expiry = chrono::Utc::now() + chrono::Duration::hours(INPUT_FILE_MIN_REMAINING_HOURS);
+    if let Some((uri, mime, expires_at)) = cached {
+        let fresh = expires_at
+            .and_then(|e| chrono::DateTime::parse_from_rfc3339(&e).ok())
+            .is_some_and(|e| e > min_expiry);
+        if fresh {
+            return Ok(file_data_part(&mime, &uri));
+        }
+    }
+
+    let local = std::path::Path::new(path);
+    let bytes = fs::read(local).map_err(|_| {
+        let name = local
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "(invalid)".to_string());
+        AppError::Io(format!("Failed to read image: {}", name))
+    })?;
+    let ext = local.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let mime = mime_from_ext(ext);
+    let display_name = local
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let file = client.upload_file(&display_name, mime, bytes).await?;
+    let uri = file
+        .uri
+        .ok_or_else(|| AppError::Http("No file URI in upload response".to_string()))?;
+    // A job sent before profiles existed has no profile to file it under.
+    if let Some(profile) = profile {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
         conn.execute(
-            "UPDATE jobs SET batch_job_name = ?1, batch_temp_file = ?2, status = 'processing', updated_at = ?3 WHERE id = ?4",
-            params![
-                batch_name,
-                jsonl_path.to_string_lossy().to_string(),
-                chrono::Utc::now().to_rfc3339(),
-                job_id
-            ],
+            "INSERT INTO gemini_files
+               (profile_id, local_path, file_name, file_uri, mime_type, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(profile_id, local_path) DO UPDATE SET
+               file_name = excluded.file_name, file_uri = excluded.file_uri,
+               mime_type = excluded.mime_type, expires_at = excluded.expires_at,
+               created_at = datetime('now')",
+            params![profile, path, file.name, uri, mime, file.expiration_time],
+        )?;
+    }
+    Ok(file_data_part(mime, &uri))
+}
+
+fn file_data_part(mime_type: &str, file_uri: &str) -> Value {
+    json!({
+        "file_data": {
+            "mime_type": mime_type,
+            "file_uri": file_uri
+        }
+    })
+}
+
+/// Where `submit_chunk` sends chunks, and what it has done so far.
+struct ChunkTarget<'a> {
+    client: &'a GeminiClient,
+    job_id: &'a str,
+    temp_dir: &'a std::path::Path,
+    mode: JobMode,
+    /// The job's status before this submission; `Processing` once the
+    /// first chunk is recorded.
+    job_status: JobStatus,
+    /// Numbers batch display names and temp files across retries.
+    next_index: i64,
+    submitted: usize,
+}
+
+/// Uploads one chunk as its own batch and records it in `job_batches`.
+async fn submit_chunk<R: Runtime>(
+    app: &AppHandle<R>,
+    target: &mut ChunkTarget<'_>,
+    chunk: JsonlChunk,
+) -> AppResult<()> {
+    let index = target.next_index;
+    let jsonl_path = target.temp_dir.join(format!(
+        "batch-{}-{}-{}.jsonl",
+        target.mode,
+        chrono::Utc::now().timestamp(),
+        index
+    ));
+    fs::write(&jsonl_path, &chunk.body)
+        .map_err(|e| AppError::Io(format!("Failed to write JSONL: {}", e)))?;
+
+    // The session is persisted before the first byte goes out, so an
+    // interrupted upload can be resumed by `resume_upload` instead of
+    // starting over.
+    let body = chunk.body.into_bytes();
+    let upload_url = target
+        .client
+        .start_upload(
+            &format!("batch-{}-{}", target.job_id, index),
+            "application/jsonl",
+            body.len(),
         )
-        .map_err(|e| e.to_string())?;
+        .await?;
+    save_upload(app, target.job_id, &upload_url, &jsonl_path, &chunk.keys, body.len())?;
+    let file = target
+        .client
+        .upload_from(&upload_url, &body, 0, |offset| {
+            record_upload_progress(app, target.job_id, offset)
+        })
+        .await?;
+
+    finish_chunk(app, target, &upload_url, &file.name, &chunk.keys, &jsonl_path).await
+}
+
+/// Creates the batch for an uploaded chunk and records it.
+async fn finish_chunk<R: Runtime>(
+    app: &AppHandle<R>,
+    target: &mut ChunkTarget<'_>,
+    upload_url: &str,
+    file_name: &str,
+    keys: &[String],
+    jsonl_path: &std::path::Path,
+) -> AppResult<()> {
+    let index = target.next_index;
+    let batch_name = target
+        .client
+        .create_batch(&format!("nanobanana-{}-{}", target.job_id, index), file_name)
+        .await?;
+
+    let recorded = record_chunk(app, target, upload_url, &batch_name, keys, jsonl_path);
+    if recorded.is_err() {
+        // The job was cancelled or deleted mid-submission; don't leave
+        // an orphan batch running (and billing) on Gemini.
+        let _ = target.client.cancel_batch(&batch_name).await;
+        let _ = forget_upload(app, target.job_id);
+        let _ = fs::remove_file(jsonl_path);
     }
+    recorded?;
 
+    target.job_status = JobStatus::Processing;
+    target.next_index += 1;
+    target.submitted += keys.len();
     Ok(())
 }
 
+/// Finishes the chunk upload an earlier submission left open, if any:
+/// asks Gemini how much arrived, sends the rest and records the batch.
+/// Returns whether a session was resumed. One that can't be (the job has
+/// settled, the temp file is gone, the session expired) is dropped, and
+/// its items are simply chunked again.
+async fn resume_upload<R: Runtime>(
+    app: &AppHandle<R>,
+    client: &GeminiClient,
+    job_id: &str,
+) -> AppResult<bool> {
+    let session: Option<(String, String, String, JobStatus, JobMode, String, i64)> = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.query_row(
+            "SELECT u.upload_url, u.temp_file, u.item_keys, j.status, j.mode, j.model,
+                    (SELECT COUNT(*) FROM job_batches b WHERE b.job_id = j.id)
+             FROM job_uploads u JOIN jobs j ON j.id = u.job_id
+             WHERE u.job_id = ?1",
+            params![job_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )
+        .optional()?
+    };
+    let Some((upload_url, temp_file, item_keys, status, mode, model, batch_count)) = session else {
+        return Ok(false);
+    };
+    let client = client.clone().with_model(model);
+
+    let session = async {
+        if status != JobStatus::Processing && !status.can_transition_to(JobStatus::Processing) {
+            return Err(AppError::Conflict(format!("job is {}", status)));
+        }
+        let keys: Vec<String> = serde_json::from_str(&item_keys)?;
+        if pending_count(app, &keys)? != keys.len() {
+            return Err(AppError::Conflict(
+                "its items were handled elsewhere".to_string(),
+            ));
+        }
+        let body = fs::read(&temp_file)
+            .map_err(|e| AppError::Io(format!("Failed to read JSONL: {}", e)))?;
+        let progress = client.query_upload(&upload_url).await?;
+        Ok((body, keys, progress))
+    }
+    .await;
+    let (body, keys, progress) = match session {
+        Ok(session) => session,
+        Err(e) => {
+            log::warn!("Dropping upload session for job {}: {}", job_id, e);
+            forget_upload(app, job_id)?;
+            return Ok(false);
+        }
+    };
+
+    let file = match progress {
+        UploadProgress::Final(file) => file,
+        UploadProgress::Active { received } => {
+            client
+                .upload_from(&upload_url, &body, received, |offset| {
+                    record_upload_progress(app, job_id, offset)
+                })
+                .await?
+        }
+    };
+
+    let jsonl_path = std::path::Path::new(&temp_file);
+    let mut target = ChunkTarget {
+        client: &client,
+        job_id,
+        temp_dir: jsonl_path.parent().unwrap_or(jsonl_path),
+        mode,
+        job_status: status,
+        next_index: batch_count,
+        submitted: 0,
+    };
+    finish_chunk(app, &mut target, &upload_url, &file.name, &keys, jsonl_path).await?;
+    events::emit_job(app, job_id);
+    Ok(true)
+}
+
+/// How many of `keys` are items still waiting to be submitted.
+fn pending_count<R: Runtime>(app: &AppHandle<R>, keys: &[String]) -> AppResult<usize> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    let mut count = 0;
+    for key in keys {
+        let status: Option<ItemStatus> = conn
+            .query_row(
+                "SELECT status FROM job_items
+                 WHERE id = ?1 AND (batch_name IS NULL OR batch_name NOT IN (
+                   SELECT batch_name FROM job_batches WHERE status IN (?2, ?3)))",
+                params![key, JobStatus::Processing, JobStatus::Downloading],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if status == Some(ItemStatus::Pending) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn save_upload<R: Runtime>(
+    app: &AppHandle<R>,
+    job_id: &str,
+    upload_url: &str,
+    jsonl_path: &std::path::Path,
+    keys: &[String],
+    total_bytes: usize,
+) -> AppResult<()> {
+    let keys = serde_json::to_string(keys)?;
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO job_uploads (job_id, upload_url, temp_file, item_keys, total_bytes)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            job_id,
+            upload_url,
+            jsonl_path.to_string_lossy().to_string(),
+            keys,
+            total_bytes as i64
+        ],
+    )?;
+    Ok(())
+}
+
+fn record_upload_progress<R: Runtime>(
+    app: &AppHandle<R>,
+    job_id: &str,
+    uploaded_bytes: usize,
+) -> AppResult<()> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    conn.execute(
+        "UPDATE job_uploads SET uploaded_bytes = ?1, updated_at = datetime('now')
+         WHERE job_id = ?2",
+        params![uploaded_bytes as i64, job_id],
+    )?;
+    Ok(())
+}
+
+/// Drops a job's upload session along with its temp JSONL.
+fn forget_upload<R: Runtime>(app: &AppHandle<R>, job_id: &str) -> AppResult<()> {
+    let temp_file: Option<String> = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        let temp_file = conn
+            .query_row(
+                "SELECT temp_file FROM job_uploads WHERE job_id = ?1",
+                params![job_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        conn.execute("DELETE FROM job_uploads WHERE job_id = ?1", params![job_id])?;
+        temp_file
+    };
+    if let Some(path) = temp_file {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+fn record_chunk<R: Runtime>(
+    app: &AppHandle<R>,
+    target: &ChunkTarget<'_>,
+    upload_url: &str,
+    batch_name: &str,
+    keys: &[String],
+    jsonl_path: &std::path::Path,
+) -> AppResult<()> {
+    let db = get_db(app);
+    let mut conn = db.conn.lock()?;
+    let tx = conn.transaction()?;
+
+    // Closing the session here also keeps two concurrent submits that
+    // resumed the same upload from both recording a batch for it.
+    let closed = tx.execute(
+        "DELETE FROM job_uploads WHERE job_id = ?1 AND upload_url = ?2",
+        params![target.job_id, upload_url],
+    )?;
+    if closed == 0 {
+        return Err(AppError::Conflict(
+            "Upload was already recorded by another submission".to_string(),
+        ));
+    }
+
+    if target.job_status == JobStatus::Processing {
+        let status: JobStatus = tx.query_row(
+            "SELECT status FROM jobs WHERE id = ?1",
+            params![target.job_id],
+            |row| row.get(0),
+        )?;
+        if status != JobStatus::Processing {
+            return Err(AppError::Conflict(format!(
+                "Job was {} while it was being submitted",
+                status
+            )));
+        }
+    } else if !transition_job(&tx, target.job_id, target.job_status, JobStatus::Processing)? {
+        return Err(AppError::Conflict(
+            "Job changed while it was being submitted".to_string(),
+        ));
+    }
+
+    tx.execute(
+        "INSERT INTO job_batches (batch_name, job_id, status, item_count, temp_file)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            batch_name,
+            target.job_id,
+            JobStatus::Processing,
+            keys.len() as i64,
+            jsonl_path.to_string_lossy().to_string()
+        ],
+    )?;
+    for key in keys {
+        tx.execute(
+            "UPDATE job_items SET batch_name = ?1, delivery = ?2 WHERE id = ?3",
+            params![batch_name, JobDelivery::Batch, key],
+        )?;
+    }
+    // The newest batch stands in for the job wherever one name is needed,
+    // e.g. cancel_batch, which then cancels all of the job's batches.
+    tx.execute(
+        "UPDATE jobs SET batch_job_name = ?1, delivery = ?2 WHERE id = ?3",
+        params![batch_name, JobDelivery::Batch, target.job_id],
+    )?;
+    Ok(tx.commit()?)
+}
+
 #[tauri::command]
-pub async fn poll_batch(app: AppHandle, batch_name: String) -> Result<BatchStatus, String> {
+pub async fn poll_batch<R: Runtime>(
+    app: AppHandle<R>,
+    batch_name: String,
+) -> AppResult<BatchStatus> {
     // Validate batch_name to prevent SSRF
     validate_batch_name(&batch_name)?;
 
-    let api_key = get_api_key(&app)?;
-    let client = app.state::<Client>();
+    let result = GeminiClient::for_batch(&app, &batch_name)?.get_batch(&batch_name).await?;
+    let status = batch_status(&result);
+    if let Some(settlement) = status.state.settlement() {
+        if let Some(job_id) = settle_batch(&app, &batch_name, settlement)? {
+            events::emit_job(&app, &job_id);
+        }
+    }
+    Ok(status)
+}
 
-    let resp = client
-        .get(format!("{}/v1beta/{}", GEMINI_BASE, batch_name))
-        .header("x-goog-api-key", &api_key)
-        .send()
-        .await
-        .map_err(|e| format!("Poll failed: {}", e))?;
+/// Summarizes a batch resource as returned by `GeminiClient::get_batch`.
+pub(crate) fn batch_status(batch: &Value) -> BatchStatus {
+    let state = BatchState::parse(batch["state"].as_str().unwrap_or_default());
+    let stats = &batch["batchStats"];
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
+    BatchStatus {
+        state,
+        total_requests: stat_count(&stats["totalRequestCount"]),
+        completed_requests: stat_count(&stats["successRequestCount"]),
+        failed_requests: stat_count(&stats["failedRequestCount"]),
     }
+}
 
-    let result: Value = resp.json().await.map_err(|e| e.to_string())?;
+/// Gemini serializes int64 fields as JSON strings ("3"), but accept bare
+/// numbers too so a proxy that re-encodes the body doesn't zero the stats.
+fn stat_count(value: &Value) -> i32 {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        .unwrap_or(0) as i32
+}
 
-    let state = result["state"].as_str().unwrap_or("JOB_STATE_PENDING").to_string();
-    let stats = &result["batchStats"];
+/// Settles a `processing` batch that ended without results: moves it to
+/// the settlement's status, fails its pending items if the settlement says
+/// so, and settles the owning job once none of its batches are in flight.
+/// Returns the job id, or `None` if the batch isn't processing (already
+/// settled, cancelled locally, or deleted).
+pub(crate) fn settle_batch<R: Runtime>(
+    app: &AppHandle<R>,
+    batch_name: &str,
+    settlement: BatchSettlement,
+) -> AppResult<Option<String>> {
+    let db = get_db(app);
+    let mut conn = db.conn.lock()?;
+    let tx = conn.transaction()?;
+
+    let job_id: Option<String> = tx
+        .query_row(
+            "SELECT b.job_id FROM job_batches b JOIN jobs j ON j.id = b.job_id
+             WHERE b.batch_name = ?1 AND b.status = ?2 AND j.status = ?2",
+            params![batch_name, JobStatus::Processing],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(job_id) = job_id else {
+        return Ok(None);
+    };
 
-    Ok(BatchStatus {
-        state,
-        total_requests: stats["totalRequestCount"].as_i64().unwrap_or(0) as i32,
-        completed_requests: stats["successRequestCount"].as_i64().unwrap_or(0) as i32,
-        failed_requests: stats["failedRequestCount"].as_i64().unwrap_or(0) as i32,
-    })
+    transition_batch(&tx, batch_name, JobStatus::Processing, settlement.job_status)?;
+    if let Some(error) = settlement.item_error {
+        tx.execute(
+            "UPDATE job_items SET status = ?1, error = ?2, updated_at = ?3
+             WHERE batch_name = ?4 AND status = ?5",
+            params![
+                ItemStatus::Failed,
+                error,
+                chrono::Utc::now().to_rfc3339(),
+                batch_name,
+                ItemStatus::Pending
+            ],
+        )?;
+    }
+    settle_job(&tx, &job_id, JobStatus::Processing)?;
+    tx.commit()?;
+    Ok(Some(job_id))
+}
+
+/// Called whenever one of a job's batches finishes. Refreshes the job's
+/// counts from its items; if other batches are still in flight the job
+/// stays (or goes back to) `processing`, otherwise it moves from `from`
+/// to its final status.
+pub(crate) fn settle_job(
+    conn: &rusqlite::Connection,
+    job_id: &str,
+    from: JobStatus,
+) -> AppResult<()> {
+    // Source counts from the DB so retries don't clobber prior success
+    // counts with only the retry-batch deltas. Per-item UPDATEs have
+    // already written the authoritative status for each row.
+    let (completed, failed, pending): (i32, i32, i32) = conn.query_row(
+        "SELECT
+               COALESCE(SUM(CASE WHEN status = ?2 THEN 1 ELSE 0 END), 0),
+               COALESCE(SUM(CASE WHEN status = ?3 THEN 1 ELSE 0 END), 0),
+               COALESCE(SUM(CASE WHEN status = ?4 THEN 1 ELSE 0 END), 0)
+             FROM job_items WHERE job_id = ?1",
+        params![
+            job_id,
+            ItemStatus::Completed,
+            ItemStatus::Failed,
+            ItemStatus::Pending
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    conn.execute(
+        "UPDATE jobs SET completed_items = ?1, failed_items = ?2 WHERE id = ?3",
+        params![completed, failed, job_id],
+    )?;
+
+    let in_flight: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM job_batches WHERE job_id = ?1 AND status IN (?2, ?3)",
+        params![job_id, JobStatus::Processing, JobStatus::Downloading],
+        |row| row.get(0),
+    )?;
+
+    let next = if in_flight > 0 {
+        JobStatus::Processing
+    } else if failed > 0 && completed == 0 {
+        JobStatus::Failed
+    } else if completed == 0 && pending > 0 && from == JobStatus::Processing {
+        // Nothing ran: every batch was cancelled on Gemini's side.
+        JobStatus::Cancelled
+    } else {
+        JobStatus::Completed
+    };
+    if next != from {
+        transition_job(conn, job_id, from, next)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn download_results(
-    app: AppHandle,
+pub async fn download_results<R: Runtime>(
+    app: AppHandle<R>,
     batch_name: String,
     job_id: String,
-) -> Result<(), String> {
+) -> AppResult<()> {
     // Validate batch_name to prevent SSRF
     validate_batch_name(&batch_name)?;
 
-    // CAS guard: claim the job by flipping processing -> downloading.
-    // A concurrent download_results or a mid-flight delete_job will see
-    // the non-'processing' state and bail, preventing orphan result files
-    // and duplicate image writes. On startup we reset any stranded
-    // 'downloading' rows back to 'processing' so a crash is recoverable.
+    // CAS guard: claim the batch and its job by flipping processing ->
+    // downloading. A concurrent download_results or a mid-flight
+    // delete_job will see the non-'processing' state and bail, preventing
+    // orphan result files and duplicate image writes. On startup we reset
+    // any stranded 'downloading' rows back to 'processing' so a crash is
+    // recoverable.
     {
         let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        let affected = conn
-            .execute(
-                "UPDATE jobs SET status = 'downloading', updated_at = ?1
-                 WHERE id = ?2 AND status = 'processing'",
-                params![chrono::Utc::now().to_rfc3339(), job_id],
-            )
-            .map_err(|e| e.to_string())?;
-        if affected == 0 {
-            return Err("Job is not in processing state (already downloaded, cancelled, or deleted)".to_string());
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        if !transition_batch(
+            &tx,
+            &batch_name,
+            JobStatus::Processing,
+            JobStatus::Downloading,
+        )? || !transition_job(&tx, &job_id, JobStatus::Processing, JobStatus::Downloading)?
+        {
+            return Err(AppError::Conflict(
+                "Job is not in processing state (already downloaded, cancelled, or deleted)"
+                    .to_string(),
+            ));
         }
+        tx.commit()?;
     }
 
-    let api_key = get_api_key(&app)?;
-    let client = app.state::<Client>();
+    if let Err(e) = save_results(&app, &batch_name).await {
+        let db = get_db(&app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        if is_transient(&e) {
+            // Hand the batch back so the next poll retries it; the cursor
+            // and the items saved so far let that attempt pick up where
+            // this one stopped.
+            log::warn!("Download of {} failed, will retry: {}", batch_name, e);
+            transition_batch(
+                &tx,
+                &batch_name,
+                JobStatus::Downloading,
+                JobStatus::Processing,
+            )?;
+            transition_job(&tx, &job_id, JobStatus::Downloading, JobStatus::Processing)?;
+        } else {
+            // Trying again would fail the same way, so fail what the batch
+            // hadn't delivered and settle the job instead of polling it
+            // forever.
+            log::warn!("Download of {} failed: {}", batch_name, e);
+            transition_batch(&tx, &batch_name, JobStatus::Downloading, JobStatus::Failed)?;
+            tx.execute(
+                "UPDATE job_items SET status = ?1, error = ?2, updated_at = ?3
+                 WHERE batch_name = ?4 AND status = ?5",
+                params![
+                    ItemStatus::Failed,
+                    format!("Failed to download results: {}", e),
+                    chrono::Utc::now().to_rfc3339(),
+                    batch_name,
+                    ItemStatus::Pending
+                ],
+            )?;
+            settle_job(&tx, &job_id, JobStatus::Downloading)?;
+        }
+        tx.commit()?;
+        drop(conn);
+        events::emit_job(&app, &job_id);
+        return Err(e);
+    }
+
+    let temp_file: Option<String> = {
+        let db = get_db(&app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        transition_batch(
+            &tx,
+            &batch_name,
+            JobStatus::Downloading,
+            JobStatus::Completed,
+        )?;
+        settle_job(&tx, &job_id, JobStatus::Downloading)?;
+        let temp_file = tx.query_row(
+            "SELECT temp_file FROM job_batches WHERE batch_name = ?1",
+            params![batch_name],
+            |row| row.get(0),
+        )?;
+        tx.commit()?;
+        temp_file
+    };
+
+    // Clean up temp JSONL file
+    if let Some(path) = temp_file {
+        let _ = fs::remove_file(&path);
+    }
 
-    // Get batch to find result file
-    let resp = client
-        .get(format!("{}/v1beta/{}", GEMINI_BASE, batch_name))
-        .header("x-goog-api-key", &api_key)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+    events::emit_job(&app, &job_id);
+    Ok(())
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
+/// Whether a failed download may go through if tried again later: the
+/// connection dropped, Gemini was briefly unavailable, or saving hit a
+/// local error (a full disk) the user can clear. A 4xx or a batch without
+/// a result file fails the same way every time.
+fn is_transient(error: &AppError) -> bool {
+    match error {
+        AppError::Http(_) | AppError::Io(_) | AppError::Db(_) => true,
+        AppError::Api { status, .. } => reqwest::StatusCode::from_u16(*status)
+            .is_ok_and(|status| gemini::is_retryable_status(status, true)),
+        _ => false,
     }
+}
 
-    let batch: Value = resp.json().await.map_err(|e| e.to_string())?;
+/// Streams the batch's result JSONL and saves each item as its line
+/// completes: a 4K batch runs to gigabytes of base64, so it is never held
+/// whole. Lines before the batch's `downloaded_lines` cursor were saved by
+/// an earlier attempt and are skipped unparsed.
+async fn save_results<R: Runtime>(app: &AppHandle<R>, batch_name: &str) -> AppResult<()> {
+    let client = GeminiClient::for_batch(app, batch_name)?;
+    let batch = client.get_batch(batch_name).await?;
     let result_file = batch["dest"]["fileName"]
         .as_str()
-        .ok_or("No result file in batch response")?;
-
-    // Download result JSONL
-    let result_resp = client
-        .get(format!(
-            "{}/download/v1beta/{}:download?alt=media",
-            GEMINI_BASE, result_file
-        ))
-        .header("x-goog-api-key", &api_key)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
-
-    if !result_resp.status().is_success() {
-        let status = result_resp.status();
-        let body = result_resp.text().await.unwrap_or_default();
-        return Err(format!("API request failed ({}): {}", status, body));
-    }
-
-    let result_text = result_resp.text().await.map_err(|e| e.to_string())?;
-    let results_dir = get_results_dir(&app)?;
+        .ok_or_else(|| AppError::NotFound("No result file in batch response".to_string()))?;
+
+    let results_dir = get_results_dir(app)?;
     let now = chrono::Utc::now().to_rfc3339();
+    let saved: i64 = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.query_row(
+            "SELECT downloaded_lines FROM job_batches WHERE batch_name = ?1",
+            params![batch_name],
+            |row| row.get(0),
+        )?
+    };
 
-    // Parse results and save images (all DB access scoped)
-    for line in result_text.lines() {
-        if line.trim().is_empty() {
-            continue;
+    let mut line_no = 0;
+    let mut save_line = |line: &[u8]| -> AppResult<()> {
+        line_no += 1;
+        if line_no <= saved {
+            return Ok(());
+        }
+        match serde_json::from_slice::<Value>(line) {
+            Ok(parsed) => {
+                let key = parsed["key"].as_str().unwrap_or("");
+                let charge = format!("{}#{}", batch_name, line_no);
+                save_item_result(app, &results_dir, key, &parsed, &charge, &now)?;
+            }
+            // A line that doesn't parse never will, so fail its item and
+            // move on rather than have every retry stop here again.
+            Err(e) => match line_key(line) {
+                Some(key) => fail_item(app, &key, &format!("Unreadable result: {}", e), &now)?,
+                None => log::warn!("Skipping unreadable line {} of {}: {}", line_no, batch_name, e),
+            },
+        }
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.execute(
+            "UPDATE job_batches SET downloaded_lines = ?1 WHERE batch_name = ?2",
+            params![line_no, batch_name],
+        )?;
+        Ok(())
+    };
+
+    let mut body = client.download_file(result_file).await?.bytes_stream();
+    let mut lines = JsonlLines::default();
+    while let Some(chunk) = body.next().await {
+        let chunk =
+            chunk.map_err(|e| AppError::Http(format!("Failed to download results: {}", e)))?;
+        for line in lines.push(&chunk) {
+            save_line(&line)?;
         }
-        let parsed: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    }
+    if let Some(line) = lines.finish() {
+        save_line(&line)?;
+    }
+    Ok(())
+}
 
-        let key = parsed["key"].as_str().unwrap_or("").to_string();
+/// The `key` of a result line that isn't valid JSON, if the start of the
+/// line, where the key is, is intact.
+fn line_key(line: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(line);
+    let rest = text.split_once("\"key\"")?.1.trim_start().strip_prefix(':')?;
+    serde_json::Deserializer::from_str(rest).into_iter::<String>().next()?.ok()
+}
 
-        // Gemini returns errors as {"code": ..., "message": ..., "status": ...}
-        // not as a bare string. Pull the message field.
-        if let Some(error) = parsed["error"]["message"].as_str() {
-            let error = error.to_string();
-            let db = get_db(&app);
-            let conn = db.conn.lock().map_err(|e| e.to_string())?;
-            conn.execute(
-                "UPDATE job_items SET status = 'failed', error = ?1, updated_at = ?2 WHERE id = ?3",
-                params![error, now, key],
+/// Records one item's outcome from a result line (`{error}` or
+/// `{response}`, as in the batch result JSONL): saves every image of every
+/// candidate to `results_dir` and marks the item completed, or marks it
+/// failed if there are none. `charge` names the response (batch and line,
+/// or one `generate_now` call) so its usage is added to the item's totals
+/// only the first time it is recorded.
+pub(crate) fn save_item_result<R: Runtime>(
+    app: &AppHandle<R>,
+    results_dir: &std::path::Path,
+    key: &str,
+    result: &Value,
+    charge: &str,
+    now: &str,
+) -> AppResult<()> {
+    // Already saved by an earlier, interrupted download of this result.
+    if item_saved(app, key)? {
+        return Ok(());
+    }
+
+    let details = ResponseDetails::from_response(&result["response"]);
+    {
+        let db = get_db(app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        let job: Option<(String, JobDelivery)> = tx
+            .query_row(
+                "SELECT j.model, i.delivery FROM jobs j JOIN job_items i ON i.job_id = j.id
+                 WHERE i.id = ?1",
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?)),
             )
-            .map_err(|e| e.to_string())?;
-        } else if let Some(candidates) = parsed["response"]["candidates"].as_array() {
-            let mut saved = false;
-            for candidate in candidates {
-                if let Some(parts) = candidate["content"]["parts"].as_array() {
-                    for part in parts {
-                        if let Some(inline_data) = part.get("inlineData") {
-                            let mime = inline_data["mimeType"].as_str().unwrap_or("image/png");
-                            let data = inline_data["data"].as_str().unwrap_or("");
-                            let ext = match mime {
-                                "image/jpeg" => "jpg",
-                                "image/webp" => "webp",
-                                "image/gif" => "gif",
-                                _ => "png",
-                            };
-
-                            let file_id = uuid::Uuid::new_v4().to_string();
-                            let file_path = results_dir.join(format!("{}.{}", file_id, ext));
-
-                            let decoded = base64::engine::general_purpose::STANDARD
-                                .decode(data)
-                                .map_err(|e| format!("Base64 decode failed: {}", e))?;
-
-                            fs::write(&file_path, &decoded)
-                                .map_err(|e| format!("Failed to write image: {}", e))?;
-
-                            {
-                                let db = get_db(&app);
-                                let conn = db.conn.lock().map_err(|e| e.to_string())?;
-                                conn.execute(
-                                    "UPDATE job_items SET status = 'completed', output_image_path = ?1, updated_at = ?2 WHERE id = ?3",
-                                    params![file_path.to_string_lossy().to_string(), now, key],
-                                )
-                                .map_err(|e| e.to_string())?;
-                            }
-
-                            saved = true;
-                            break;
-                        }
-                    }
-                    if saved {
-                        break;
+            .optional()?;
+        tx.execute(
+            "UPDATE job_items SET response_text = ?1, finish_reason = ?2, block_reason = ?3,
+               safety_ratings = ?4
+             WHERE id = ?5",
+            params![
+                details.text,
+                details.finish_reason,
+                details.block_reason,
+                details.safety_ratings,
+                key
+            ],
+        )?;
+        // Every attempt is billed, so a retried item's usage adds up; a
+        // response read again (a reset cursor, a missing output file) was
+        // billed once and is skipped.
+        if let Some((usage, (model, delivery))) = details.usage.as_ref().zip(job) {
+            let cost = pricing::model_pricing(&model).map(|p| p.cost(usage, delivery));
+            let new = tx.execute(
+                "INSERT OR IGNORE INTO job_item_charges (source, item_id, cost, recorded_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![charge, key, cost, now],
+            )? > 0;
+            if new {
+                tx.execute(
+                    "UPDATE job_items SET prompt_tokens = COALESCE(prompt_tokens, 0) + ?1,
+                       text_output_tokens = COALESCE(text_output_tokens, 0) + ?2,
+                       image_output_tokens = COALESCE(image_output_tokens, 0) + ?3,
+                       thoughts_tokens = COALESCE(thoughts_tokens, 0) + ?4,
+                       cost = CASE WHEN ?5 IS NULL THEN cost ELSE COALESCE(cost, 0) + ?5 END
+                     WHERE id = ?6",
+                    params![
+                        usage.prompt_tokens,
+                        usage.text_output_tokens,
+                        usage.image_output_tokens,
+                        usage.thoughts_tokens,
+                        cost,
+                        key
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+    }
+
+    // Gemini returns errors as {"code": ..., "message": ..., "status": ...}
+    // not as a bare string. Pull the message field.
+    if let Some(error) = result["error"]["message"].as_str() {
+        return fail_item(app, key, error, now);
+    }
+
+    let images = match save_images(results_dir, result) {
+        Ok(images) => images,
+        // Image data that doesn't decode won't on a retry either.
+        Err(AppError::Validation(e)) => return fail_item(app, key, &e, now),
+        Err(e) => return Err(e),
+    };
+    let Some(first) = images.first() else {
+        return fail_item(app, key, &details.no_image_error(), now);
+    };
+    {
+        let db = get_db(app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        // Rows left by an attempt that failed before marking the item.
+        tx.execute(
+            "DELETE FROM job_item_outputs WHERE item_id = ?1",
+            params![key],
+        )?;
+        for image in &images {
+            tx.execute(
+                "INSERT INTO job_item_outputs
+                 (item_id, candidate_index, part_index, path, mime_type, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    key,
+                    image.candidate_index as i64,
+                    image.part_index as i64,
+                    image.path,
+                    image.mime_type,
+                    now
+                ],
+            )?;
+        }
+        tx.execute(
+            "UPDATE job_items SET status = ?1, output_image_path = ?2, updated_at = ?3 WHERE id = ?4",
+            params![ItemStatus::Completed, first.path, now, key],
+        )?;
+        tx.commit()?;
+    }
+    events::emit_item(app, key);
+    Ok(())
+}
+
+/// What a response says besides its images.
+struct ResponseDetails {
+    text: Option<String>,
+    finish_reason: Option<String>,
+    block_reason: Option<String>,
+    safety_ratings: SafetyRatings,
+    usage: Option<TokenUsage>,
+}
+
+impl ResponseDetails {
+    fn from_response(response: &Value) -> Self {
+        let candidates = response["candidates"].as_array().map_or(&[][..], Vec::as_slice);
+        let text: Vec<&str> = candidates
+            .iter()
+            .flat_map(|c| c["content"]["parts"].as_array().into_iter().flatten())
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        let first = candidates.first();
+        // A blocked prompt has no candidates; its ratings are on the prompt.
+        let ratings = first
+            .map(|c| &c["safetyRatings"])
+            .filter(|r| r.is_array())
+            .unwrap_or(&response["promptFeedback"]["safetyRatings"]);
+        Self {
+            text: (!text.is_empty()).then(|| text.join("\n")),
+            finish_reason: first
+                .and_then(|c| c["finishReason"].as_str())
+                .map(str::to_string),
+            block_reason: response["promptFeedback"]["blockReason"]
+                .as_str()
+                .map(str::to_string),
+            safety_ratings: serde_json::from_value(ratings.clone()).unwrap_or_default(),
+            usage: token_usage(&response["usageMetadata"]),
+        }
+    }
+
+    /// Why no image came back, as specifically as the response tells.
+    fn no_image_error(&self) -> String {
+        if let Some(reason) = &self.block_reason {
+            return format!("Prompt was blocked ({})", reason);
+        }
+        match self.finish_reason.as_deref() {
+            None | Some("STOP" | "FINISH_REASON_UNSPECIFIED") => "No image in response".to_string(),
+            Some(
+                reason @ ("SAFETY" | "IMAGE_SAFETY" | "PROHIBITED_CONTENT"
+                | "IMAGE_PROHIBITED_CONTENT" | "BLOCKLIST" | "SPII"),
+            ) => format!("Blocked by safety filters ({})", reason),
+            Some(reason) => format!("Generation stopped ({})", reason),
+        }
+    }
+}
+
+/// Reads `usageMetadata`. Output tokens come as one total plus a
+/// per-modality breakdown; whatever isn't image is billed as text.
+fn token_usage(metadata: &Value) -> Option<TokenUsage> {
+    metadata.as_object()?;
+    let count = |field: &str| metadata[field].as_i64().unwrap_or(0);
+    let image_output_tokens: i64 = metadata["candidatesTokensDetails"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|d| d["modality"] == "IMAGE")
+        .filter_map(|d| d["tokenCount"].as_i64())
+        .sum();
+    Some(TokenUsage {
+        prompt_tokens: count("promptTokenCount"),
+        text_output_tokens: (count("candidatesTokenCount") - image_output_tokens).max(0),
+        image_output_tokens,
+        thoughts_tokens: count("thoughtsTokenCount"),
+    })
+}
+
+struct SavedImage {
+    candidate_index: usize,
+    part_index: usize,
+    path: String,
+    mime_type: String,
+}
+
+/// Decodes and writes every `inlineData` part of every candidate. If one
+/// fails, the files already written for this result are removed again.
+fn save_images(results_dir: &std::path::Path, result: &Value) -> AppResult<Vec<SavedImage>> {
+    let mut images: Vec<SavedImage> = Vec::new();
+    let candidates = result["response"]["candidates"].as_array();
+    for (candidate_index, candidate) in candidates.into_iter().flatten().enumerate() {
+        let Some(parts) = candidate["content"]["parts"].as_array() else {
+            continue;
+        };
+        for (part_index, part) in parts.iter().enumerate() {
+            let Some(inline_data) = part.get("inlineData") else {
+                continue;
+            };
+            let mime = inline_data["mimeType"].as_str().unwrap_or("image/png");
+            match save_image(results_dir, mime, inline_data["data"].as_str().unwrap_or("")) {
+                Ok(path) => images.push(SavedImage {
+                    candidate_index,
+                    part_index,
+                    path,
+                    mime_type: mime.to_string(),
+                }),
+                Err(e) => {
+                    for image in &images {
+                        let _ = fs::remove_file(&image.path);
                     }
+                    return Err(e);
                 }
             }
-            if !saved {
-                let db = get_db(&app);
-                let conn = db.conn.lock().map_err(|e| e.to_string())?;
-                conn.execute(
-                    "UPDATE job_items SET status = 'failed', error = 'No image in response', updated_at = ?1 WHERE id = ?2",
-                    params![now, key],
-                )
-                .map_err(|e| e.to_string())?;
-            }
         }
     }
+    Ok(images)
+}
 
-    // Source counts from the DB so retries don't clobber prior success
-    // counts with only the retry-batch deltas. Per-item UPDATEs above
-    // have already written the authoritative status for each row.
-    let (completed, failed): (i32, i32) = {
-        let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        conn.query_row(
-            "SELECT
-               COALESCE(SUM(CASE WHEN status = 'completed' THEN 1 ELSE 0 END), 0),
-               COALESCE(SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END), 0)
-             FROM job_items WHERE job_id = ?1",
-            params![job_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| e.to_string())?
+fn save_image(results_dir: &std::path::Path, mime: &str, data: &str) -> AppResult<String> {
+    let ext = match mime {
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "png",
     };
 
-    let final_status = if failed > 0 && completed == 0 {
-        "failed"
-    } else {
-        "completed"
-    };
+    let file_id = uuid::Uuid::new_v4().to_string();
+    let file_path = results_dir.join(format!("{}.{}", file_id, ext));
 
-    {
-        let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "UPDATE jobs SET status = ?1, completed_items = ?2, failed_items = ?3, updated_at = ?4 WHERE id = ?5",
-            params![final_status, completed, failed, now, job_id],
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| AppError::Validation(format!("Undecodable image data: {}", e)))?;
+
+    fs::write(&file_path, &decoded)
+        .map_err(|e| AppError::Io(format!("Failed to write image: {}", e)))?;
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+/// Whether the item is completed and its output file is still on disk.
+fn item_saved<R: Runtime>(app: &AppHandle<R>, key: &str) -> AppResult<bool> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    let output: Option<Option<String>> = conn
+        .query_row(
+            "SELECT output_image_path FROM job_items WHERE id = ?1 AND status = ?2",
+            params![key, ItemStatus::Completed],
+            |row| row.get(0),
         )
-        .map_err(|e| e.to_string())?;
-    }
+        .optional()?;
+    Ok(output
+        .flatten()
+        .is_some_and(|path| std::path::Path::new(&path).is_file()))
+}
 
-    // Clean up temp JSONL file
+pub(crate) fn fail_item<R: Runtime>(
+    app: &AppHandle<R>,
+    key: &str,
+    error: &str,
+    now: &str,
+) -> AppResult<()> {
     {
-        let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        let temp_file: Option<String> = conn.query_row(
-            "SELECT batch_temp_file FROM jobs WHERE id = ?1",
-            params![job_id],
-            |row| row.get(0),
-        ).ok().flatten();
-        if let Some(path) = temp_file {
-            let _ = fs::remove_file(&path);
-        }
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.execute(
+            "UPDATE job_items SET status = ?1, error = ?2, updated_at = ?3 WHERE id = ?4",
+            params![ItemStatus::Failed, error, now, key],
+        )?;
     }
-
+    events::emit_item(app, key);
     Ok(())
 }
 
 #[tauri::command]
-pub async fn cancel_batch(app: AppHandle, batch_name: String) -> Result<(), String> {
+pub async fn cancel_batch<R: Runtime>(app: AppHandle<R>, batch_name: String) -> AppResult<()> {
     // Validate batch_name
     validate_batch_name(&batch_name)?;
 
-    let api_key = get_api_key(&app)?;
-    let client = app.state::<Client>();
+    // Cancelling any of a job's batches cancels the whole job, so cancel
+    // every batch of that job still in flight.
+    let (job_id, batch_names): (Option<String>, Vec<String>) = {
+        let db = get_db(&app);
+        let conn = db.conn.lock()?;
+        let job_id: Option<String> = conn
+            .query_row(
+                "SELECT job_id FROM job_batches WHERE batch_name = ?1",
+                params![batch_name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let mut batch_names = match &job_id {
+            Some(job_id) => {
+                let mut stmt = conn.prepare(
+                    "SELECT batch_name FROM job_batches WHERE job_id = ?1 AND status = ?2",
+                )?;
+                let names = stmt
+                    .query_map(params![job_id, JobStatus::Processing], |row| row.get(0))?
+                    .collect::<Result<Vec<String>, _>>()?;
+                names
+            }
+            None => Vec::new(),
+        };
+        if !batch_names.contains(&batch_name) {
+            batch_names.push(batch_name.clone());
+        }
+        (job_id, batch_names)
+    };
 
-    let _resp = client
-        .post(format!("{}/v1beta/{}:cancel", GEMINI_BASE, batch_name))
-        .header("x-goog-api-key", &api_key)
-        .send()
-        .await
-        .map_err(|e| format!("Cancel failed: {}", e))?;
-    // Don't require success — batch may already be done
+    // Try every batch even if one fails, so a single unreachable batch
+    // doesn't leave the others running. Batches that had already finished
+    // count as cancelled.
+    let client = match &job_id {
+        Some(job_id) => GeminiClient::for_job(&app, job_id)?,
+        None => GeminiClient::from_app(&app)?,
+    };
+    let mut cancelled = Vec::new();
+    let mut failures = Vec::new();
+    for name in &batch_names {
+        match client.cancel_batch(name).await {
+            Ok(()) => cancelled.push(name),
+            Err(e) => failures.push((name, e)),
+        }
+    }
 
     // Update job status in DB
+    let Some(job_id) = job_id else {
+        return failures.into_iter().next().map_or(Ok(()), |(_, e)| Err(e));
+    };
     {
         let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
-        conn.execute(
-            "UPDATE jobs SET status = 'cancelled', updated_at = ?1 WHERE batch_job_name = ?2 AND status IN ('pending', 'processing')",
-            params![chrono::Utc::now().to_rfc3339(), batch_name],
-        ).map_err(|e| e.to_string())?;
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        for name in &cancelled {
+            transition_batch(&tx, name, JobStatus::Processing, JobStatus::Cancelled)?;
+        }
+        // The job is only cancelled once none of its batches can still run.
+        let status: JobStatus = tx.query_row(
+            "SELECT status FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )?;
+        if failures.is_empty() && status.can_transition_to(JobStatus::Cancelled) {
+            transition_job(&tx, &job_id, status, JobStatus::Cancelled)?;
+        }
+        tx.commit()?;
     }
 
-    Ok(())
+    events::emit_job(&app, &job_id);
+    let mut failures = failures.into_iter();
+    let Some((_, first)) = failures.next() else {
+        return Ok(());
+    };
+    for (name, e) in failures {
+        log::warn!("Failed to cancel {}: {}", name, e);
+    }
+    Err(first)
 }
 
+/// Checks `api_key` against `model` (default: the `default_model`
+/// setting) and every other model in the pricing table, reporting why a
+/// refused key was refused. Not reaching the API is reported in the
+/// result rather than as an error.
 #[tauri::command]
-pub async fn validate_api_key(app: AppHandle, api_key: String) -> Result<bool, String> {
+pub async fn validate_api_key<R: Runtime>(
+    app: AppHandle<R>,
+    api_key: String,
+    model: Option<String>,
+) -> AppResult<KeyValidation> {
     {
-        let mut last = VALIDATE_API_KEY_LAST
-            .lock()
-            .map_err(|e| e.to_string())?;
+        let mut last = VALIDATE_API_KEY_LAST.lock()?;
         let now = Instant::now();
         if let Some(prev) = *last {
             if now.duration_since(prev) < VALIDATE_API_KEY_MIN_INTERVAL {
-                return Err(
+                return Err(AppError::Validation(
                     "Too many validation attempts. Wait a moment and try again.".to_string(),
-                );
+                ));
             }
         }
         *last = Some(now);
     }
 
-    let client = app.state::<Client>();
-
-    let resp = client
-        .get(format!(
-            "{}/v1beta/models/{}",
-            GEMINI_BASE, MODEL
-        ))
-        .header("x-goog-api-key", &api_key)
-        .send()
-        .await
-        .map_err(|e| format!("Validation failed: {}", e))?;
+    let model = match model.filter(|m| !m.is_empty()) {
+        Some(model) => {
+            gemini::validate_model(&model)?;
+            model
+        }
+        None => get_default_model(&app)?,
+    };
+    let client = GeminiClient::with_key(&app, api_key)?;
+    Ok(diagnose_key(&client, &model).await)
+}
 
-    Ok(resp.status().is_success())
+/// Probes `model` and the priced models concurrently, each once; a
+/// retried probe would hide the latency and transient errors it reports.
+async fn diagnose_key(client: &GeminiClient, model: &str) -> KeyValidation {
+    let mut models = vec![model.to_string()];
+    for pricing in pricing::PRICING {
+        if !models.iter().any(|m| m == pricing.model) {
+            models.push(pricing.model.to_string());
+        }
+    }
+    let probes = join_all(models.iter().map(|m| client.probe_model(m))).await;
+
+    let mut message = None;
+    let mut access = Vec::with_capacity(models.len());
+    for (model, (result, elapsed)) in models.into_iter().zip(probes) {
+        let (http_status, reason, error) = match result {
+            Ok((status, _)) if (200..300).contains(&status) => (Some(status), None, None),
+            Ok((status, body)) => (
+                Some(status),
+                gemini::api_error_reason(&body),
+                body["error"]["message"].as_str().map(str::to_string),
+            ),
+            Err(e) => (None, None, Some(e)),
+        };
+        if access.is_empty() {
+            message = error;
+        }
+        access.push(ModelAccess {
+            model,
+            accessible: http_status.is_some_and(|s| (200..300).contains(&s)),
+            http_status,
+            reason,
+            latency_ms: elapsed.as_millis() as u64,
+        });
+    }
+    let requested = &access[0];
+    KeyValidation {
+        valid: requested.accessible,
+        reachable: requested.http_status.is_some(),
+        http_status: requested.http_status,
+        reason: requested.reason.clone(),
+        message,
+        latency_ms: requested.latency_ms,
+        models: access,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{VALIDATE_API_KEY_LAST, VALIDATE_API_KEY_MIN_INTERVAL};
-    use std::time::Instant;
+    use super::*;
+    use crate::commands::{create_i2i_job, create_t2i_job, get_job, save_config};
+    use crate::gemini::mock::{complete_job, t2i_job, t2i_request, MockGemini};
+    use crate::models::{CreateI2IJobRequest, CreateT2IJobRequest, SafetyRating};
+    use base64::engine::general_purpose::STANDARD;
+    use std::collections::VecDeque;
 
     #[test]
     fn rate_limit_interval_is_2s() {
@@ -618,4 +1514,1039 @@ mod tests {
         // a previous test run in-process; we just assert it's sane.
         let _ = *last; // type-checks as Option<Instant>
     }
+
+    #[test]
+    fn stat_count_accepts_strings_and_numbers() {
+        assert_eq!(stat_count(&json!("3")), 3);
+        assert_eq!(stat_count(&json!(4)), 4);
+        assert_eq!(stat_count(&json!(null)), 0);
+        assert_eq!(stat_count(&json!("x")), 0);
+    }
+
+    #[tokio::test]
+    async fn submit_poll_download_round_trip() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING", "JOB_STATE_SUCCEEDED"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let request = CreateT2IJobRequest {
+            aspect_ratio: "16:9".to_string(),
+            ..t2i_request(&["a red fox", "a blue whale"], "1K")
+        };
+        let created = create_t2i_job(app.clone(), request).unwrap();
+        let job_id = created.job.id.clone();
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+
+        let job = get_job(app.clone(), job_id.clone()).unwrap().job;
+        assert_eq!(job.status, JobStatus::Processing);
+        let batch_name = job.batch_job_name.unwrap();
+
+        // The uploaded JSONL carries the raw prompts and a real imageConfig.
+        let requests = mock.state.lock().unwrap().batch_requests(&batch_name);
+        assert_eq!(requests.len(), 2);
+        for line in &requests {
+            let text = line["request"]["contents"][0]["parts"][0]["text"].as_str().unwrap();
+            assert!(text == "a red fox" || text == "a blue whale", "{}", text);
+            let image_config = &line["request"]["generation_config"]["imageConfig"];
+            assert_eq!(image_config["imageSize"], "1024x576");
+            assert_eq!(image_config["aspectRatio"], "16:9");
+        }
+
+        let status = poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+        assert_eq!(status.state, BatchState::Running);
+        let status = poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+        assert_eq!(status.state, BatchState::Succeeded);
+        assert_eq!(status.total_requests, 2);
+        assert_eq!(status.completed_requests, 2);
+
+        download_results(app.clone(), batch_name, job_id.clone()).await.unwrap();
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!(done.job.completed_items, 2);
+        for item in &done.items {
+            assert_eq!(item.status, ItemStatus::Completed);
+            let path = item.output_image_path.as_ref().unwrap();
+            let bytes = fs::read(path).unwrap();
+            assert!(bytes.starts_with(b"\x89PNG"), "saved file is a PNG");
+        }
+    }
+
+    #[tokio::test]
+    async fn upload_submit_poll_and_download_ride_out_transient_errors() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a"], "1K").job.id;
+
+        // Every call of the round trip is turned away once first.
+        let turned_away = |mock: &MockGemini| {
+            mock.state.lock().unwrap().transient_errors =
+                VecDeque::from([(503, None), (429, Some("0".to_string()))]);
+        };
+        turned_away(&mock);
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        let batch_name = get_job(app.clone(), job_id.clone()).unwrap().job.batch_job_name.unwrap();
+        turned_away(&mock);
+        poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+        turned_away(&mock);
+        download_results(app.clone(), batch_name, job_id.clone()).await.unwrap();
+
+        assert_eq!(get_job(app.clone(), job_id).unwrap().job.status, JobStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn download_records_per_item_errors() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["ok", "blocked", "wordy"], "1K");
+        let job_id = created.job.id.clone();
+        {
+            let mut state = mock.state.lock().unwrap();
+            state
+                .item_errors
+                .insert(created.items[1].id.clone(), "Prompt was blocked".to_string());
+            state
+                .item_text_only
+                .insert(created.items[2].id.clone(), "I can't draw that.".to_string());
+        }
+
+        let done = complete_job(&app, &job_id).await;
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!(done.job.completed_items, 1);
+        assert_eq!(done.job.failed_items, 2);
+        let by_id = |id: &str| done.items.iter().find(|i| i.id == id).unwrap();
+        assert_eq!(by_id(&created.items[0].id).status, ItemStatus::Completed);
+        assert_eq!(by_id(&created.items[1].id).error.as_deref(), Some("Prompt was blocked"));
+        assert_eq!(by_id(&created.items[2].id).error.as_deref(), Some("No image in response"));
+    }
+
+    #[tokio::test]
+    async fn undecodable_results_fail_their_items_and_settle_the_job() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        for (prompts, bad, expected) in [
+            (&["ok", "corrupt"][..], 1, JobStatus::Completed),
+            (&["corrupt"][..], 0, JobStatus::Failed),
+        ] {
+            let created = t2i_job(&app, prompts, "1K");
+            let job_id = created.job.id.clone();
+            let corrupt = created.items[bad].id.clone();
+            mock.state.lock().unwrap().item_bad_image.insert(corrupt.clone());
+
+            let done = complete_job(&app, &job_id).await;
+            assert_eq!(done.job.status, expected);
+            assert_eq!(done.batches[0].status, JobStatus::Completed);
+            let batch_name = done.job.batch_job_name.as_deref().unwrap();
+            assert_eq!(downloaded_lines(&app, batch_name), prompts.len() as i64);
+            let item = done.items.iter().find(|i| i.id == corrupt).unwrap();
+            assert_eq!(item.status, ItemStatus::Failed);
+            assert!(item.error.as_deref().unwrap().starts_with("Undecodable image data"));
+        }
+    }
+
+    #[test]
+    fn line_key_reads_the_key_of_a_broken_line() {
+        assert_eq!(
+            line_key(br#"{"key": "item-1", "response": {"candidates": [{"#).as_deref(),
+            Some("item-1")
+        );
+        assert_eq!(line_key(b"{\"response\": "), None);
+    }
+
+    fn downloaded_lines(app: &AppHandle<tauri::test::MockRuntime>, batch_name: &str) -> i64 {
+        let db = get_db(app);
+        let conn = db.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT downloaded_lines FROM job_batches WHERE batch_name = ?1",
+            params![batch_name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn interrupted_download_reverts_and_resumes_without_duplicates() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a", "b", "c"], "1K").job.id;
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        let batch_name = get_job(app.clone(), job_id.clone()).unwrap().job.batch_job_name.unwrap();
+        poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+
+        // The first download breaks off halfway through the second line.
+        mock.state.lock().unwrap().truncate_downloads = VecDeque::from([1]);
+        let err = download_results(app.clone(), batch_name.clone(), job_id.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Failed to download results"), "{}", err);
+
+        let job = get_job(app.clone(), job_id.clone()).unwrap();
+        assert_eq!(job.job.status, JobStatus::Processing);
+        assert_eq!(job.batches[0].status, JobStatus::Processing);
+        let completed = || {
+            get_job(app.clone(), job_id.clone())
+                .unwrap()
+                .items
+                .iter()
+                .filter(|i| i.status == ItemStatus::Completed)
+                .count()
+        };
+        assert_eq!(completed(), 1);
+        assert_eq!(downloaded_lines(&app, &batch_name), 1);
+
+        // As if the app died after saving the first item but before
+        // advancing the cursor: the item must still not be saved twice.
+        {
+            let db = get_db(&app);
+            let conn = db.conn.lock().unwrap();
+            conn.execute("UPDATE job_batches SET downloaded_lines = 0", []).unwrap();
+        }
+        download_results(app.clone(), batch_name.clone(), job_id.clone()).await.unwrap();
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!(done.job.completed_items, 3);
+        assert_eq!(downloaded_lines(&app, &batch_name), 3);
+        let saved = fs::read_dir(get_results_dir(&app).unwrap()).unwrap().count();
+        assert_eq!(saved, 3, "no duplicate result files");
+    }
+
+    #[tokio::test]
+    async fn download_that_cannot_succeed_fails_the_batch_instead_of_retrying() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a", "b"], "1K").job.id;
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        let batch_name = get_job(app.clone(), job_id.clone()).unwrap().job.batch_job_name.unwrap();
+        poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+
+        mock.state.lock().unwrap().expire_results(&batch_name);
+        let err = download_results(app.clone(), batch_name.clone(), job_id.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Api { status: 404, .. }), "{:?}", err);
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Failed);
+        assert_eq!(done.batches[0].status, JobStatus::Failed);
+        assert_eq!(done.job.failed_items, 2);
+        for item in &done.items {
+            assert!(item.error.as_deref().unwrap().starts_with("Failed to download results"));
+        }
+    }
+
+    #[test]
+    fn only_transient_download_errors_are_retried() {
+        let api = |status| AppError::Api {
+            status,
+            code: None,
+            message: String::new(),
+        };
+        assert!(is_transient(&AppError::Http("connection reset".to_string())));
+        assert!(is_transient(&AppError::Io("disk full".to_string())));
+        assert!(is_transient(&api(503)));
+        assert!(is_transient(&api(500)));
+        assert!(!is_transient(&api(404)));
+        assert!(!is_transient(&AppError::Auth("key revoked".to_string())));
+        assert!(!is_transient(&AppError::NotFound("no result file".to_string())));
+    }
+
+    #[tokio::test]
+    async fn download_saves_every_candidate_image() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let created = t2i_job(&app, &["one", "many"], "1K");
+        let job_id = created.job.id.clone();
+        let many = created.items[1].id.clone();
+        mock.state.lock().unwrap().item_candidates.insert(many.clone(), 3);
+
+        let done = complete_job(&app, &job_id).await;
+        for item in &done.items {
+            let expected: Vec<i32> = if item.id == many { vec![0, 1, 2] } else { vec![0] };
+            let indexes: Vec<i32> = item.outputs.iter().map(|o| o.candidate_index).collect();
+            assert_eq!(indexes, expected);
+            assert_eq!(item.output_image_path.as_ref(), Some(&item.outputs[0].path));
+            for output in &item.outputs {
+                assert_eq!(output.mime_type, "image/png");
+                assert!(fs::read(&output.path).unwrap().starts_with(b"\x89PNG"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn download_records_token_usage_and_cost() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let created = t2i_job(&app, &["a", "b"], "1K");
+        let job_id = created.job.id.clone();
+        // 2 x 1120 image tokens at the batch rate of $30 per million.
+        let estimate = created.job.estimated_cost.unwrap();
+        assert!((estimate - 0.0672).abs() < 1e-9, "{}", estimate);
+        assert_eq!(created.job.actual_cost, None);
+
+        let done = complete_job(&app, &job_id).await;
+        for item in &done.items {
+            let usage = item.usage.as_ref().unwrap();
+            assert_eq!(
+                *usage,
+                TokenUsage {
+                    prompt_tokens: 10,
+                    text_output_tokens: 5,
+                    image_output_tokens: 1120,
+                    thoughts_tokens: 0,
+                }
+            );
+            // (10 * $0.50 + 5 * $3 + 1120 * $60) per million, halved.
+            assert!((item.cost.unwrap() - 0.03361).abs() < 1e-9);
+        }
+        assert!((done.job.actual_cost.unwrap() - 0.06722).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn a_retried_item_costs_every_attempt() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let created = t2i_job(&app, &["wordy"], "1K");
+        let job_id = created.job.id.clone();
+        let item_id = created.items[0].id.clone();
+        mock.state.lock().unwrap().item_text_only.insert(item_id.clone(), "No.".to_string());
+
+        // (10 * $0.50 + 5 * $3) per million, halved.
+        let first = complete_job(&app, &job_id).await;
+        assert_eq!(first.items[0].status, ItemStatus::Failed);
+        assert!((first.items[0].cost.unwrap() - 0.00001).abs() < 1e-12);
+
+        // Reading the same result again doesn't bill it again.
+        let batch_name = first.job.batch_job_name.unwrap();
+        {
+            let db = get_db(&app);
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE job_batches SET status = ?1, downloaded_lines = 0 WHERE batch_name = ?2",
+                params![JobStatus::Processing, batch_name],
+            )
+            .unwrap();
+            conn.execute(
+                "UPDATE jobs SET status = ?1 WHERE id = ?2",
+                params![JobStatus::Processing, job_id],
+            )
+            .unwrap();
+        }
+        download_results(app.clone(), batch_name, job_id.clone()).await.unwrap();
+        let reread = get_job(app.clone(), job_id.clone()).unwrap();
+        assert!((reread.items[0].cost.unwrap() - 0.00001).abs() < 1e-12);
+
+        mock.state.lock().unwrap().item_text_only.clear();
+        let second = complete_job(&app, &job_id).await;
+        assert_eq!(second.items[0].status, ItemStatus::Completed);
+        assert!((second.items[0].cost.unwrap() - 0.03362).abs() < 1e-9);
+        assert!((second.job.actual_cost.unwrap() - 0.03362).abs() < 1e-9);
+        // The tokens add up the same way, so they still match the cost.
+        assert_eq!(
+            second.items[0].usage,
+            Some(TokenUsage {
+                prompt_tokens: 20,
+                text_output_tokens: 10,
+                image_output_tokens: 1120,
+                thoughts_tokens: 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn item_records_text_finish_reason_and_safety() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let created = t2i_job(&app, &["a", "b", "c", "d"], "1K");
+        let ids: Vec<&str> = created.items.iter().map(|i| i.id.as_str()).collect();
+        let results_dir = get_results_dir(&app).unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let data = STANDARD.encode(b"png");
+        let image = json!({"inlineData": {"mimeType": "image/png", "data": data}});
+        let results = [
+            json!({"response": {"candidates": [{
+                "content": {"parts": [{"text": "A fox."}, image]},
+                "finishReason": "STOP",
+            }]}}),
+            json!({"response": {"candidates": [{
+                "finishReason": "IMAGE_SAFETY",
+                "safetyRatings": [
+                    {"category": "HARM_CATEGORY_DANGEROUS_CONTENT", "probability": "HIGH", "blocked": true},
+                    {"category": "HARM_CATEGORY_HARASSMENT", "probability": "NEGLIGIBLE"},
+                ],
+            }]}}),
+            json!({"response": {"promptFeedback": {
+                "blockReason": "PROHIBITED_CONTENT",
+                "safetyRatings": [{"category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "probability": "HIGH"}],
+            }}}),
+            json!({"response": {"candidates": [{
+                "content": {"parts": [{"text": "I can't draw that."}]},
+                "finishReason": "STOP",
+            }]}}),
+        ];
+        for (id, result) in ids.iter().zip(&results) {
+            save_item_result(&app, &results_dir, id, result, id, &now).unwrap();
+        }
+
+        let items = get_job(app.clone(), created.job.id).unwrap().items;
+        let item = |id: &str| items.iter().find(|i| i.id == id).unwrap();
+
+        let drawn = item(ids[0]);
+        assert_eq!(drawn.status, ItemStatus::Completed);
+        assert_eq!(drawn.response_text.as_deref(), Some("A fox."));
+        assert_eq!(drawn.finish_reason.as_deref(), Some("STOP"));
+
+        let unsafe_image = item(ids[1]);
+        assert_eq!(
+            unsafe_image.error.as_deref(),
+            Some("Blocked by safety filters (IMAGE_SAFETY)")
+        );
+        assert_eq!(
+            unsafe_image.safety_ratings.0[0],
+            SafetyRating {
+                category: "HARM_CATEGORY_DANGEROUS_CONTENT".into(),
+                probability: "HIGH".into(),
+                blocked: true,
+            }
+        );
+        assert!(!unsafe_image.safety_ratings.0[1].blocked);
+
+        let blocked_prompt = item(ids[2]);
+        assert_eq!(blocked_prompt.block_reason.as_deref(), Some("PROHIBITED_CONTENT"));
+        assert_eq!(
+            blocked_prompt.error.as_deref(),
+            Some("Prompt was blocked (PROHIBITED_CONTENT)")
+        );
+        assert_eq!(blocked_prompt.safety_ratings.0.len(), 1);
+
+        let refused = item(ids[3]);
+        assert_eq!(refused.error.as_deref(), Some("No image in response"));
+        assert_eq!(refused.response_text.as_deref(), Some("I can't draw that."));
+        assert!(refused.block_reason.is_none() && refused.safety_ratings.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn retry_resubmits_only_failed_items() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["one", "two"], "1K");
+        let job_id = created.job.id.clone();
+        let failing = created.items[1].id.clone();
+        mock.state.lock().unwrap().item_errors.insert(failing.clone(), "transient".to_string());
+
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        let first = get_job(app.clone(), job_id.clone()).unwrap().job.batch_job_name.unwrap();
+        poll_batch(app.clone(), first.clone()).await.unwrap();
+        download_results(app.clone(), first, job_id.clone()).await.unwrap();
+
+        mock.state.lock().unwrap().item_errors.clear();
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        let second = get_job(app.clone(), job_id.clone()).unwrap().job.batch_job_name.unwrap();
+        let requests = mock.state.lock().unwrap().batch_requests(&second);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0]["key"], failing.as_str());
+
+        poll_batch(app.clone(), second.clone()).await.unwrap();
+        download_results(app.clone(), second, job_id.clone()).await.unwrap();
+        let done = get_job(app.clone(), job_id).unwrap().job;
+        assert_eq!((done.completed_items, done.failed_items), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn retry_with_a_bad_size_is_refused_before_anything_changes() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["one", "two"], "1K");
+        let job_id = created.job.id.clone();
+        let failing = created.items[1].id.clone();
+        mock.state.lock().unwrap().item_errors.insert(failing.clone(), "transient".to_string());
+        complete_job(&app, &job_id).await;
+        get_db(&app)
+            .conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE jobs SET output_size = '9K' WHERE id = ?1", params![job_id])
+            .unwrap();
+        let sent = mock.state.lock().unwrap().requests.len();
+
+        let err = submit_batch(app.clone(), job_id.clone(), None).await.unwrap_err();
+        assert!(matches!(err, AppError::Validation(_)), "{:?}", err);
+        let after = get_job(app.clone(), job_id).unwrap();
+        assert_eq!((after.job.status, after.job.failed_items), (JobStatus::Completed, 1));
+        let item = after.items.iter().find(|i| i.id == failing).unwrap();
+        assert_eq!(item.status, ItemStatus::Failed);
+        assert_eq!(mock.state.lock().unwrap().requests.len(), sent);
+    }
+
+    fn small_batches() -> BatchLimits {
+        BatchLimits {
+            max_items: 2,
+            max_bytes: usize::MAX,
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_splits_job_into_batches_by_item_count() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b", "c", "d", "e"], "1K");
+        let job_id = created.job.id.clone();
+        submit_job(&app, &job_id, small_batches()).await.unwrap();
+
+        let submitted = get_job(app.clone(), job_id.clone()).unwrap();
+        assert_eq!(submitted.job.status, JobStatus::Processing);
+        let sizes: Vec<i32> = submitted.batches.iter().map(|b| b.item_count).collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        for batch in &submitted.batches {
+            let requests = mock.state.lock().unwrap().batch_requests(&batch.batch_name);
+            assert_eq!(requests.len() as i32, batch.item_count);
+        }
+
+        let names: Vec<String> = submitted.batches.iter().map(|b| b.batch_name.clone()).collect();
+        for (i, name) in names.iter().enumerate() {
+            poll_batch(app.clone(), name.clone()).await.unwrap();
+            download_results(app.clone(), name.clone(), job_id.clone()).await.unwrap();
+            let job = get_job(app.clone(), job_id.clone()).unwrap().job;
+            if i + 1 < names.len() {
+                // Earlier batches land while the job keeps processing.
+                assert_eq!(job.status, JobStatus::Processing);
+            }
+        }
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!(done.job.completed_items, 5);
+        assert!(done.items.iter().all(|i| i.output_image_path.is_some()));
+        assert!(done.batches.iter().all(|b| b.status == JobStatus::Completed));
+    }
+
+    fn upload_session(app: &AppHandle<tauri::test::MockRuntime>, job_id: &str) -> Option<String> {
+        let db = get_db(app);
+        let conn = db.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT item_keys FROM job_uploads WHERE job_id = ?1",
+            params![job_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn interrupted_upload_resumes_on_next_submit() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b", "c"], "1K");
+        let job_id = created.job.id.clone();
+        mock.state.lock().unwrap().fail_upload_chunks = usize::MAX;
+        let err = submit_job(&app, &job_id, small_batches())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Upload interrupted"), "{}", err);
+
+        // The first chunk's session outlives the failure.
+        assert_eq!(get_job(app.clone(), job_id.clone()).unwrap().job.status, JobStatus::Pending);
+        let keys: Vec<String> =
+            serde_json::from_str(&upload_session(&app, &job_id).unwrap()).unwrap();
+        assert_eq!(keys, vec![created.items[0].id.clone(), created.items[1].id.clone()]);
+
+        mock.state.lock().unwrap().fail_upload_chunks = 0;
+        submit_job(&app, &job_id, small_batches()).await.unwrap();
+
+        let submitted = get_job(app.clone(), job_id.clone()).unwrap();
+        assert_eq!(submitted.job.status, JobStatus::Processing);
+        let sizes: Vec<i32> = submitted.batches.iter().map(|b| b.item_count).collect();
+        assert_eq!(sizes, vec![2, 1]);
+        assert!(upload_session(&app, &job_id).is_none());
+        // One session per chunk: the interrupted one was continued, not
+        // started over.
+        assert_eq!(file_uploads(&mock), 2);
+        let state = mock.state.lock().unwrap();
+        assert!(state.requests.iter().any(|r| r.starts_with("POST /upload/session/")));
+        let first = state.batch_requests(&submitted.batches[0].batch_name);
+        assert_eq!(first[0]["key"], created.items[0].id.as_str());
+    }
+
+    #[tokio::test]
+    async fn session_of_a_completed_job_is_dropped() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let job_id = t2i_job(&app, &["a"], "1K").job.id;
+        mock.state.lock().unwrap().fail_upload_chunks = usize::MAX;
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap_err();
+        let temp_file: String = {
+            let db = get_db(&app);
+            let conn = db.conn.lock().unwrap();
+            conn.query_row(
+                "SELECT temp_file FROM job_uploads WHERE job_id = ?1",
+                params![job_id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        {
+            let db = get_db(&app);
+            let conn = db.conn.lock().unwrap();
+            // E.g. generated with generate_now after the failed submit.
+            conn.execute(
+                "UPDATE job_items SET status = ?1 WHERE job_id = ?2",
+                params![ItemStatus::Completed, job_id],
+            )
+            .unwrap();
+        }
+
+        assert!(!resume_upload(&app, &GeminiClient::from_app(&app).unwrap(), &job_id)
+            .await
+            .unwrap());
+        assert!(upload_session(&app, &job_id).is_none());
+        assert!(!std::path::Path::new(&temp_file).exists());
+        let state = mock.state.lock().unwrap();
+        assert!(!state.requests.iter().any(|r| r.ends_with(":batchGenerateContent")));
+    }
+
+    #[tokio::test]
+    async fn expired_sub_batch_fails_only_its_items() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b", "c"], "1K");
+        let job_id = created.job.id.clone();
+        submit_job(&app, &job_id, small_batches()).await.unwrap();
+        let batches = get_job(app.clone(), job_id.clone()).unwrap().batches;
+        let (first, second) = (batches[0].batch_name.clone(), batches[1].batch_name.clone());
+        mock.state.lock().unwrap().script_batch(&second, &["JOB_STATE_EXPIRED"]);
+
+        poll_batch(app.clone(), second).await.unwrap();
+        assert_eq!(
+            get_job(app.clone(), job_id.clone()).unwrap().job.status,
+            JobStatus::Processing
+        );
+        poll_batch(app.clone(), first.clone()).await.unwrap();
+        download_results(app.clone(), first, job_id.clone()).await.unwrap();
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!((done.job.completed_items, done.job.failed_items), (2, 1));
+        let expired: Vec<&str> = done
+            .items
+            .iter()
+            .filter_map(|i| i.error.as_deref())
+            .collect();
+        assert_eq!(expired, vec!["Batch expired before completing"]);
+    }
+
+    #[tokio::test]
+    async fn cancel_cancels_every_batch_of_the_job() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b", "c"], "1K");
+        submit_job(&app, &created.job.id, small_batches()).await.unwrap();
+        let submitted = get_job(app.clone(), created.job.id.clone()).unwrap();
+
+        cancel_batch(app.clone(), submitted.job.batch_job_name.unwrap()).await.unwrap();
+
+        let state = mock.state.lock().unwrap();
+        for batch in &submitted.batches {
+            assert_eq!(
+                state.batch_state(&batch.batch_name).as_deref(),
+                Some("JOB_STATE_CANCELLED")
+            );
+        }
+        drop(state);
+        let done = get_job(app.clone(), created.job.id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Cancelled);
+        assert!(done.batches.iter().all(|b| b.status == JobStatus::Cancelled));
+    }
+
+    #[tokio::test]
+    async fn cancel_marks_job_cancelled() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["slow"], "1K");
+        submit_batch(app.clone(), created.job.id.clone(), None).await.unwrap();
+        let batch_name = get_job(app.clone(), created.job.id.clone())
+            .unwrap()
+            .job
+            .batch_job_name
+            .unwrap();
+
+        cancel_batch(app.clone(), batch_name.clone()).await.unwrap();
+
+        assert_eq!(
+            mock.state.lock().unwrap().batch_state(&batch_name).as_deref(),
+            Some("JOB_STATE_CANCELLED")
+        );
+        let job = get_job(app.clone(), created.job.id).unwrap().job;
+        assert_eq!(job.status, JobStatus::Cancelled);
+
+        // Gemini refuses to cancel a finished batch; that's not an error.
+        cancel_batch(app.clone(), batch_name).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancel_keeps_going_past_a_batch_that_cannot_be_cancelled() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b", "c"], "1K");
+        let job_id = created.job.id.clone();
+        submit_job(&app, &job_id, small_batches()).await.unwrap();
+        let submitted = get_job(app.clone(), job_id.clone()).unwrap();
+        let stuck = submitted.batches[0].batch_name.clone();
+        mock.state.lock().unwrap().refuse_cancel.insert(stuck.clone());
+
+        let err = cancel_batch(app.clone(), submitted.job.batch_job_name.clone().unwrap())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "api");
+
+        let after = get_job(app.clone(), job_id.clone()).unwrap();
+        assert_eq!(after.job.status, JobStatus::Processing);
+        for batch in &after.batches {
+            let expected =
+                if batch.batch_name == stuck { JobStatus::Processing } else { JobStatus::Cancelled };
+            assert_eq!(batch.status, expected, "{}", batch.batch_name);
+        }
+
+        // Nor is the job deleted while its batch may still be running.
+        let err = crate::commands::delete_job(app.clone(), job_id.clone()).await.unwrap_err();
+        assert_eq!(err.kind(), "api");
+        assert!(get_job(app.clone(), job_id.clone()).is_ok());
+
+        mock.state.lock().unwrap().refuse_cancel.clear();
+        crate::commands::delete_job(app.clone(), job_id.clone()).await.unwrap();
+        assert!(get_job(app.clone(), job_id).is_err());
+    }
+
+    #[tokio::test]
+    async fn poll_batch_settles_failed_batch() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_FAILED"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let created = t2i_job(&app, &["a", "b"], "1K");
+        submit_batch(app.clone(), created.job.id.clone(), None).await.unwrap();
+        let batch_name = get_job(app.clone(), created.job.id.clone())
+            .unwrap()
+            .job
+            .batch_job_name
+            .unwrap();
+
+        let status = poll_batch(app.clone(), batch_name).await.unwrap();
+        assert_eq!(status.state, BatchState::Failed);
+
+        let done = get_job(app.clone(), created.job.id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Failed);
+        assert_eq!(done.job.failed_items, 2);
+        for item in &done.items {
+            assert_eq!(item.status, ItemStatus::Failed);
+            assert_eq!(item.error.as_deref(), Some("Batch failed"));
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_without_valid_key_surfaces_auth_error() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        save_config(app.clone(), "wrong".to_string()).unwrap();
+
+        let created = t2i_job(&app, &["x"], "1K");
+        let err = submit_batch(app.clone(), created.job.id.clone(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "auth");
+        assert!(err.to_string().contains("API key not valid"), "{}", err);
+        let job = get_job(app.clone(), created.job.id).unwrap().job;
+        assert_eq!(job.status, JobStatus::Pending);
+    }
+
+    fn i2i_job(app: &AppHandle<tauri::test::MockRuntime>, image_paths: Vec<String>) -> String {
+        create_i2i_job(
+            app.clone(),
+            CreateI2IJobRequest {
+                prompt: "make it blue".to_string(),
+                image_paths,
+                output_size: "1K".to_string(),
+                temperature: 1.0,
+                aspect_ratio: "1:1".to_string(),
+                model: None,
+            },
+        )
+        .unwrap()
+        .job
+        .id
+    }
+
+    fn file_uploads(mock: &MockGemini) -> usize {
+        let state = mock.state.lock().unwrap();
+        state
+            .requests
+            .iter()
+            .filter(|r| r.as_str() == "POST /upload/v1beta/files")
+            .count()
+    }
+
+    fn write_upload(app: &AppHandle<tauri::test::MockRuntime>, name: &str) -> String {
+        let path = crate::paths::get_uploads_dir(app).unwrap().join(name);
+        fs::write(&path, crate::gemini::mock::png(4, 4, [0, 0, 255])).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn i2i_inputs_are_uploaded_once_and_referenced_by_uri() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let image = write_upload(&app, "source.png");
+
+        let job_id = i2i_job(&app, vec![image.clone(), image.clone()]);
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        // One upload for the shared image, one for the JSONL.
+        assert_eq!(file_uploads(&mock), 2);
+
+        let batch_name = get_job(app.clone(), job_id).unwrap().job.batch_job_name.unwrap();
+        let requests = mock.state.lock().unwrap().batch_requests(&batch_name);
+        assert_eq!(requests.len(), 2);
+        for line in &requests {
+            let parts = line["request"]["contents"][0]["parts"].as_array().unwrap();
+            assert!(parts.iter().all(|p| p.get("inline_data").is_none()));
+            let file_data = parts.iter().find_map(|p| p.get("file_data")).unwrap();
+            assert_eq!(file_data["mime_type"], "image/png");
+            assert!(file_data["file_uri"].as_str().unwrap().contains("/v1beta/files/"));
+        }
+
+        // A re-run of the same image reuses the cached URI.
+        let rerun = i2i_job(&app, vec![image]);
+        submit_batch(app.clone(), rerun, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 3);
+    }
+
+    #[tokio::test]
+    async fn i2i_input_close_to_expiry_is_uploaded_again() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let image = write_upload(&app, "source.png");
+
+        let first = i2i_job(&app, vec![image.clone()]);
+        submit_batch(app.clone(), first, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 2);
+
+        {
+            let db = get_db(&app);
+            let conn = db.conn.lock().unwrap();
+            let soon = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+            conn.execute("UPDATE gemini_files SET expires_at = ?1", params![soon]).unwrap();
+        }
+        let second = i2i_job(&app, vec![image]);
+        submit_batch(app.clone(), second, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 4);
+    }
+
+    #[tokio::test]
+    async fn saving_a_new_key_forgets_uploaded_files() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let image = write_upload(&app, "source.png");
+
+        let job_id = i2i_job(&app, vec![image]);
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap();
+        // A key can't change under a job that's still running.
+        let batch_name = get_job(app.clone(), job_id).unwrap().job.batch_job_name.unwrap();
+        cancel_batch(app.clone(), batch_name).await.unwrap();
+        crate::commands::save_config(app.clone(), crate::gemini::mock::MOCK_API_KEY.to_string()).unwrap();
+
+        let db = get_db(&app);
+        let conn = db.conn.lock().unwrap();
+        let cached: i64 = conn
+            .query_row("SELECT COUNT(*) FROM gemini_files", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cached, 0);
+    }
+
+    #[tokio::test]
+    async fn uploaded_files_are_cached_per_profile() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let image = write_upload(&app, "source.png");
+
+        let first = i2i_job(&app, vec![image.clone()]);
+        submit_batch(app.clone(), first, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 2);
+
+        // Another profile's project can't see the first one's uploads.
+        let original = crate::commands::list_profiles(app.clone()).unwrap().remove(0).id;
+        let other = crate::commands::add_profile(
+            app.clone(),
+            "Other".to_string(),
+            crate::gemini::mock::MOCK_API_KEY.to_string(),
+        )
+        .unwrap();
+        crate::commands::set_active_profile(app.clone(), other.id).unwrap();
+        let second = i2i_job(&app, vec![image.clone()]);
+        submit_batch(app.clone(), second, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 4);
+
+        // Switching back finds the first profile's uploads still cached.
+        crate::commands::set_active_profile(app.clone(), original).unwrap();
+        let third = i2i_job(&app, vec![image]);
+        submit_batch(app.clone(), third, None).await.unwrap();
+        assert_eq!(file_uploads(&mock), 5);
+    }
+
+    #[tokio::test]
+    async fn submit_uses_the_job_model() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        save_config_value(&app, "default_model", "gemini-2.5-flash-image");
+
+        let defaulted = t2i_job(&app, &["a"], "1K").job;
+        assert_eq!(defaulted.model, "gemini-2.5-flash-image");
+        let chosen = create_t2i_job(
+            app.clone(),
+            CreateT2IJobRequest {
+                model: Some(crate::gemini::DEFAULT_MODEL.to_string()),
+                ..t2i_request(&["b"], "1K")
+            },
+        )
+        .unwrap()
+        .job;
+        submit_batch(app.clone(), defaulted.id, None).await.unwrap();
+        submit_batch(app.clone(), chosen.id, None).await.unwrap();
+
+        let state = mock.state.lock().unwrap();
+        let creates: Vec<&String> = state
+            .requests
+            .iter()
+            .filter(|r| r.ends_with(":batchGenerateContent"))
+            .collect();
+        assert_eq!(
+            creates,
+            vec![
+                "POST /v1beta/models/gemini-2.5-flash-image:batchGenerateContent",
+                &format!("POST /v1beta/models/{}:batchGenerateContent", crate::gemini::DEFAULT_MODEL),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_rejects_malformed_model() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let err = create_t2i_job(
+            app.clone(),
+            CreateT2IJobRequest {
+                model: Some("../files/secret".to_string()),
+                ..t2i_request(&["a"], "1K")
+            },
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&err, AppError::Validation(m) if m.starts_with("Invalid model")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn diagnose_key_reports_reason_and_model_access() {
+        let mock = MockGemini::start().await;
+        let model = "gemini-3-pro-image-preview";
+        let client = |key: &str| {
+            GeminiClient::new(reqwest::Client::new(), mock.base_url.clone(), model, key)
+        };
+        mock.state
+            .lock()
+            .unwrap()
+            .denied_models
+            .insert("gemini-2.5-flash-image".into(), "SERVICE_DISABLED".into());
+
+        let ok = diagnose_key(&client(gemini::mock::MOCK_API_KEY), model).await;
+        assert!(ok.valid && ok.reachable);
+        assert_eq!((ok.http_status, ok.reason, ok.message), (Some(200), None, None));
+        assert_eq!(ok.models.len(), pricing::PRICING.len());
+        assert_eq!(ok.models[0].model, model);
+        let denied = ok
+            .models
+            .iter()
+            .find(|m| m.model == "gemini-2.5-flash-image")
+            .unwrap();
+        assert!(!denied.accessible);
+        assert_eq!(denied.http_status, Some(403));
+        assert_eq!(denied.reason.as_deref(), Some("SERVICE_DISABLED"));
+
+        let bad = diagnose_key(&client("wrong"), model).await;
+        assert!(!bad.valid && bad.reachable);
+        assert_eq!(bad.http_status, Some(400));
+        assert_eq!(bad.reason.as_deref(), Some("API_KEY_INVALID"));
+        assert!(bad.message.unwrap().contains("API key not valid"));
+        assert!(bad.models.iter().all(|m| !m.accessible));
+    }
+
+    #[tokio::test]
+    async fn diagnose_key_reports_an_unreachable_api_without_failing() {
+        // Bind and drop a listener for a port nothing answers on.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let client = GeminiClient::new(reqwest::Client::new(), base_url, "m", "key");
+
+        let result = diagnose_key(&client, "gemini-2.5-flash-image").await;
+        assert!(!result.valid && !result.reachable);
+        assert_eq!((result.http_status, result.reason), (None, None));
+        assert!(result.message.is_some());
+    }
+
+    fn save_config_value<R: Runtime>(app: &AppHandle<R>, key: &str, value: &str) {
+        let db = get_db(app);
+        let conn = db.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO config (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )
+        .unwrap();
+    }
 }
@@ -0,0 +1,238 @@
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::models::{ItemStatus, JobDelivery, JobStatus, SpendSummary};
+use crate::pricing;
+use crate::settings::Settings;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+
+#[tauri::command]
+pub fn get_spend_summary<R: Runtime>(app: AppHandle<R>) -> AppResult<SpendSummary> {
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    spend_summary(&conn, &Local::now())
+}
+
+/// Refuses to submit `job_id` when the estimate for its pending and
+/// failed items is more than what is left of the daily or monthly
+/// budget. Jobs on a model without pricing can't be estimated and are
+/// let through.
+pub(crate) fn check_budget<R: Runtime>(
+    app: &AppHandle<R>,
+    job_id: &str,
+    delivery: JobDelivery,
+) -> AppResult<()> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    let Some(estimate) = submission_estimate(&conn, job_id, delivery)? else {
+        return Ok(());
+    };
+    let summary = spend_summary(&conn, &Local::now())?;
+    let periods = [
+        ("daily", summary.daily_remaining),
+        ("monthly", summary.monthly_remaining),
+    ];
+    for (period, remaining) in periods {
+        if let Some(remaining) = remaining.filter(|r| estimate > r + 1e-9) {
+            return Err(AppError::Budget(format!(
+                "This job is estimated at ${:.2}, more than the ${:.2} left in the {} budget.",
+                estimate, remaining, period
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Expected cost of submitting the items of `job_id` that a submit would
+/// send, at the rate of the `delivery` they are about to be sent with, or
+/// `None` when the job's model has no pricing.
+fn submission_estimate(
+    conn: &Connection,
+    job_id: &str,
+    delivery: JobDelivery,
+) -> AppResult<Option<f64>> {
+    let (model, output_size): (String, String) = conn
+        .query_row(
+            "SELECT model, output_size FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Job not found: {}", job_id)))?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM job_items WHERE job_id = ?1 AND status IN (?2, ?3)",
+        params![job_id, ItemStatus::Pending, ItemStatus::Failed],
+        |row| row.get(0),
+    )?;
+    Ok(pricing::model_pricing(&model)
+        .and_then(|p| p.image_cost(&output_size, delivery))
+        .map(|cost| cost * count as f64))
+}
+
+/// Spend in the day and month containing `now`, in `now`'s time zone.
+pub(crate) fn spend_summary<Tz: TimeZone>(
+    conn: &Connection,
+    now: &DateTime<Tz>,
+) -> AppResult<SpendSummary> {
+    let today = now.date_naive();
+    let spent_today = spent_since(conn, &period_start(now, today))?;
+    let month = today.with_day(1).unwrap_or(today);
+    let spent_this_month = spent_since(conn, &period_start(now, month))?;
+    let in_flight = in_flight_estimate(conn)?;
+
+    let settings = Settings::load(conn)?;
+    let (daily_budget, monthly_budget) = (settings.daily_budget, settings.monthly_budget);
+    let remaining =
+        |budget: Option<f64>, spent: f64| budget.map(|b| (b - spent - in_flight).max(0.0));
+    Ok(SpendSummary {
+        spent_today,
+        spent_this_month,
+        in_flight,
+        daily_budget,
+        monthly_budget,
+        daily_remaining: remaining(daily_budget, spent_today),
+        monthly_remaining: remaining(monthly_budget, spent_this_month),
+    })
+}
+
+/// Midnight at the start of `date` in `now`'s time zone, as the UTC
+/// RFC 3339 string charge timestamps are stored in. Falls back to the
+/// UTC midnight if a DST gap skips the local one.
+fn period_start<Tz: TimeZone>(now: &DateTime<Tz>, date: NaiveDate) -> String {
+    let midnight = date.and_time(NaiveTime::MIN);
+    now.timezone()
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+        .to_rfc3339()
+}
+
+/// What was billed since `since`, going by when each response's charge
+/// was recorded: an item's cost adds up over attempts made on different
+/// days, and its `updated_at` moves whenever it is retried or settled.
+fn spent_since(conn: &Connection, since: &str) -> AppResult<f64> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(SUM(cost), 0) FROM job_item_charges WHERE recorded_at >= ?1",
+        params![since],
+        |row| row.get(0),
+    )?)
+}
+
+/// Estimated cost of the still-pending items of submitted jobs, which
+/// Gemini will bill once their batches finish.
+fn in_flight_estimate(conn: &Connection) -> AppResult<f64> {
+    let mut stmt = conn.prepare(
+        "SELECT j.model, j.output_size, i.delivery, COUNT(*) FROM job_items i
+         JOIN jobs j ON j.id = i.job_id
+         WHERE j.status IN (?1, ?2) AND i.status = ?3
+         GROUP BY j.id, i.delivery",
+    )?;
+    let rows = stmt
+        .query_map(
+            params![
+                JobStatus::Processing,
+                JobStatus::Downloading,
+                ItemStatus::Pending
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, JobDelivery>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .iter()
+        .filter_map(|(model, size, delivery, count)| {
+            let cost = pricing::model_pricing(model)?.image_cost(size, *delivery)?;
+            Some(cost * *count as f64)
+        })
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{download_results, get_job, poll_batch, submit_batch, update_settings};
+    use crate::gemini::mock::{t2i_job, MockGemini};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn period_start_is_local_midnight_in_utc() {
+        let tz = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        let now = tz.with_ymd_and_hms(2026, 3, 15, 1, 30, 0).unwrap();
+        let today = now.date_naive();
+        assert_eq!(period_start(&now, today), "2026-03-14T22:00:00+00:00");
+        let month = today.with_day(1).unwrap();
+        assert_eq!(period_start(&now, month), "2026-02-28T22:00:00+00:00");
+    }
+
+    #[test]
+    fn spend_counts_each_charge_on_the_day_it_was_recorded() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::run_migrations(&conn).unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO jobs (id, prompt) VALUES ('j1', 'p');
+            INSERT INTO job_items (id, job_id, cost, updated_at)
+                VALUES ('i1', 'j1', 0.5, '2026-03-15T09:00:00+00:00');
+            INSERT INTO job_item_charges (source, item_id, cost, recorded_at) VALUES
+                ('batches/old#1', 'i1', 0.2, '2026-02-20T09:00:00+00:00'),
+                ('batches/new#1', 'i1', 0.3, '2026-03-15T08:00:00+00:00');
+            "#,
+        )
+        .unwrap();
+
+        // The item was retried today, but last month's charge stays there.
+        assert!(close(spent_since(&conn, "2026-03-15T00:00:00+00:00").unwrap(), 0.3));
+        assert!(close(spent_since(&conn, "2026-02-01T00:00:00+00:00").unwrap(), 0.5));
+    }
+
+    #[tokio::test]
+    async fn submit_over_budget_is_refused_unless_overridden() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a", "b"], "1K").job.id;
+        let budget = serde_json::json!({"daily_budget": 0.05});
+        update_settings(app.clone(), budget.as_object().unwrap().clone()).unwrap();
+
+        // Two 1K images at $0.0336 each.
+        let err = submit_batch(app.clone(), job_id.clone(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), "budget");
+        assert!(err.to_string().contains("$0.07"), "{}", err);
+        assert!(mock.state.lock().unwrap().requests.is_empty());
+
+        submit_batch(app.clone(), job_id.clone(), Some(true))
+            .await
+            .unwrap();
+        let summary = get_spend_summary(app.clone()).unwrap();
+        assert!(close(summary.in_flight, 0.0672));
+        assert_eq!(summary.daily_remaining, Some(0.0));
+        assert_eq!(summary.monthly_budget, None);
+
+        let batch_name = get_job(app.clone(), job_id.clone())
+            .unwrap()
+            .job
+            .batch_job_name
+            .unwrap();
+        poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+        download_results(app.clone(), batch_name, job_id)
+            .await
+            .unwrap();
+        let summary = get_spend_summary(app.clone()).unwrap();
+        assert!(close(summary.in_flight, 0.0));
+        assert!(close(summary.spent_today, 0.06722));
+        assert!(close(summary.spent_this_month, 0.06722));
+    }
+}
@@ -1,35 +1,34 @@
+use crate::commands::profiles::{
+    active_profile_id, check_no_jobs_in_flight, profile_secret, save_active_key,
+};
 use crate::db::get_db;
-use crate::models::ConfigStatus;
+use crate::error::AppResult;
+use crate::gemini::{validate_model, GeminiClient};
+use crate::http::{build_client, HttpClient};
+use crate::models::{ConfigStatus, ImageModel};
+use crate::secrets::get_secrets;
+use crate::settings::{load_settings, Settings};
 use rusqlite::params;
-use std::collections::HashMap;
-use tauri::{AppHandle, Manager};
-
-const API_KEY_KEY: &str = "gemini_api_key";
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Manager, Runtime};
 
+/// Status of the active profile's key.
 #[tauri::command]
-pub fn get_config(app: AppHandle) -> Result<ConfigStatus, String> {
-    let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    let result: Result<String, _> = conn.query_row(
-        "SELECT value FROM config WHERE key = ?1",
-        params![API_KEY_KEY],
-        |row| row.get(0),
-    );
-
-    match result {
-        Ok(key) if !key.is_empty() => {
-            // Mask the key: show first 2 chars and last 3 chars
-            let masked = if key.len() > 8 {
-                format!("{}...{}", &key[..2], &key[key.len() - 3..])
-            } else {
-                "****".to_string()
-            };
-            Ok(ConfigStatus {
-                has_key: true,
-                masked: Some(masked),
-            })
-        }
+pub fn get_config<R: Runtime>(app: AppHandle<R>) -> AppResult<ConfigStatus> {
+    let active = {
+        let db = get_db(&app);
+        let conn = db.conn.lock()?;
+        active_profile_id(&conn)?
+    };
+    let key = match active {
+        Some(id) => get_secrets(&app).get(&profile_secret(&id))?,
+        None => None,
+    };
+    match key {
+        Some(key) if !key.is_empty() => Ok(ConfigStatus {
+            has_key: true,
+            masked: Some(mask_key(&key)),
+        }),
         _ => Ok(ConfigStatus {
             has_key: false,
             masked: None,
@@ -37,137 +36,220 @@ pub fn get_config(app: AppHandle) -> Result<ConfigStatus, String> {
     }
 }
 
+/// Mask a key for display: show first 2 chars and last 3 chars.
+pub(crate) fn mask_key(key: &str) -> String {
+    if key.len() > 8 {
+        format!("{}...{}", &key[..2], &key[key.len() - 3..])
+    } else {
+        "****".to_string()
+    }
+}
+
+/// Sets the active profile's key, creating a profile if there is none.
+/// Refused while jobs submitted with the active profile are in flight.
 #[tauri::command]
-pub fn save_config(app: AppHandle, api_key: String) -> Result<(), String> {
+pub fn save_config<R: Runtime>(app: AppHandle<R>, api_key: String) -> AppResult<()> {
     let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    conn.execute(
-        "INSERT INTO config (key, value) VALUES (?1, ?2)
-         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-        params![API_KEY_KEY, api_key],
-    )
-    .map_err(|e| e.to_string())?;
+    let conn = db.conn.lock()?;
+    let id = save_active_key(&conn, get_secrets(&app), &api_key)?;
+    forget_uploaded_files(&conn, &id)?;
 
     Ok(())
 }
 
+/// Removes the active profile's key; the profile itself stays. Refused
+/// while jobs submitted with it are in flight.
 #[tauri::command]
-pub fn delete_config(app: AppHandle) -> Result<(), String> {
+pub fn delete_config<R: Runtime>(app: AppHandle<R>) -> AppResult<()> {
     let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    conn.execute("DELETE FROM config WHERE key = ?1", params![API_KEY_KEY])
-        .map_err(|e| e.to_string())?;
+    let conn = db.conn.lock()?;
+    if let Some(id) = active_profile_id(&conn)? {
+        check_no_jobs_in_flight(&conn, &id, "remove the key")?;
+        get_secrets(&app).delete(&profile_secret(&id))?;
+        forget_uploaded_files(&conn, &id)?;
+    }
 
     Ok(())
 }
 
-const ALLOWED_SETTING_KEYS: &[&str] = &[
-    "default_output_size",
-    "default_aspect_ratio",
-    "default_temperature",
-    "results_dir",
-    "uploads_dir",
-];
+/// Files API uploads belong to the key's project (and endpoint) that
+/// created them, so a profile's cached URIs are useless once its key
+/// changes.
+pub(crate) fn forget_uploaded_files(
+    conn: &rusqlite::Connection,
+    profile_id: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "DELETE FROM gemini_files WHERE profile_id = ?1",
+        params![profile_id],
+    )?;
+    Ok(())
+}
 
-#[tauri::command]
-pub fn get_setting(app: AppHandle, key: String) -> Result<Option<String>, String> {
-    if !ALLOWED_SETTING_KEYS.contains(&key.as_str()) {
-        return Err(format!("Setting key '{}' is not allowed", key));
-    }
-    let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let value = conn
-        .query_row(
-            "SELECT value FROM config WHERE key = ?1",
-            params![key],
-            |row| row.get::<_, String>(0),
-        )
-        .ok();
-    Ok(value)
+/// Every profile's uploads, for when the endpoint they were made on changes.
+fn forget_all_uploaded_files(conn: &rusqlite::Connection) -> AppResult<()> {
+    conn.execute("DELETE FROM gemini_files", [])?;
+    Ok(())
 }
 
 #[tauri::command]
-pub fn save_setting(app: AppHandle, key: String, value: String) -> Result<(), String> {
-    if !ALLOWED_SETTING_KEYS.contains(&key.as_str()) {
-        return Err(format!("Setting key '{}' is not allowed", key));
-    }
-    let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.execute(
-        "INSERT INTO config (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-        params![key, value],
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(())
+pub fn get_settings<R: Runtime>(app: AppHandle<R>) -> AppResult<Settings> {
+    load_settings(&app)
 }
 
+/// Applies `changes`, keyed by `Settings` field with `null` restoring a
+/// default, and returns the saved settings. Nothing is saved unless all
+/// of them are valid, the folders writable and, for network settings,
+/// the HTTP client builds.
 #[tauri::command]
-pub fn get_all_settings(app: AppHandle) -> Result<HashMap<String, String>, String> {
+pub fn update_settings<R: Runtime>(
+    app: AppHandle<R>,
+    changes: Map<String, Value>,
+) -> AppResult<Settings> {
     let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn
-        .prepare("SELECT key, value FROM config")
-        .map_err(|e| e.to_string())?;
-    let map = stmt
-        .query_map([], |row| {
-            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-        })
-        .map_err(|e| e.to_string())?
-        .filter_map(|r| r.ok())
-        .filter(|(k, _): &(String, String)| ALLOWED_SETTING_KEYS.contains(&k.as_str()))
-        .collect();
-    Ok(map)
+    let conn = db.conn.lock()?;
+    let current = Settings::load(&conn)?;
+    let next = current.apply(changes)?;
+    next.validate()?;
+    let http = if next.same_network(&current) {
+        None
+    } else {
+        Some(build_client(&next)?)
+    };
+    next.save(&conn)?;
+    if next.gemini_base_url != current.gemini_base_url {
+        forget_all_uploaded_files(&conn)?;
+    }
+    if let Some(http) = http {
+        app.state::<HttpClient>().replace(http)?;
+    }
+    Ok(next)
 }
 
 #[tauri::command]
-pub fn get_default_results_dir(app: AppHandle) -> Result<String, String> {
-    let pictures = app.path().picture_dir().map_err(|e| e.to_string())?;
+pub fn get_default_results_dir<R: Runtime>(app: AppHandle<R>) -> AppResult<String> {
+    let pictures = app.path().picture_dir()?;
     let dir = pictures.join("Nana Studio");
     Ok(dir.to_string_lossy().to_string())
 }
 
+/// Image-capable models available to the stored key, for the model picker.
+#[tauri::command]
+pub async fn list_models<R: Runtime>(app: AppHandle<R>) -> AppResult<Vec<ImageModel>> {
+    let models = GeminiClient::from_app(&app)?.list_models().await?;
+    Ok(image_models(&models))
+}
+
+/// Keeps the Gemini image models: the API has no output-modality field,
+/// so go by the `-image` naming every such model uses, and require
+/// `generateContent` (Imagen's `predict`-only models don't fit our
+/// requests).
+fn image_models(models: &[Value]) -> Vec<ImageModel> {
+    models
+        .iter()
+        .filter_map(|m| {
+            let id = m["name"].as_str()?.strip_prefix("models/")?;
+            let methods: Vec<&str> = m["supportedGenerationMethods"]
+                .as_array()?
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            if !id.contains("-image") || !methods.contains(&"generateContent") {
+                return None;
+            }
+            validate_model(id).ok()?;
+            Some(ImageModel {
+                id: id.to_string(),
+                display_name: m["displayName"].as_str().unwrap_or(id).to_string(),
+                description: m["description"].as_str().map(str::to_string),
+                supports_batch: methods.contains(&"batchGenerateContent"),
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_allowed_setting_keys_contains_expected() {
-        assert!(ALLOWED_SETTING_KEYS.contains(&"default_output_size"));
-        assert!(ALLOWED_SETTING_KEYS.contains(&"default_aspect_ratio"));
-        assert!(ALLOWED_SETTING_KEYS.contains(&"default_temperature"));
-        assert!(ALLOWED_SETTING_KEYS.contains(&"results_dir"));
-        assert!(ALLOWED_SETTING_KEYS.contains(&"uploads_dir"));
+    fn image_models_keeps_generate_content_image_models() {
+        let models = vec![
+            serde_json::json!({
+                "name": "models/gemini-3.1-flash-image-preview",
+                "displayName": "Nano Banana 2",
+                "supportedGenerationMethods": ["generateContent", "batchGenerateContent"],
+            }),
+            serde_json::json!({
+                "name": "models/gemini-2.5-flash",
+                "supportedGenerationMethods": ["generateContent"],
+            }),
+            serde_json::json!({
+                "name": "models/imagen-4.0-generate-001",
+                "supportedGenerationMethods": ["predict"],
+            }),
+        ];
+        assert_eq!(
+            image_models(&models),
+            vec![ImageModel {
+                id: "gemini-3.1-flash-image-preview".to_string(),
+                display_name: "Nano Banana 2".to_string(),
+                description: None,
+                supports_batch: true,
+            }]
+        );
     }
 
-    #[test]
-    fn test_allowed_setting_keys_rejects_api_key() {
-        assert!(!ALLOWED_SETTING_KEYS.contains(&"gemini_api_key"));
+    #[tokio::test]
+    async fn list_models_follows_pages() {
+        let mock = crate::gemini::mock::MockGemini::start().await;
+        let test_app = mock.app();
+        let ids: Vec<String> = list_models(test_app.handle())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![crate::gemini::DEFAULT_MODEL, "gemini-2.5-flash-image"]
+        );
     }
 
-    #[test]
-    fn test_allowed_setting_keys_rejects_arbitrary() {
-        assert!(!ALLOWED_SETTING_KEYS.contains(&"admin"));
-        assert!(!ALLOWED_SETTING_KEYS.contains(&"password"));
-        assert!(!ALLOWED_SETTING_KEYS.contains(&""));
-        assert!(!ALLOWED_SETTING_KEYS.contains(&"DROP TABLE config"));
-    }
+    #[tokio::test]
+    async fn update_settings_saves_only_valid_changes() {
+        let mock = crate::gemini::mock::MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let changes = |value: Value| value.as_object().unwrap().clone();
 
-    #[test]
-    fn test_get_all_settings_filter_excludes_api_key() {
-        let rows = vec![
-            ("gemini_api_key".to_string(), "secret".to_string()),
-            ("default_output_size".to_string(), "2K".to_string()),
-            ("unknown".to_string(), "x".to_string()),
-        ];
-        let filtered: HashMap<String, String> = rows
-            .into_iter()
-            .filter(|(k, _)| ALLOWED_SETTING_KEYS.contains(&k.as_str()))
-            .collect();
-        assert!(!filtered.contains_key("gemini_api_key"));
-        assert!(!filtered.contains_key("unknown"));
-        assert_eq!(filtered.get("default_output_size"), Some(&"2K".to_string()));
+        let err = update_settings(
+            app.clone(),
+            changes(serde_json::json!({"default_temperature": 0.5, "default_output_size": "3K"})),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), "validation");
+        assert_eq!(get_settings(app.clone()).unwrap().default_temperature, 1.0);
+
+        let ca = test_app.dir.join("corp.pem");
+        std::fs::write(&ca, "not a certificate").unwrap();
+        let err = update_settings(
+            app.clone(),
+            changes(serde_json::json!({"ca_bundle_path": ca})),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("CA bundle"), "{}", err);
+        assert_eq!(get_settings(app.clone()).unwrap().ca_bundle_path, None);
+
+        // The rebuilt client is the one later requests use.
+        let saved = update_settings(
+            app.clone(),
+            changes(serde_json::json!({"connect_timeout_secs": 5, "no_proxy": "127.0.0.1"})),
+        )
+        .unwrap();
+        assert_eq!(saved.connect_timeout_secs, 5);
+        assert_eq!(get_settings(app.clone()).unwrap(), saved);
+        assert!(!list_models(app.clone()).await.unwrap().is_empty());
     }
 
     #[test]
@@ -1,18 +1,24 @@
 use base64::{engine::general_purpose::STANDARD, Engine};
+use crate::error::{AppError, AppResult};
 use crate::models::UploadedFile;
 use crate::paths::{get_uploads_dir, get_results_dir, mime_from_ext};
 use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Manager, Runtime};
 use uuid::Uuid;
 
 const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB
 const ALLOWED_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
 
 #[tauri::command]
-pub fn upload_images(app: AppHandle, files: Vec<String>) -> Result<Vec<UploadedFile>, String> {
+pub fn upload_images<R: Runtime>(
+    app: AppHandle<R>,
+    files: Vec<String>,
+) -> AppResult<Vec<UploadedFile>> {
     // Enforce max 20 files before processing
     if files.len() > 20 {
-        return Err("Maximum 20 files allowed per batch".to_string());
+        return Err(AppError::Validation(
+            "Maximum 20 files allowed per batch".to_string(),
+        ));
     }
 
     let uploads_dir = get_uploads_dir(&app)?;
@@ -29,7 +35,7 @@ pub fn upload_images(app: AppHandle, files: Vec<String>) -> Result<Vec<UploadedF
                 .file_name()
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_else(|| "(invalid)".to_string());
-            return Err(format!("File not found: {}", name));
+            return Err(AppError::NotFound(format!("File not found: {}", name)));
         }
 
         // Validate extension
@@ -40,19 +46,19 @@ pub fn upload_images(app: AppHandle, files: Vec<String>) -> Result<Vec<UploadedF
             .unwrap_or_default();
 
         if !ALLOWED_EXTENSIONS.contains(&ext.as_str()) {
-            return Err(format!(
+            return Err(AppError::Validation(format!(
                 "Invalid file type: {}. Allowed: JPEG, PNG, WebP, GIF",
                 ext
-            ));
+            )));
         }
 
         // Validate file size
-        let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+        let metadata = std::fs::metadata(&path)?;
         if metadata.len() > MAX_FILE_SIZE {
-            return Err(format!(
+            return Err(AppError::Validation(format!(
                 "File too large: {}. Max size: 10MB",
                 path.file_name().unwrap_or_default().to_string_lossy()
-            ));
+            )));
         }
 
         // Copy to uploads directory
@@ -60,7 +66,7 @@ pub fn upload_images(app: AppHandle, files: Vec<String>) -> Result<Vec<UploadedF
         let new_filename = format!("{}.{}", id, ext);
         let dest_path = uploads_dir.join(&new_filename);
 
-        std::fs::copy(&path, &dest_path).map_err(|e| e.to_string())?;
+        std::fs::copy(&path, &dest_path)?;
 
         let name = path
             .file_name()
@@ -78,7 +84,7 @@ pub fn upload_images(app: AppHandle, files: Vec<String>) -> Result<Vec<UploadedF
 }
 
 #[tauri::command]
-pub fn get_image(app: AppHandle, path: String) -> Result<String, String> {
+pub fn get_image<R: Runtime>(app: AppHandle<R>, path: String) -> AppResult<String> {
     let path = PathBuf::from(&path);
 
     if !path.exists() {
@@ -86,15 +92,15 @@ pub fn get_image(app: AppHandle, path: String) -> Result<String, String> {
             .file_name()
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_else(|| "(invalid)".to_string());
-        return Err(format!("Image not found: {}", name));
+        return Err(AppError::NotFound(format!("Image not found: {}", name)));
     }
 
     // Validate path is within allowed directories (default + custom configured)
-    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    let canonical = path.canonicalize().map_err(|e| e.to_string())?;
+    let app_data_dir = app.path().app_data_dir()?;
+    let canonical = path.canonicalize()?;
     let uploads_dir = get_uploads_dir(&app)?;
     let results_dir = get_results_dir(&app)?;
-    let pictures_dir = app.path().picture_dir().map_err(|e| e.to_string())?;
+    let pictures_dir = app.path().picture_dir()?;
     let mut allowed = vec![
         app_data_dir.join("uploads"),
         app_data_dir.join("results"),
@@ -111,11 +117,13 @@ pub fn get_image(app: AppHandle, path: String) -> Result<String, String> {
             .map(|cd| canonical.starts_with(cd))
             .unwrap_or(false)
     }) {
-        return Err("Access denied: path outside allowed directories".to_string());
+        return Err(AppError::Validation(
+            "Access denied: path outside allowed directories".to_string(),
+        ));
     }
 
     // Read file and encode as base64
-    let data = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let data = std::fs::read(&path)?;
     let base64 = STANDARD.encode(&data);
 
     // Determine mime type
@@ -131,18 +139,14 @@ pub fn get_image(app: AppHandle, path: String) -> Result<String, String> {
 }
 
 #[tauri::command]
-pub fn delete_upload(app: AppHandle, path: String) -> Result<(), String> {
+pub fn delete_upload<R: Runtime>(app: AppHandle<R>, path: String) -> AppResult<()> {
     let uploads_dir = get_uploads_dir(&app)?;
-    let default_uploads = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())?
-        .join("uploads");
+    let default_uploads = app.path().app_data_dir()?.join("uploads");
 
     let file_path = PathBuf::from(&path);
 
     // Security: canonicalize paths to prevent symlink bypass
-    let canonical_path = file_path.canonicalize().map_err(|e| e.to_string())?;
+    let canonical_path = file_path.canonicalize()?;
     let mut allowed_dirs = vec![uploads_dir];
     if !allowed_dirs.contains(&default_uploads) {
         allowed_dirs.push(default_uploads);
@@ -153,11 +157,13 @@ pub fn delete_upload(app: AppHandle, path: String) -> Result<(), String> {
             .unwrap_or(false)
     });
     if !in_allowed {
-        return Err("Cannot delete files outside uploads directory".to_string());
+        return Err(AppError::Validation(
+            "Cannot delete files outside uploads directory".to_string(),
+        ));
     }
 
     if file_path.exists() {
-        std::fs::remove_file(&file_path).map_err(|e| e.to_string())?;
+        std::fs::remove_file(&file_path)?;
     }
 
     Ok(())
@@ -0,0 +1,304 @@
+// src-tauri/src/commands/interactive.rs
+use crate::commands::{
+    active_profile_id, check_budget, fail_item, input_file_part, request_parts, save_item_result,
+    settle_job, transition_job,
+};
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::events;
+use crate::gemini::{self, GeminiClient};
+use crate::models::{ItemStatus, JobDelivery, JobMode, JobStatus};
+use crate::paths::get_results_dir;
+use rusqlite::params;
+use serde_json::json;
+use tauri::{AppHandle, Runtime};
+
+/// Largest job `generate_now` will run. Anything bigger should go through
+/// the (cheaper) Batch API.
+pub const INTERACTIVE_MAX_ITEMS: usize = 4;
+
+/// Generates a small job's pending items right away with `generateContent`
+/// instead of a batch. Items run one at a time and are saved exactly as
+/// `download_results` saves batch results; the job and the items it runs
+/// are marked `delivery = 'interactive'` since these calls are billed at
+/// full price.
+///
+/// Returns once every item has been recorded. A request Gemini refuses
+/// fails just its item; any other error stops the run and is returned
+/// once the job is settled. Budget-checked like `submit_batch`, at the
+/// full price.
+#[tauri::command]
+pub async fn generate_now<R: Runtime>(
+    app: AppHandle<R>,
+    job_id: String,
+    override_budget: Option<bool>,
+) -> AppResult<()> {
+    if !override_budget.unwrap_or(false) {
+        check_budget(&app, &job_id, JobDelivery::Interactive)?;
+    }
+    let client = GeminiClient::for_job(&app, &job_id)?;
+    let results_dir = get_results_dir(&app)?;
+
+    // Claim the job as submit_batch does: failed items go back to pending
+    // so a retry only regenerates what didn't succeed.
+    let (mode, model, temperature, prompt, image_config, items) = {
+        let db = get_db(&app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+
+        let (status, mode, model, temperature, prompt, output_size, aspect_ratio): (
+            JobStatus,
+            JobMode,
+            String,
+            f64,
+            String,
+            String,
+            String,
+        ) = tx.query_row(
+            "SELECT status, mode, model, temperature, prompt, output_size, aspect_ratio
+                 FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )?;
+
+        if !status.can_transition_to(JobStatus::Processing) {
+            return Err(AppError::Conflict(format!(
+                "Cannot generate: job is already {}. Wait for it to finish.",
+                status
+            )));
+        }
+        let image_config = gemini::image_config(&output_size, &aspect_ratio)?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        tx.execute(
+            "UPDATE job_items SET status = ?1, error = NULL, response_text = NULL,
+               finish_reason = NULL, block_reason = NULL, safety_ratings = '[]',
+               updated_at = ?2
+             WHERE job_id = ?3 AND status = ?4",
+            params![ItemStatus::Pending, now, job_id, ItemStatus::Failed],
+        )?;
+
+        let mut stmt = tx.prepare(
+            "SELECT id, input_prompt, input_image_path FROM job_items
+                 WHERE job_id = ?1 AND status = ?2",
+        )?;
+        let items: Vec<(String, Option<String>, Option<String>)> = stmt
+            .query_map(params![job_id, ItemStatus::Pending], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+
+        if items.is_empty() {
+            return Err(AppError::Conflict(
+                "Nothing to generate — all items already completed.".to_string(),
+            ));
+        }
+        if items.len() > INTERACTIVE_MAX_ITEMS {
+            return Err(AppError::Validation(format!(
+                "Generate now handles at most {} images; submit larger jobs as a batch.",
+                INTERACTIVE_MAX_ITEMS
+            )));
+        }
+
+        transition_job(&tx, &job_id, status, JobStatus::Processing)?;
+        tx.execute(
+            "UPDATE job_items SET delivery = ?1 WHERE job_id = ?2 AND status = ?3",
+            params![JobDelivery::Interactive, job_id, ItemStatus::Pending],
+        )?;
+        tx.execute(
+            "UPDATE jobs SET delivery = ?1, failed_items = 0,
+               profile_id = COALESCE(profile_id, ?2), updated_at = ?3
+             WHERE id = ?4",
+            params![JobDelivery::Interactive, active_profile_id(&tx)?, now, job_id],
+        )?;
+        tx.commit()?;
+
+        (mode, model, temperature, prompt, image_config, items)
+    };
+    let client = client.with_model(model);
+    events::emit_job(&app, &job_id);
+
+    // An error that isn't about one request (a rejected key, a dropped
+    // connection, a full disk) would fail every remaining item alike, so
+    // stop at the first one and fail the items it left untried.
+    let mut stopped = None;
+    for (index, (item_id, item_prompt, item_image_path)) in items.iter().enumerate() {
+        // Stop spending on a job that was deleted while it ran.
+        let status: Option<JobStatus> = {
+            let db = get_db(&app);
+            let conn = db.conn.lock()?;
+            conn.query_row(
+                "SELECT status FROM jobs WHERE id = ?1",
+                params![job_id],
+                |row| row.get(0),
+            )
+            .ok()
+        };
+        if status != Some(JobStatus::Processing) {
+            return Ok(());
+        }
+
+        let response = async {
+            let image = match (mode, item_image_path) {
+                (JobMode::ImageToImage, Some(path)) => {
+                    Some(input_file_part(&app, &client, &job_id, path).await?)
+                }
+                _ => None,
+            };
+            let parts = request_parts(mode, &prompt, item_prompt.as_deref(), image);
+            let request = gemini::generate_request(parts, temperature, &image_config);
+            client.generate_content(&request).await
+        }
+        .await;
+
+        // Same shape as a batch result line, so both share one save path.
+        let result = match response {
+            Ok(response) => json!({"response": response}),
+            Err(e @ AppError::Api { .. }) => json!({"error": {"message": e.to_string()}}),
+            Err(e) => {
+                stopped = Some((index, e));
+                break;
+            }
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        let charge = format!("generate_now/{}", uuid::Uuid::new_v4());
+        if let Err(e) = save_item_result(&app, &results_dir, item_id, &result, &charge, &now) {
+            stopped = Some((index, e));
+            break;
+        }
+    }
+
+    if let Some((index, e)) = &stopped {
+        let now = chrono::Utc::now().to_rfc3339();
+        for (item_id, _, _) in &items[*index..] {
+            fail_item(&app, item_id, &e.to_string(), &now)?;
+        }
+    }
+    {
+        let db = get_db(&app);
+        let mut conn = db.conn.lock()?;
+        let tx = conn.transaction()?;
+        settle_job(&tx, &job_id, JobStatus::Processing)?;
+        tx.commit()?;
+    }
+    events::emit_job(&app, &job_id);
+    match stopped {
+        Some((_, e)) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::get_job;
+    use crate::gemini::mock::{t2i_job, MockGemini};
+
+    #[tokio::test]
+    async fn generate_now_saves_results_without_a_batch() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a red fox", "a blue whale"], "1K").job.id;
+
+        generate_now(app.clone(), job_id.clone(), None).await.unwrap();
+
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!(done.job.delivery, JobDelivery::Interactive);
+        assert_eq!(done.job.completed_items, 2);
+        assert!(done.batches.is_empty());
+        for item in &done.items {
+            let bytes = std::fs::read(item.output_image_path.as_ref().unwrap()).unwrap();
+            assert!(bytes.starts_with(b"\x89PNG"));
+        }
+
+        let state = mock.state.lock().unwrap();
+        assert_eq!(state.generate_requests.len(), 2);
+        let image_config = &state.generate_requests[0]["generation_config"]["imageConfig"];
+        assert_eq!(image_config["aspectRatio"], "1:1");
+        assert!(!state.requests.iter().any(|r| r.ends_with(":batchGenerateContent")));
+    }
+
+    #[tokio::test]
+    async fn generate_now_fails_only_the_rejected_item() {
+        let mock = MockGemini::start().await;
+        mock.state
+            .lock()
+            .unwrap()
+            .item_errors
+            .insert("blocked".to_string(), "Prompt was blocked".to_string());
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["fine", "blocked"], "1K").job.id;
+
+        generate_now(app.clone(), job_id.clone(), None).await.unwrap();
+
+        let done = get_job(app.clone(), job_id.clone()).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert_eq!((done.job.completed_items, done.job.failed_items), (1, 1));
+        let failed = done.items.iter().find(|i| i.status == ItemStatus::Failed).unwrap();
+        assert_eq!(failed.input_prompt.as_deref(), Some("blocked"));
+        assert!(failed.error.as_deref().unwrap().contains("Prompt was blocked"));
+
+        // A retry regenerates only the failed item.
+        mock.state.lock().unwrap().item_errors.clear();
+        generate_now(app.clone(), job_id.clone(), None).await.unwrap();
+        assert_eq!(mock.state.lock().unwrap().generate_requests.len(), 3);
+        assert_eq!(get_job(app.clone(), job_id).unwrap().job.completed_items, 2);
+    }
+
+    #[tokio::test]
+    async fn generate_now_stops_and_settles_when_the_key_is_rejected() {
+        let mock = MockGemini::start().await;
+        mock.state.lock().unwrap().transient_errors.push_back((401, None));
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["a red fox", "a blue whale"], "1K").job.id;
+
+        let err = generate_now(app.clone(), job_id.clone(), None).await.unwrap_err();
+        assert_eq!(err.kind(), "auth");
+
+        // The second item isn't tried with a key that was just refused.
+        let generated = mock
+            .state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|r| r.ends_with(":generateContent"))
+            .count();
+        assert_eq!(generated, 1);
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Failed);
+        assert_eq!(done.job.failed_items, 2);
+        assert!(done.items.iter().all(|i| i.error.as_deref() == Some(&*err.to_string())));
+    }
+
+    #[tokio::test]
+    async fn generate_now_rejects_jobs_meant_for_batches() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["1", "2", "3", "4", "5"], "1K").job.id;
+
+        let err = generate_now(app.clone(), job_id.clone(), None).await.unwrap_err();
+        assert_eq!(err.kind(), "validation");
+        assert!(err.to_string().contains("at most 4"), "{}", err);
+        let job = get_job(app.clone(), job_id).unwrap().job;
+        assert_eq!(job.status, JobStatus::Pending);
+        assert_eq!(job.delivery, JobDelivery::Batch);
+        assert!(mock.state.lock().unwrap().generate_requests.is_empty());
+    }
+}
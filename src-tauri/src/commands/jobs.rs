@@ -1,121 +1,221 @@
 use crate::db::get_db;
-use crate::models::{CreateI2IJobRequest, CreateT2IJobRequest, Job, JobItem, JobWithItems};
-use rusqlite::params;
-use tauri::AppHandle;
+use crate::error::{AppError, AppResult};
+use crate::gemini;
+use crate::models::{
+    CreateI2IJobRequest, CreateT2IJobRequest, ItemStatus, Job, JobBatch, JobDelivery, JobItem,
+    JobItemOutput, JobMode, JobStatus, JobWithItems, SafetyRatings, JOB_BATCH_COLUMNS,
+    JOB_COLUMNS, JOB_ITEM_COLUMNS, JOB_ITEM_OUTPUT_COLUMNS,
+};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::{AppHandle, Runtime};
 use uuid::Uuid;
 
 #[tauri::command]
-pub fn get_jobs(app: AppHandle, status: Option<String>) -> Result<Vec<Job>, String> {
+pub fn get_jobs<R: Runtime>(app: AppHandle<R>, status: Option<String>) -> AppResult<Vec<Job>> {
     let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-
-    let sql = match status.as_deref() {
-        Some("active") => {
-            "SELECT id, status, mode, prompt, output_size, temperature, aspect_ratio,
-                    batch_job_name, batch_temp_file, total_items, completed_items, failed_items,
-                    created_at, updated_at
-             FROM jobs WHERE status IN ('pending', 'processing') ORDER BY created_at DESC"
-        }
-        None | Some("all") => {
-            "SELECT id, status, mode, prompt, output_size, temperature, aspect_ratio,
-                    batch_job_name, batch_temp_file, total_items, completed_items, failed_items,
-                    created_at, updated_at
-             FROM jobs ORDER BY created_at DESC"
-        }
+    let conn = db.conn.lock()?;
+
+    let (filter, statuses): (&str, &[JobStatus]) = match status.as_deref() {
+        Some("active") => (
+            "WHERE status IN (?1, ?2)",
+            &[JobStatus::Pending, JobStatus::Processing],
+        ),
+        None | Some("all") => ("", &[]),
         Some(other) => {
-            return Err(format!("Unknown status filter: '{}'. Use 'active' or 'all'.", other));
+            return Err(AppError::Validation(format!(
+                "Unknown status filter: '{}'. Use 'active' or 'all'.",
+                other
+            )));
         }
     };
+    let sql = format!(
+        "SELECT {} FROM jobs {} ORDER BY created_at DESC",
+        JOB_COLUMNS, filter
+    );
 
-    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql)?;
     let jobs = stmt
-        .query_map([], Job::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        .query_map(params_from_iter(statuses), Job::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(jobs)
 }
 
 #[tauri::command]
-pub fn get_job(app: AppHandle, id: String) -> Result<JobWithItems, String> {
+pub fn get_job<R: Runtime>(app: AppHandle<R>, id: String) -> AppResult<JobWithItems> {
     let db = get_db(&app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let conn = db.conn.lock()?;
 
-    let job: Job = conn
-        .query_row(
-            "SELECT id, status, mode, prompt, output_size, temperature, aspect_ratio,
-                    batch_job_name, batch_temp_file, total_items, completed_items, failed_items,
-                    created_at, updated_at
-             FROM jobs WHERE id = ?1",
-            params![id],
-            Job::from_row,
-        )
-        .map_err(|e| e.to_string())?;
+    let job = load_job(&conn, &id)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Job not found: {}", id)))?;
+    let items = load_items(&conn, &id)?;
+    let batches = load_batches(&conn, &id)?;
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, job_id, input_prompt, input_image_path, output_image_path, status, error,
-                    created_at, updated_at
-             FROM job_items WHERE job_id = ?1 ORDER BY created_at",
-        )
-        .map_err(|e| e.to_string())?;
-
-    let items = stmt
-        .query_map(params![id], |row| {
-            Ok(JobItem {
-                id: row.get(0)?,
-                job_id: row.get(1)?,
-                input_prompt: row.get(2)?,
-                input_image_path: row.get(3)?,
-                output_image_path: row.get(4)?,
-                status: row.get(5)?,
-                error: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-
-    Ok(JobWithItems { job, items })
+    Ok(JobWithItems { job, items, batches })
+}
+
+pub(crate) fn load_job(conn: &Connection, id: &str) -> rusqlite::Result<Job> {
+    conn.query_row(
+        &format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS),
+        params![id],
+        Job::from_row,
+    )
+}
+
+pub(crate) fn load_items(conn: &Connection, job_id: &str) -> rusqlite::Result<Vec<JobItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_items WHERE job_id = ?1 ORDER BY created_at",
+        JOB_ITEM_COLUMNS
+    ))?;
+    let mut items = stmt
+        .query_map(params![job_id], JobItem::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_item_outputs
+         WHERE item_id IN (SELECT id FROM job_items WHERE job_id = ?1)
+         ORDER BY candidate_index, part_index",
+        JOB_ITEM_OUTPUT_COLUMNS
+    ))?;
+    let mut outputs: HashMap<String, Vec<JobItemOutput>> = HashMap::new();
+    for output in stmt.query_map(params![job_id], JobItemOutput::from_row)? {
+        let output = output?;
+        outputs.entry(output.item_id.clone()).or_default().push(output);
+    }
+    for item in &mut items {
+        item.outputs = outputs.remove(&item.id).unwrap_or_default();
+    }
+    Ok(items)
+}
+
+pub(crate) fn load_batches(conn: &Connection, job_id: &str) -> rusqlite::Result<Vec<JobBatch>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_batches WHERE job_id = ?1 ORDER BY created_at, batch_name",
+        JOB_BATCH_COLUMNS
+    ))?;
+    let batches = stmt
+        .query_map(params![job_id], JobBatch::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(batches)
+}
+
+/// Moves job `id` from `from` to `to` as a compare-and-swap on `from`.
+/// Commands write job status only through here, so transitions missing
+/// from `JobStatus::TRANSITIONS` are rejected before touching the row.
+/// (Startup crash recovery in `Database::new` resets rows in bulk, with
+/// its transitions checked against the same table.) Returns `false` if
+/// the job wasn't in `from` (or doesn't exist).
+pub(crate) fn transition_job(
+    conn: &Connection,
+    id: &str,
+    from: JobStatus,
+    to: JobStatus,
+) -> AppResult<bool> {
+    if !from.can_transition_to(to) {
+        return Err(AppError::Conflict(format!(
+            "Illegal job status transition: {} -> {}",
+            from, to
+        )));
+    }
+    let affected = conn.execute(
+        "UPDATE jobs SET status = ?1, updated_at = ?2 WHERE id = ?3 AND status = ?4",
+        params![to, chrono::Utc::now().to_rfc3339(), id, from],
+    )?;
+    Ok(affected > 0)
+}
+
+/// `transition_job` for one of a job's sub-batches in `job_batches`.
+pub(crate) fn transition_batch(
+    conn: &Connection,
+    batch_name: &str,
+    from: JobStatus,
+    to: JobStatus,
+) -> AppResult<bool> {
+    if !from.can_transition_to(to) {
+        return Err(AppError::Conflict(format!(
+            "Illegal batch status transition: {} -> {}",
+            from, to
+        )));
+    }
+    let affected = conn.execute(
+        "UPDATE job_batches SET status = ?1, updated_at = ?2 WHERE batch_name = ?3 AND status = ?4",
+        params![to, chrono::Utc::now().to_rfc3339(), batch_name, from],
+    )?;
+    Ok(affected > 0)
+}
+
+pub(crate) fn load_item(conn: &Connection, id: &str) -> rusqlite::Result<JobItem> {
+    let mut item = conn.query_row(
+        &format!("SELECT {} FROM job_items WHERE id = ?1", JOB_ITEM_COLUMNS),
+        params![id],
+        JobItem::from_row,
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM job_item_outputs WHERE item_id = ?1
+         ORDER BY candidate_index, part_index",
+        JOB_ITEM_OUTPUT_COLUMNS
+    ))?;
+    item.outputs = stmt
+        .query_map(params![id], JobItemOutput::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(item)
+}
+
+/// The model a new job generates with: the requested one, else the
+/// `default_model` setting.
+fn job_model<R: Runtime>(app: &AppHandle<R>, requested: Option<&str>) -> AppResult<String> {
+    match requested.map(str::trim).filter(|m| !m.is_empty()) {
+        Some(model) => {
+            gemini::validate_model(model)?;
+            Ok(model.to_string())
+        }
+        None => crate::paths::get_default_model(app),
+    }
 }
 
 #[tauri::command]
-pub fn create_t2i_job(app: AppHandle, request: CreateT2IJobRequest) -> Result<JobWithItems, String> {
+pub fn create_t2i_job<R: Runtime>(
+    app: AppHandle<R>,
+    request: CreateT2IJobRequest,
+) -> AppResult<JobWithItems> {
+    gemini::image_size(&request.output_size, &request.aspect_ratio)?;
+    let model = job_model(&app, request.model.as_deref())?;
+
     let db = get_db(&app);
-    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.conn.lock()?;
 
     let job_id = Uuid::new_v4().to_string();
     let first_prompt = request.prompts.first().cloned().unwrap_or_default();
     let total_items = request.prompts.len() as i32;
 
-    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let tx = conn.transaction()?;
 
     tx.execute(
-        "INSERT INTO jobs (id, mode, prompt, output_size, temperature, aspect_ratio, total_items, status)
-         VALUES (?1, 'text-to-image', ?2, ?3, ?4, ?5, ?6, 'pending')",
+        "INSERT INTO jobs (id, mode, prompt, output_size, temperature, aspect_ratio, total_items, status, model)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
             job_id,
+            JobMode::TextToImage,
             first_prompt,
             request.output_size,
             request.temperature,
             request.aspect_ratio,
-            total_items
+            total_items,
+            JobStatus::Pending,
+            model
         ],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     let mut items = Vec::new();
     for prompt in &request.prompts {
         let item_id = Uuid::new_v4().to_string();
         tx.execute(
             "INSERT INTO job_items (id, job_id, input_prompt, status)
-             VALUES (?1, ?2, ?3, 'pending')",
-            params![item_id, job_id, prompt],
-        )
-        .map_err(|e| e.to_string())?;
+             VALUES (?1, ?2, ?3, ?4)",
+            params![item_id, job_id, prompt, ItemStatus::Pending],
+        )?;
 
         items.push(JobItem {
             id: item_id,
@@ -123,19 +223,28 @@ pub fn create_t2i_job(app: AppHandle, request: CreateT2IJobRequest) -> Result<Jo
             input_prompt: Some(prompt.clone()),
             input_image_path: None,
             output_image_path: None,
-            status: "pending".to_string(),
+            status: ItemStatus::Pending,
             error: None,
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            response_text: None,
+            finish_reason: None,
+            block_reason: None,
+            safety_ratings: SafetyRatings::default(),
+            usage: None,
+            cost: None,
+            outputs: Vec::new(),
         });
     }
 
-    tx.commit().map_err(|e| e.to_string())?;
+    tx.commit()?;
 
     let job = Job {
         id: job_id,
-        status: "pending".to_string(),
-        mode: "text-to-image".to_string(),
+        status: JobStatus::Pending,
+        mode: JobMode::TextToImage,
+        delivery: JobDelivery::Batch,
+        model,
         prompt: first_prompt,
         output_size: request.output_size,
         temperature: request.temperature,
@@ -147,55 +256,72 @@ pub fn create_t2i_job(app: AppHandle, request: CreateT2IJobRequest) -> Result<Jo
         failed_items: 0,
         created_at: chrono::Utc::now().to_rfc3339(),
         updated_at: chrono::Utc::now().to_rfc3339(),
-    };
+        estimated_cost: None,
+        actual_cost: None,
+        profile_id: None,
+    }
+    .with_estimate(0);
 
-    Ok(JobWithItems { job, items })
+    Ok(JobWithItems {
+        job,
+        items,
+        batches: Vec::new(),
+    })
 }
 
 #[tauri::command]
-pub fn create_i2i_job(app: AppHandle, request: CreateI2IJobRequest) -> Result<JobWithItems, String> {
+pub fn create_i2i_job<R: Runtime>(
+    app: AppHandle<R>,
+    request: CreateI2IJobRequest,
+) -> AppResult<JobWithItems> {
+    gemini::image_size(&request.output_size, &request.aspect_ratio)?;
+    let model = job_model(&app, request.model.as_deref())?;
+
     // Validate image paths are within uploads directory
     let uploads_dir = crate::paths::get_uploads_dir(&app)?;
     for image_path in &request.image_paths {
         let canonical = std::path::Path::new(image_path)
             .canonicalize()
-            .map_err(|_| format!("Image not found: {}", image_path))?;
+            .map_err(|_| AppError::NotFound(format!("Image not found: {}", image_path)))?;
         if !canonical.starts_with(&uploads_dir) {
-            return Err("Image paths must be within the uploads directory".to_string());
+            return Err(AppError::Validation(
+                "Image paths must be within the uploads directory".to_string(),
+            ));
         }
     }
 
     let db = get_db(&app);
-    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    let mut conn = db.conn.lock()?;
 
     let job_id = Uuid::new_v4().to_string();
     let total_items = request.image_paths.len() as i32;
 
-    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let tx = conn.transaction()?;
 
     tx.execute(
-        "INSERT INTO jobs (id, mode, prompt, output_size, temperature, aspect_ratio, total_items, status)
-         VALUES (?1, 'image-to-image', ?2, ?3, ?4, ?5, ?6, 'pending')",
+        "INSERT INTO jobs (id, mode, prompt, output_size, temperature, aspect_ratio, total_items, status, model)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         params![
             job_id,
+            JobMode::ImageToImage,
             request.prompt,
             request.output_size,
             request.temperature,
             request.aspect_ratio,
-            total_items
+            total_items,
+            JobStatus::Pending,
+            model
         ],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     let mut items = Vec::new();
     for image_path in &request.image_paths {
         let item_id = Uuid::new_v4().to_string();
         tx.execute(
             "INSERT INTO job_items (id, job_id, input_image_path, status)
-             VALUES (?1, ?2, ?3, 'pending')",
-            params![item_id, job_id, image_path],
-        )
-        .map_err(|e| e.to_string())?;
+             VALUES (?1, ?2, ?3, ?4)",
+            params![item_id, job_id, image_path, ItemStatus::Pending],
+        )?;
 
         items.push(JobItem {
             id: item_id,
@@ -203,19 +329,28 @@ pub fn create_i2i_job(app: AppHandle, request: CreateI2IJobRequest) -> Result<Jo
             input_prompt: None,
             input_image_path: Some(image_path.clone()),
             output_image_path: None,
-            status: "pending".to_string(),
+            status: ItemStatus::Pending,
             error: None,
             created_at: chrono::Utc::now().to_rfc3339(),
             updated_at: chrono::Utc::now().to_rfc3339(),
+            response_text: None,
+            finish_reason: None,
+            block_reason: None,
+            safety_ratings: SafetyRatings::default(),
+            usage: None,
+            cost: None,
+            outputs: Vec::new(),
         });
     }
 
-    tx.commit().map_err(|e| e.to_string())?;
+    tx.commit()?;
 
     let job = Job {
         id: job_id,
-        status: "pending".to_string(),
-        mode: "image-to-image".to_string(),
+        status: JobStatus::Pending,
+        mode: JobMode::ImageToImage,
+        delivery: JobDelivery::Batch,
+        model,
         prompt: request.prompt,
         output_size: request.output_size,
         temperature: request.temperature,
@@ -227,48 +362,76 @@ pub fn create_i2i_job(app: AppHandle, request: CreateI2IJobRequest) -> Result<Jo
         failed_items: 0,
         created_at: chrono::Utc::now().to_rfc3339(),
         updated_at: chrono::Utc::now().to_rfc3339(),
-    };
+        estimated_cost: None,
+        actual_cost: None,
+        profile_id: None,
+    }
+    .with_estimate(0);
 
-    Ok(JobWithItems { job, items })
+    Ok(JobWithItems {
+        job,
+        items,
+        batches: Vec::new(),
+    })
 }
 
 #[tauri::command]
-pub async fn delete_job(app: AppHandle, id: String) -> Result<(), String> {
-    let (status, batch_name): (String, Option<String>) = {
+pub async fn delete_job<R: Runtime>(app: AppHandle<R>, id: String) -> AppResult<()> {
+    let (status, batch_name): (JobStatus, Option<String>) = {
         let db = get_db(&app);
-        let conn = db.conn.lock().map_err(|e| e.to_string())?;
+        let conn = db.conn.lock()?;
         conn.query_row(
             "SELECT status, batch_job_name FROM jobs WHERE id = ?1",
             params![id],
             |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| e.to_string())?
+        )?
     };
 
     // Refuse to delete while download_results is mid-flight — letting it
     // proceed would leave orphan images on disk and race the row deletes.
-    if status == "downloading" {
-        return Err(
+    if status == JobStatus::Downloading {
+        return Err(AppError::Conflict(
             "Cannot delete job while results are being downloaded. Try again in a moment."
                 .to_string(),
-        );
+        ));
     }
 
     // Cancel batch if still active (pending/processing). Terminal states
-    // (completed/failed/cancelled) don't need a cancel call.
-    if (status == "pending" || status == "processing") && batch_name.is_some() {
-        let _ = super::batch::cancel_batch(app.clone(), batch_name.unwrap()).await;
+    // (completed/failed/cancelled) don't need a cancel call. If it can't be
+    // cancelled, keep the job: deleting it would leave the batch running
+    // (and billing) with nothing left to track it.
+    let active = matches!(status, JobStatus::Pending | JobStatus::Processing);
+    if let Some(batch_name) = batch_name.filter(|_| active) {
+        super::batch::cancel_batch(app.clone(), batch_name).await?;
     }
 
-    // Delete from DB atomically (job_items FK references jobs.id)
+    // Delete from DB atomically (job_items, job_batches and job_uploads FK
+    // references jobs.id)
     let db = get_db(&app);
-    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let tx = conn.transaction().map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM job_items WHERE job_id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
-    tx.execute("DELETE FROM jobs WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
-    tx.commit().map_err(|e| e.to_string())?;
+    let mut conn = db.conn.lock()?;
+    let tx = conn.transaction()?;
+    let upload_file: Option<String> = tx
+        .query_row(
+            "SELECT temp_file FROM job_uploads WHERE job_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    tx.execute(
+        "DELETE FROM job_item_outputs
+         WHERE item_id IN (SELECT id FROM job_items WHERE job_id = ?1)",
+        params![id],
+    )?;
+    tx.execute("DELETE FROM job_items WHERE job_id = ?1", params![id])?;
+    tx.execute("DELETE FROM job_batches WHERE job_id = ?1", params![id])?;
+    tx.execute("DELETE FROM job_uploads WHERE job_id = ?1", params![id])?;
+    tx.execute("DELETE FROM jobs WHERE id = ?1", params![id])?;
+    tx.commit()?;
+
+    // An interrupted upload's JSONL is never handed to a batch row.
+    if let Some(path) = upload_file {
+        let _ = std::fs::remove_file(path);
+    }
 
     Ok(())
 }
@@ -1,9 +1,17 @@
 pub mod batch;
+pub mod budget;
 pub mod config;
 pub mod files;
+pub mod interactive;
 pub mod jobs;
+pub mod profiles;
+pub mod usage;
 
 pub use batch::*;
+pub use budget::*;
 pub use config::*;
 pub use files::*;
+pub use interactive::*;
 pub use jobs::*;
+pub use profiles::*;
+pub use usage::*;
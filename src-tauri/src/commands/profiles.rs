@@ -0,0 +1,390 @@
+use crate::commands::config::{forget_uploaded_files, mask_key};
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::models::{JobStatus, KeyProfile};
+use crate::secrets::{get_secrets, SecretStore, API_KEY_SECRET};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+/// `config` key holding the id of the profile new submissions use.
+const ACTIVE_PROFILE_KEY: &str = "active_profile_id";
+
+/// Name given to the profile created for a lone key: one saved through
+/// `save_config`, or carried over from before profiles existed.
+const DEFAULT_PROFILE_NAME: &str = "Default";
+
+/// Secret store name of a profile's key.
+pub(crate) fn profile_secret(id: &str) -> String {
+    format!("{}.{}", API_KEY_SECRET, id)
+}
+
+pub(crate) fn active_profile_id(conn: &Connection) -> AppResult<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT p.id FROM config c JOIN key_profiles p ON p.id = c.value WHERE c.key = ?1",
+            params![ACTIVE_PROFILE_KEY],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn set_active(conn: &Connection, id: &str) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO config (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![ACTIVE_PROFILE_KEY, id],
+    )?;
+    Ok(())
+}
+
+fn validate_name(name: &str) -> AppResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation(
+            "Profile name must not be empty".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn name_taken(conn: &Connection, name: &str, except: Option<&str>) -> AppResult<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM key_profiles WHERE name = ?1 AND id IS NOT ?2",
+        params![name, except],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Adds a profile for `api_key`, making it active if no profile is.
+pub(crate) fn create_profile(
+    conn: &Connection,
+    store: &dyn SecretStore,
+    name: &str,
+    api_key: &str,
+) -> AppResult<String> {
+    let name = validate_name(name)?;
+    if name_taken(conn, &name, None)? {
+        return Err(AppError::Conflict(format!(
+            "A profile named '{}' already exists",
+            name
+        )));
+    }
+    let id = Uuid::new_v4().to_string();
+    store.set(&profile_secret(&id), api_key)?;
+    conn.execute(
+        "INSERT INTO key_profiles (id, name, created_at) VALUES (?1, ?2, ?3)",
+        params![id, name, chrono::Utc::now().to_rfc3339()],
+    )?;
+    if active_profile_id(conn)?.is_none() {
+        set_active(conn, &id)?;
+    }
+    Ok(id)
+}
+
+/// `base`, or `base 2`, `base 3`, ... if that name is taken.
+fn unused_name(conn: &Connection, base: &str) -> AppResult<String> {
+    let mut name = base.to_string();
+    let mut n = 1;
+    while name_taken(conn, &name, None)? {
+        n += 1;
+        name = format!("{} {}", base, n);
+    }
+    Ok(name)
+}
+
+/// Refuses to `action` profile `id` while a job submitted with it is
+/// still in flight, since its batches can only be polled and downloaded
+/// with that profile's key.
+pub(crate) fn check_no_jobs_in_flight(conn: &Connection, id: &str, action: &str) -> AppResult<()> {
+    let in_flight: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM jobs WHERE profile_id = ?1 AND status IN (?2, ?3)",
+        params![id, JobStatus::Processing, JobStatus::Downloading],
+        |row| row.get(0),
+    )?;
+    if in_flight > 0 {
+        return Err(AppError::Conflict(format!(
+            "Cannot {}: {} job(s) submitted with this profile are still running.",
+            action, in_flight
+        )));
+    }
+    Ok(())
+}
+
+/// Stores `api_key` in the active profile, or in a new "Default" profile
+/// when none is active. Returns the profile's id. Refused while the
+/// active profile has jobs in flight.
+pub(crate) fn save_active_key(
+    conn: &Connection,
+    store: &dyn SecretStore,
+    api_key: &str,
+) -> AppResult<String> {
+    match active_profile_id(conn)? {
+        Some(id) => {
+            check_no_jobs_in_flight(conn, &id, "change the key")?;
+            store.set(&profile_secret(&id), api_key)?;
+            Ok(id)
+        }
+        None => {
+            let name = unused_name(conn, DEFAULT_PROFILE_NAME)?;
+            create_profile(conn, store, &name, api_key)
+        }
+    }
+}
+
+/// Files the key an earlier version kept under `API_KEY_SECRET` as the
+/// "Default" profile, unless profiles already exist, then removes it.
+pub fn adopt_legacy_key(conn: &Connection, store: &dyn SecretStore) -> AppResult<()> {
+    let Some(key) = store.get(API_KEY_SECRET)? else {
+        return Ok(());
+    };
+    let profiles: i64 =
+        conn.query_row("SELECT COUNT(*) FROM key_profiles", [], |row| row.get(0))?;
+    if profiles == 0 && !key.is_empty() {
+        create_profile(conn, store, DEFAULT_PROFILE_NAME, &key)?;
+    }
+    store.delete(API_KEY_SECRET)
+}
+
+fn load_profile(conn: &Connection, store: &dyn SecretStore, id: &str) -> AppResult<KeyProfile> {
+    let (name, created_at): (String, String) = conn
+        .query_row(
+            "SELECT name, created_at FROM key_profiles WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Key profile not found: {}", id)))?;
+    let masked = store
+        .get(&profile_secret(id))?
+        .filter(|key| !key.is_empty())
+        .map(|key| mask_key(&key));
+    Ok(KeyProfile {
+        id: id.to_string(),
+        name,
+        masked,
+        active: active_profile_id(conn)?.as_deref() == Some(id),
+        created_at,
+    })
+}
+
+#[tauri::command]
+pub fn list_profiles<R: Runtime>(app: AppHandle<R>) -> AppResult<Vec<KeyProfile>> {
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare("SELECT id FROM key_profiles ORDER BY created_at, name")?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    ids.iter()
+        .map(|id| load_profile(&conn, get_secrets(&app), id))
+        .collect()
+}
+
+#[tauri::command]
+pub fn add_profile<R: Runtime>(
+    app: AppHandle<R>,
+    name: String,
+    api_key: String,
+) -> AppResult<KeyProfile> {
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    let store = get_secrets(&app);
+    let id = create_profile(&conn, store, &name, &api_key)?;
+    load_profile(&conn, store, &id)
+}
+
+#[tauri::command]
+pub fn rename_profile<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    name: String,
+) -> AppResult<KeyProfile> {
+    let name = validate_name(&name)?;
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    if name_taken(&conn, &name, Some(&id))? {
+        return Err(AppError::Conflict(format!(
+            "A profile named '{}' already exists",
+            name
+        )));
+    }
+    let changed = conn.execute(
+        "UPDATE key_profiles SET name = ?1 WHERE id = ?2",
+        params![name, id],
+    )?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("Key profile not found: {}", id)));
+    }
+    load_profile(&conn, get_secrets(&app), &id)
+}
+
+/// Deletes a profile and its key. Refused while a job submitted with it
+/// is still in flight, since its batches can only be polled and
+/// downloaded with that key.
+#[tauri::command]
+pub fn delete_profile<R: Runtime>(app: AppHandle<R>, id: String) -> AppResult<()> {
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    check_no_jobs_in_flight(&conn, &id, "delete")?;
+    let was_active = active_profile_id(&conn)?.as_deref() == Some(id.as_str());
+    let changed = conn.execute("DELETE FROM key_profiles WHERE id = ?1", params![id])?;
+    if changed == 0 {
+        return Err(AppError::NotFound(format!("Key profile not found: {}", id)));
+    }
+    conn.execute(
+        "UPDATE jobs SET profile_id = NULL WHERE profile_id = ?1",
+        params![id],
+    )?;
+    if was_active {
+        conn.execute(
+            "DELETE FROM config WHERE key = ?1",
+            params![ACTIVE_PROFILE_KEY],
+        )?;
+    }
+    forget_uploaded_files(&conn, &id)?;
+    get_secrets(&app).delete(&profile_secret(&id))
+}
+
+/// Makes `id` the profile new submissions use.
+#[tauri::command]
+pub fn set_active_profile<R: Runtime>(app: AppHandle<R>, id: String) -> AppResult<KeyProfile> {
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    let store = get_secrets(&app);
+    let profile = load_profile(&conn, store, &id)?;
+    if !profile.active {
+        set_active(&conn, &id)?;
+    }
+    load_profile(&conn, store, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{
+        delete_config, download_results, get_job, poll_batch, save_config, submit_batch,
+    };
+    use crate::gemini::mock::{t2i_job, MockGemini};
+    use crate::secrets::EncryptedFileStore;
+
+    #[tokio::test]
+    async fn profiles_can_be_added_renamed_activated_and_deleted() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let default = list_profiles(app.clone()).unwrap();
+        assert_eq!(default.len(), 1);
+        assert!(default[0].active);
+
+        let test = add_profile(app.clone(), " Test ".into(), "AIzaTestKey123".into()).unwrap();
+        assert_eq!(test.name, "Test");
+        assert_eq!(test.masked.as_deref(), Some("AI...123"));
+        assert!(!test.active);
+        let err = add_profile(app.clone(), "Test".into(), "x".into()).unwrap_err();
+        assert_eq!(err.kind(), "conflict");
+        let err = rename_profile(app.clone(), test.id.clone(), default[0].name.clone());
+        assert_eq!(err.unwrap_err().kind(), "conflict");
+        let renamed = rename_profile(app.clone(), test.id.clone(), "Billing test".into());
+        assert_eq!(renamed.unwrap().name, "Billing test");
+
+        assert!(
+            set_active_profile(app.clone(), test.id.clone())
+                .unwrap()
+                .active
+        );
+        let profiles = list_profiles(app.clone()).unwrap();
+        assert_eq!(profiles.iter().filter(|p| p.active).count(), 1);
+
+        delete_profile(app.clone(), test.id.clone()).unwrap();
+        assert_eq!(list_profiles(app.clone()).unwrap().len(), 1);
+        let store = get_secrets(&app);
+        assert_eq!(store.get(&profile_secret(&test.id)).unwrap(), None);
+        // No active profile left: saving a key makes the one it lands in active.
+        save_config(app.clone(), "AIzaNewKey".into()).unwrap();
+        let profiles = list_profiles(app.clone()).unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert!(profiles.iter().any(|p| p.active && p.name == "Default 2"));
+    }
+
+    #[tokio::test]
+    async fn batches_are_polled_with_the_submitting_profile() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let submitter = list_profiles(app.clone()).unwrap().remove(0);
+
+        let job_id = t2i_job(&app, &["a"], "1K").job.id;
+        submit_batch(app.clone(), job_id.clone(), None)
+            .await
+            .unwrap();
+        let job = get_job(app.clone(), job_id.clone()).unwrap().job;
+        assert_eq!(job.profile_id.as_deref(), Some(submitter.id.as_str()));
+
+        // The mock rejects this key, so only the submitter's key can work.
+        let other = add_profile(app.clone(), "Other".into(), "wrong".into()).unwrap();
+        set_active_profile(app.clone(), other.id).unwrap();
+        let err = delete_profile(app.clone(), submitter.id.clone()).unwrap_err();
+        assert_eq!(err.kind(), "conflict");
+
+        let batch_name = job.batch_job_name.unwrap();
+        poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+        download_results(app.clone(), batch_name, job_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            get_job(app.clone(), job_id).unwrap().job.status,
+            JobStatus::Completed
+        );
+        delete_profile(app.clone(), submitter.id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_key_of_a_profile_with_jobs_in_flight_is_kept() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        let job_id = t2i_job(&app, &["a"], "1K").job.id;
+        submit_batch(app.clone(), job_id.clone(), None)
+            .await
+            .unwrap();
+        let err = save_config(app.clone(), "AIzaNewKey".into()).unwrap_err();
+        assert_eq!(err.kind(), "conflict");
+        let err = delete_config(app.clone()).unwrap_err();
+        assert_eq!(err.kind(), "conflict");
+
+        let batch_name = get_job(app.clone(), job_id)
+            .unwrap()
+            .job
+            .batch_job_name
+            .unwrap();
+        crate::commands::cancel_batch(app.clone(), batch_name)
+            .await
+            .unwrap();
+        save_config(app.clone(), "AIzaNewKey".into()).unwrap();
+    }
+
+    #[test]
+    fn legacy_key_becomes_the_default_profile() {
+        let dir = std::env::temp_dir().join(format!("nanobanana-profiles-{}", Uuid::new_v4()));
+        let db = crate::db::Database::new(dir.clone()).unwrap();
+        let store = EncryptedFileStore::new(dir.clone());
+        let conn = db.conn.lock().unwrap();
+        store.set(API_KEY_SECRET, "legacy-key").unwrap();
+
+        adopt_legacy_key(&conn, &store).unwrap();
+        let id = active_profile_id(&conn).unwrap().unwrap();
+        assert_eq!(load_profile(&conn, &store, &id).unwrap().name, "Default");
+        assert_eq!(
+            store.get(&profile_secret(&id)).unwrap().as_deref(),
+            Some("legacy-key")
+        );
+        assert_eq!(store.get(API_KEY_SECRET).unwrap(), None);
+        drop(conn);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,358 @@
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::models::{ItemStatus, JobDelivery, JobMode, UsageRow};
+use crate::pricing;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+const CSV_HEADER: &str = "day,model,output_size,mode,jobs,items,completed_items,failed_items,\
+success_rate,failure_rate,estimated_cost_usd,actual_cost_usd";
+
+/// Jobs and spend grouped by day, model, output size and mode, newest
+/// day first. `from` and `to` are inclusive `YYYY-MM-DD` local dates.
+#[tauri::command]
+pub fn get_usage_report<R: Runtime>(
+    app: AppHandle<R>,
+    from: Option<String>,
+    to: Option<String>,
+) -> AppResult<Vec<UsageRow>> {
+    let from = from.as_deref().map(parse_day).transpose()?;
+    let to = to.as_deref().map(parse_day).transpose()?;
+    let db = get_db(&app);
+    let conn = db.conn.lock()?;
+    usage_report(&conn, from, to)
+}
+
+/// Writes the usage report to `path` as CSV and returns the number of
+/// rows written. The path comes from the webview, so only an absolute
+/// `.csv` path that is new or an existing regular file is written to.
+#[tauri::command]
+pub fn export_usage_csv<R: Runtime>(
+    app: AppHandle<R>,
+    path: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> AppResult<usize> {
+    let path = Path::new(&path);
+    check_export_path(path)?;
+    let rows = get_usage_report(app, from, to)?;
+    fs::write(path, usage_csv(&rows))
+        .map_err(|e| AppError::Io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(rows.len())
+}
+
+fn check_export_path(path: &Path) -> AppResult<()> {
+    if !path.is_absolute() {
+        return Err(AppError::Validation(format!(
+            "Export path must be absolute: {}",
+            path.display()
+        )));
+    }
+    if !path.extension().is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+        return Err(AppError::Validation(format!(
+            "Export path must end in .csv: {}",
+            path.display()
+        )));
+    }
+    // Not followed: a symlink named .csv could point anywhere.
+    match fs::symlink_metadata(path) {
+        Ok(meta) if !meta.is_file() => Err(AppError::Validation(format!(
+            "Refusing to overwrite {}: not a regular file",
+            path.display()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn parse_day(day: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|_| AppError::Validation(format!("Expected a date as YYYY-MM-DD, got '{}'", day)))
+}
+
+/// Local date of a stored UTC timestamp: SQLite's `datetime('now')`
+/// column default, or RFC 3339 as the app writes it.
+fn local_day(timestamp: &str) -> Option<NaiveDate> {
+    let utc = match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(t) => t.with_timezone(&Utc),
+        Err(_) => NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+            .ok()?
+            .and_utc(),
+    };
+    Some(utc.with_timezone(&Local).date_naive())
+}
+
+#[derive(Default)]
+struct Totals {
+    jobs: i64,
+    items: i64,
+    completed_items: i64,
+    failed_items: i64,
+    estimated_cost: Option<f64>,
+    actual_cost: Option<f64>,
+}
+
+fn add(total: &mut Option<f64>, value: Option<f64>) {
+    if let Some(value) = value {
+        *total = Some(total.unwrap_or(0.0) + value);
+    }
+}
+
+pub(crate) fn usage_report(
+    conn: &Connection,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> AppResult<Vec<UsageRow>> {
+    // Item counts come from job_items rather than the job counters, which
+    // a retry resets before the items are resubmitted.
+    let mut stmt = conn.prepare(
+        "SELECT j.created_at, j.model, j.output_size, j.mode,
+           (SELECT COUNT(*) FROM job_items i WHERE i.job_id = j.id AND i.delivery = ?3),
+           (SELECT COUNT(*) FROM job_items i WHERE i.job_id = j.id),
+           (SELECT COUNT(*) FROM job_items i WHERE i.job_id = j.id AND i.status = ?1),
+           (SELECT COUNT(*) FROM job_items i WHERE i.job_id = j.id AND i.status = ?2),
+           (SELECT SUM(cost) FROM job_items i WHERE i.job_id = j.id)
+         FROM jobs j",
+    )?;
+    let jobs = stmt
+        .query_map(
+            params![
+                ItemStatus::Completed,
+                ItemStatus::Failed,
+                JobDelivery::Interactive
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, JobMode>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, i64>(7)?,
+                    row.get::<_, Option<f64>>(8)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups: HashMap<(NaiveDate, String, String, JobMode), Totals> = HashMap::new();
+    for (created_at, model, size, mode, interactive, items, completed, failed, cost) in jobs {
+        let Some(day) = local_day(&created_at) else {
+            log::warn!("Skipping job with unreadable created_at '{}'", created_at);
+            continue;
+        };
+        if from.is_some_and(|from| day < from) || to.is_some_and(|to| day > to) {
+            continue;
+        }
+        // Each item at the rate of how it was (or will be) sent.
+        let estimate =
+            pricing::model_pricing(&model).and_then(|p| p.images_cost(&size, items, interactive));
+        let totals = groups.entry((day, model, size, mode)).or_default();
+        totals.jobs += 1;
+        totals.items += items;
+        totals.completed_items += completed;
+        totals.failed_items += failed;
+        add(&mut totals.estimated_cost, estimate);
+        add(&mut totals.actual_cost, cost);
+    }
+
+    let mut rows: Vec<UsageRow> = groups
+        .into_iter()
+        .map(|((day, model, output_size, mode), t)| {
+            let rate = |n: i64| {
+                if t.items > 0 {
+                    n as f64 / t.items as f64
+                } else {
+                    0.0
+                }
+            };
+            UsageRow {
+                day: day.format("%Y-%m-%d").to_string(),
+                model,
+                output_size,
+                mode,
+                jobs: t.jobs,
+                items: t.items,
+                completed_items: t.completed_items,
+                failed_items: t.failed_items,
+                success_rate: rate(t.completed_items),
+                failure_rate: rate(t.failed_items),
+                estimated_cost: t.estimated_cost,
+                actual_cost: t.actual_cost,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.day
+            .cmp(&a.day)
+            .then_with(|| a.model.cmp(&b.model))
+            .then_with(|| a.output_size.cmp(&b.output_size))
+            .then_with(|| a.mode.as_str().cmp(b.mode.as_str()))
+    });
+    Ok(rows)
+}
+
+/// The report as CSV with a header line. Costs are in USD; unknown ones
+/// are left empty.
+fn usage_csv(rows: &[UsageRow]) -> String {
+    let money = |cost: Option<f64>| cost.map(|c| format!("{:.6}", c)).unwrap_or_default();
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for row in rows {
+        let fields = [
+            row.day.clone(),
+            csv_field(&row.model),
+            csv_field(&row.output_size),
+            row.mode.to_string(),
+            row.jobs.to_string(),
+            row.items.to_string(),
+            row.completed_items.to_string(),
+            row.failed_items.to_string(),
+            format!("{:.4}", row.success_rate),
+            format!("{:.4}", row.failure_rate),
+            money(row.estimated_cost),
+            money(row.actual_cost),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quotes a field that contains a comma, quote or newline (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::mock::{complete_job, t2i_job, MockGemini};
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(
+            csv_field("gemini-2.5-flash-image"),
+            "gemini-2.5-flash-image"
+        );
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[tokio::test]
+    async fn items_are_costed_at_the_rate_they_were_sent() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        // Half goes out in a batch; what fails there is generated now.
+        let created = t2i_job(&app, &["a", "b"], "1K");
+        let job_id = created.job.id;
+        mock.state
+            .lock()
+            .unwrap()
+            .item_errors
+            .insert(created.items[1].id.clone(), "Bad prompt".to_string());
+        complete_job(&app, &job_id).await;
+        crate::commands::generate_now(app.clone(), job_id.clone(), None)
+            .await
+            .unwrap();
+
+        let done = crate::commands::get_job(app.clone(), job_id).unwrap();
+        let costs: Vec<f64> = done.items.iter().map(|i| i.cost.unwrap()).collect();
+        assert!((costs[0] - 0.03361).abs() < 1e-9, "{:?}", costs);
+        assert!((costs[1] - 0.06722).abs() < 1e-9, "{:?}", costs);
+        // $0.0336 for the batch item, $0.0672 for the interactive one.
+        let rows = get_usage_report(app.clone(), None, None).unwrap();
+        assert!((rows[0].estimated_cost.unwrap() - 0.1008).abs() < 1e-9);
+        assert!((done.job.estimated_cost.unwrap() - 0.1008).abs() < 1e-9);
+        assert!((rows[0].actual_cost.unwrap() - 0.10083).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn report_groups_jobs_and_exports_csv() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+
+        for prompts in [&["a", "b"][..], &["c"][..]] {
+            let created = t2i_job(&app, prompts, "1K");
+            if let Some(item) = created.items.get(1) {
+                let mut state = mock.state.lock().unwrap();
+                state
+                    .item_errors
+                    .insert(item.id.clone(), "Bad prompt".to_string());
+            }
+            complete_job(&app, &created.job.id).await;
+        }
+        t2i_job(&app, &["d"], "2K");
+
+        let rows = get_usage_report(app.clone(), None, None).unwrap();
+        assert_eq!(rows.len(), 2);
+        let done = &rows[0];
+        assert_eq!(
+            (done.output_size.as_str(), done.mode),
+            ("1K", JobMode::TextToImage)
+        );
+        assert_eq!((done.jobs, done.items), (2, 3));
+        assert_eq!((done.completed_items, done.failed_items), (2, 1));
+        assert!((done.success_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert!((done.failure_rate - 1.0 / 3.0).abs() < 1e-9);
+        // 3 x $0.0336 estimated; 2 completed items billed at $0.03361.
+        assert!((done.estimated_cost.unwrap() - 0.1008).abs() < 1e-9);
+        assert!((done.actual_cost.unwrap() - 0.06722).abs() < 1e-9);
+        let pending = &rows[1];
+        assert_eq!((pending.output_size.as_str(), pending.items), ("2K", 1));
+        assert_eq!(pending.actual_cost, None);
+
+        let tomorrow = (Local::now().date_naive() + chrono::Days::new(1)).to_string();
+        assert!(get_usage_report(app.clone(), Some(tomorrow), None)
+            .unwrap()
+            .is_empty());
+        let err = get_usage_report(app.clone(), Some("last week".into()), None).unwrap_err();
+        assert_eq!(err.kind(), "validation");
+
+        let path = test_app.dir.join("usage.csv");
+        let written =
+            export_usage_csv(app.clone(), path.to_string_lossy().into(), None, None).unwrap();
+        assert_eq!(written, 2);
+        let csv = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].contains(",1K,text-to-image,2,3,2,1,0.6667,0.3333,0.100800,0.067220"));
+        assert!(
+            lines[2].ends_with(",1,0,0,0.0000,0.0000,0.050400,"),
+            "{}",
+            lines[2]
+        );
+    }
+
+    #[test]
+    fn export_path_must_be_a_csv_file() {
+        let dir = std::env::temp_dir().join(format!("nanobanana-export-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("folder.csv")).unwrap();
+        fs::write(dir.join("old.csv"), "old").unwrap();
+
+        assert!(check_export_path(&dir.join("usage.csv")).is_ok());
+        assert!(check_export_path(&dir.join("old.CSV")).is_ok());
+        for bad in [
+            Path::new("usage.csv"),
+            &dir.join("usage.txt"),
+            &dir.join(".bashrc"),
+            &dir.join("folder.csv"),
+        ] {
+            let err = check_export_path(bad).unwrap_err();
+            assert_eq!(err.kind(), "validation", "{}", bad.display());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
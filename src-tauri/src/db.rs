@@ -1,8 +1,8 @@
-use rusqlite::{Connection, Result as SqliteResult};
+use crate::models::{JobDelivery, JobStatus};
+use rusqlite::{params, Connection, Result as SqliteResult};
 use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::AppHandle;
-use tauri::Manager;
+use tauri::{AppHandle, Manager, Runtime};
 
 pub struct Database {
     pub conn: Mutex<Connection>,
@@ -20,9 +20,20 @@ impl Database {
         // Crash recovery: if the app was killed mid-download, the job is
         // stranded in 'downloading'. Reset so the next poll tick can
         // re-enter download_results via its CAS guard.
+        // Bulk form of the Downloading -> Processing transition.
+        debug_assert!(JobStatus::Downloading.can_transition_to(JobStatus::Processing));
+        for table in ["jobs", "job_batches"] {
+            conn.execute(
+                &format!("UPDATE {} SET status = ?1 WHERE status = ?2", table),
+                params![JobStatus::Processing, JobStatus::Downloading],
+            )?;
+        }
+        // An interactive job has no batch to pick back up; whatever it
+        // didn't finish stays pending for a retry.
+        debug_assert!(JobStatus::Processing.can_transition_to(JobStatus::Failed));
         conn.execute(
-            "UPDATE jobs SET status = 'processing' WHERE status = 'downloading'",
-            [],
+            "UPDATE jobs SET status = ?1 WHERE status = ?2 AND delivery = ?3",
+            params![JobStatus::Failed, JobStatus::Processing, JobDelivery::Interactive],
         )?;
 
         Ok(Self {
@@ -32,7 +43,7 @@ impl Database {
 }
 
 /// Schema version ladder. Bump when adding a new migration.
-const LATEST_VERSION: i64 = 1;
+const LATEST_VERSION: i64 = 12;
 
 /// Runs any pending migrations, gated on PRAGMA user_version.
 ///
@@ -47,10 +58,95 @@ pub fn run_migrations(conn: &Connection) -> SqliteResult<()> {
         conn.execute_batch("PRAGMA user_version = 1")?;
     }
 
+    if current < 2 {
+        // Table rebuilds. Foreign keys must be off or dropping `jobs`
+        // cascades into job_items (the bundled SQLite enables them by
+        // default), and the pragma is a no-op inside a transaction.
+        let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+        conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+        // Atomic so a crash can't leave jobs_v2 behind with the original
+        // table already dropped.
+        let result = conn.unchecked_transaction().and_then(|tx| {
+            migrate_v2(&tx)?;
+            tx.execute_batch("PRAGMA user_version = 2")?;
+            tx.commit()
+        });
+        if foreign_keys {
+            conn.execute_batch("PRAGMA foreign_keys = ON")?;
+        }
+        result?;
+    }
+
+    if current < 3 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v3(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 3")?;
+        tx.commit()?;
+    }
+
+    if current < 4 {
+        migrate_v4(conn)?;
+        conn.execute_batch("PRAGMA user_version = 4")?;
+    }
+
+    if current < 5 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v5(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 5")?;
+        tx.commit()?;
+    }
+
+    if current < 6 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v6(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 6")?;
+        tx.commit()?;
+    }
+
+    if current < 7 {
+        migrate_v7(conn)?;
+        conn.execute_batch("PRAGMA user_version = 7")?;
+    }
+
+    if current < 8 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v8(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 8")?;
+        tx.commit()?;
+    }
+
+    if current < 9 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v9(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 9")?;
+        tx.commit()?;
+    }
+
+    if current < 10 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v10(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 10")?;
+        tx.commit()?;
+    }
+
+    if current < 11 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v11(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 11")?;
+        tx.commit()?;
+    }
+
+    if current < 12 {
+        let tx = conn.unchecked_transaction()?;
+        migrate_v12(&tx)?;
+        tx.execute_batch("PRAGMA user_version = 12")?;
+        tx.commit()?;
+    }
+
     // Future migrations: gate on `current < N`, bump user_version at the end.
     // Keep each migration idempotent so a partially-applied upgrade can retry.
 
-    debug_assert_eq!(LATEST_VERSION, 1, "update the ladder when adding migrations");
+    debug_assert_eq!(LATEST_VERSION, 12, "update the ladder when adding migrations");
     Ok(())
 }
 
@@ -100,7 +196,342 @@ fn migrate_v1(conn: &Connection) -> SqliteResult<()> {
     Ok(())
 }
 
-pub fn get_db(app: &AppHandle) -> &Database {
+/// v2: CHECK constraints on `jobs.status`, `jobs.mode` and
+/// `job_items.status`, matching `JobStatus`, `JobMode` and `ItemStatus`.
+///
+/// SQLite can't add a CHECK to an existing column, so both tables are
+/// rebuilt. Rows from pre-ladder installs may hold NULLs or unknown
+/// values; they're coerced to the column defaults (unknown statuses to
+/// 'failed') rather than failing the upgrade.
+fn migrate_v2(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        DROP TABLE IF EXISTS jobs_v2;
+        CREATE TABLE jobs_v2 (
+            id TEXT PRIMARY KEY,
+            status TEXT NOT NULL DEFAULT 'pending'
+                CHECK (status IN ('pending', 'processing', 'downloading', 'completed', 'failed', 'cancelled')),
+            mode TEXT NOT NULL DEFAULT 'text-to-image'
+                CHECK (mode IN ('text-to-image', 'image-to-image')),
+            prompt TEXT NOT NULL,
+            output_size TEXT NOT NULL DEFAULT '1K',
+            temperature REAL NOT NULL DEFAULT 1,
+            aspect_ratio TEXT NOT NULL DEFAULT '1:1',
+            batch_job_name TEXT,
+            batch_temp_file TEXT,
+            total_items INTEGER NOT NULL DEFAULT 0,
+            completed_items INTEGER NOT NULL DEFAULT 0,
+            failed_items INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        INSERT INTO jobs_v2
+        SELECT
+            id,
+            CASE
+                WHEN status IS NULL THEN 'pending'
+                WHEN status IN ('pending', 'processing', 'downloading', 'completed', 'failed', 'cancelled') THEN status
+                ELSE 'failed'
+            END,
+            CASE WHEN mode = 'image-to-image' THEN mode ELSE 'text-to-image' END,
+            COALESCE(prompt, ''),
+            COALESCE(output_size, '1K'),
+            COALESCE(temperature, 1),
+            COALESCE(aspect_ratio, '1:1'),
+            batch_job_name,
+            batch_temp_file,
+            COALESCE(total_items, 0),
+            COALESCE(completed_items, 0),
+            COALESCE(failed_items, 0),
+            COALESCE(created_at, datetime('now')),
+            COALESCE(updated_at, datetime('now'))
+        FROM jobs;
+        DROP TABLE jobs;
+        ALTER TABLE jobs_v2 RENAME TO jobs;
+        CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status);
+
+        DROP TABLE IF EXISTS job_items_v2;
+        CREATE TABLE job_items_v2 (
+            id TEXT PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            input_prompt TEXT,
+            input_image_path TEXT,
+            output_image_path TEXT,
+            status TEXT NOT NULL DEFAULT 'pending'
+                CHECK (status IN ('pending', 'completed', 'failed')),
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (job_id) REFERENCES jobs(id) ON DELETE CASCADE
+        );
+        INSERT INTO job_items_v2
+        SELECT
+            id,
+            job_id,
+            input_prompt,
+            input_image_path,
+            output_image_path,
+            CASE
+                WHEN status IS NULL THEN 'pending'
+                WHEN status IN ('pending', 'completed', 'failed') THEN status
+                ELSE 'failed'
+            END,
+            error,
+            COALESCE(created_at, datetime('now')),
+            COALESCE(updated_at, datetime('now'))
+        FROM job_items
+        WHERE job_id IS NOT NULL;
+        DROP TABLE job_items;
+        ALTER TABLE job_items_v2 RENAME TO job_items;
+        CREATE INDEX IF NOT EXISTS idx_job_items_job_id ON job_items(job_id);
+        CREATE INDEX IF NOT EXISTS idx_job_items_status ON job_items(status);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v3: `job_batches`, one row per Gemini batch submitted for a job, and
+/// `job_items.batch_name` recording which batch last carried each item.
+///
+/// Jobs submitted before v3 had exactly one batch (`jobs.batch_job_name`);
+/// it is backfilled so in-flight jobs keep polling after the upgrade.
+fn migrate_v3(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS job_batches (
+            batch_name TEXT PRIMARY KEY,
+            job_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'processing'
+                CHECK (status IN ('pending', 'processing', 'downloading', 'completed', 'failed', 'cancelled')),
+            item_count INTEGER NOT NULL DEFAULT 0,
+            completed_requests INTEGER NOT NULL DEFAULT 0,
+            failed_requests INTEGER NOT NULL DEFAULT 0,
+            temp_file TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (job_id) REFERENCES jobs(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_job_batches_job_id ON job_batches(job_id);
+        CREATE INDEX IF NOT EXISTS idx_job_batches_status ON job_batches(status);
+
+        ALTER TABLE job_items ADD COLUMN batch_name TEXT;
+
+        INSERT OR IGNORE INTO job_batches (batch_name, job_id, status, item_count, temp_file)
+        SELECT
+            batch_job_name,
+            id,
+            CASE WHEN status = 'downloading' THEN 'processing' ELSE status END,
+            CASE
+                WHEN status IN ('processing', 'downloading') THEN
+                    (SELECT COUNT(*) FROM job_items
+                     WHERE job_items.job_id = jobs.id AND job_items.status = 'pending')
+                ELSE total_items
+            END,
+            batch_temp_file
+        FROM jobs
+        WHERE batch_job_name IS NOT NULL;
+
+        UPDATE job_items SET batch_name =
+            (SELECT batch_job_name FROM jobs WHERE jobs.id = job_items.job_id);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v4: `gemini_files`, the Files API upload cache for I2I inputs, keyed
+/// by the key profile that uploaded them (an upload belongs to that key's
+/// project) and by local path (files under uploads/ are never rewritten
+/// in place).
+fn migrate_v4(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS gemini_files (
+            profile_id TEXT NOT NULL,
+            local_path TEXT NOT NULL,
+            file_name TEXT NOT NULL,
+            file_uri TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            expires_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (profile_id, local_path)
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v5: `jobs.delivery`, so interactive (`generate_now`) jobs can be told
+/// apart from batch jobs when reporting cost, and `job_items.delivery`,
+/// how each item was last sent. A job can mix the two (a batch, then
+/// `generate_now` for what failed), and each item is billed at its own
+/// rate. Everything before v5 went through the Batch API.
+fn migrate_v5(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE jobs ADD COLUMN delivery TEXT NOT NULL DEFAULT 'batch'
+            CHECK (delivery IN ('batch', 'interactive'));
+        ALTER TABLE job_items ADD COLUMN delivery TEXT NOT NULL DEFAULT 'batch'
+            CHECK (delivery IN ('batch', 'interactive'));
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v6: per-job `model`. Jobs already sent ran on whatever `gemini_model`
+/// was set to, else the old hardcoded default; batch jobs never submitted
+/// get what a new job would. That setting becomes `default_model`, the
+/// model new jobs start with.
+fn migrate_v6(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        "ALTER TABLE jobs ADD COLUMN model TEXT NOT NULL DEFAULT 'gemini-3.1-pro-preview'",
+    )?;
+    conn.execute(
+        "UPDATE jobs SET model = ?1 WHERE batch_job_name IS NULL AND delivery = ?2",
+        params![crate::gemini::DEFAULT_MODEL, JobDelivery::Batch],
+    )?;
+    conn.execute_batch(
+        r#"
+        UPDATE jobs SET model = (
+            SELECT trim(value) FROM config WHERE key = 'gemini_model' AND trim(value) != ''
+        )
+        WHERE EXISTS (
+            SELECT 1 FROM config WHERE key = 'gemini_model' AND trim(value) != ''
+        );
+
+        UPDATE config SET key = 'default_model' WHERE key = 'gemini_model';
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v7: `job_uploads`, the resumable upload session of a chunk that was
+/// being sent when submission stopped, so the next submit (or startup)
+/// can pick it up at the offset Gemini already has.
+fn migrate_v7(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS job_uploads (
+            job_id TEXT PRIMARY KEY,
+            upload_url TEXT NOT NULL,
+            temp_file TEXT NOT NULL,
+            item_keys TEXT NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            uploaded_bytes INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (job_id) REFERENCES jobs(id) ON DELETE CASCADE
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v8: `job_batches.downloaded_lines`, how many lines of the batch's
+/// result file have been saved, so an interrupted download resumes after
+/// them instead of starting over.
+fn migrate_v8(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE job_batches ADD COLUMN downloaded_lines INTEGER NOT NULL DEFAULT 0;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v9: `job_item_outputs`, every image an item produced (a response can
+/// carry several candidates, each with several images). Backfilled from
+/// `job_items.output_image_path`, which stays as the item's first output.
+fn migrate_v9(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS job_item_outputs (
+            id INTEGER PRIMARY KEY,
+            item_id TEXT NOT NULL,
+            candidate_index INTEGER NOT NULL,
+            part_index INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE (item_id, candidate_index, part_index),
+            FOREIGN KEY (item_id) REFERENCES job_items(id) ON DELETE CASCADE
+        );
+
+        INSERT OR IGNORE INTO job_item_outputs
+            (item_id, candidate_index, part_index, path, mime_type, created_at)
+        SELECT id, 0, 0, output_image_path,
+            CASE
+                WHEN output_image_path LIKE '%.jpg' THEN 'image/jpeg'
+                WHEN output_image_path LIKE '%.webp' THEN 'image/webp'
+                WHEN output_image_path LIKE '%.gif' THEN 'image/gif'
+                ELSE 'image/png'
+            END,
+            updated_at
+        FROM job_items WHERE output_image_path IS NOT NULL;
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v10: what the model said about each item besides images: its text
+/// parts, the candidate's `finishReason` and safety ratings, and the
+/// prompt's `blockReason`, so a safety block can be told from a refusal.
+fn migrate_v10(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE job_items ADD COLUMN response_text TEXT;
+        ALTER TABLE job_items ADD COLUMN finish_reason TEXT;
+        ALTER TABLE job_items ADD COLUMN block_reason TEXT;
+        ALTER TABLE job_items ADD COLUMN safety_ratings TEXT NOT NULL DEFAULT '[]';
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v11: per-item token usage from the response's `usageMetadata` and the
+/// cost it bills as, so job totals match the invoice. A retried item is
+/// billed for every attempt, so these are totals; `job_item_charges` has
+/// one row per billed response, keyed by where it came from, so reading
+/// the same result twice doesn't count it twice.
+fn migrate_v11(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        ALTER TABLE job_items ADD COLUMN prompt_tokens INTEGER;
+        ALTER TABLE job_items ADD COLUMN text_output_tokens INTEGER;
+        ALTER TABLE job_items ADD COLUMN image_output_tokens INTEGER;
+        ALTER TABLE job_items ADD COLUMN thoughts_tokens INTEGER;
+        ALTER TABLE job_items ADD COLUMN cost REAL;
+
+        CREATE TABLE IF NOT EXISTS job_item_charges (
+            source TEXT PRIMARY KEY,
+            item_id TEXT NOT NULL,
+            cost REAL,
+            recorded_at TEXT NOT NULL,
+            FOREIGN KEY (item_id) REFERENCES job_items(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_job_item_charges_item_id ON job_item_charges(item_id);
+        "#,
+    )?;
+    Ok(())
+}
+
+/// v12: named API key profiles. The keys themselves are in the secret
+/// store; jobs remember the profile that submitted them so their batches
+/// are polled and downloaded with the same key.
+fn migrate_v12(conn: &Connection) -> SqliteResult<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS key_profiles (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        ALTER TABLE jobs ADD COLUMN profile_id TEXT;
+        "#,
+    )?;
+    Ok(())
+}
+
+pub fn get_db<R: Runtime>(app: &AppHandle<R>) -> &Database {
     app.state::<Database>().inner()
 }
 
@@ -114,9 +545,9 @@ mod tests {
         let conn = Connection::open_in_memory().unwrap();
         run_migrations(&conn).unwrap();
         let v: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
-        assert_eq!(v, 1);
+        assert_eq!(v, 12);
 
-        // All v1 tables exist and are writable.
+        // All tables exist and are writable.
         conn.execute("INSERT INTO jobs (id, prompt) VALUES ('j1', 'p')", [])
             .unwrap();
         conn.execute(
@@ -135,7 +566,7 @@ mod tests {
         // Running again must not error or reset user_version.
         run_migrations(&conn).unwrap();
         let v: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
-        assert_eq!(v, 1);
+        assert_eq!(v, 12);
     }
 
     #[test]
@@ -190,7 +621,7 @@ mod tests {
         run_migrations(&conn).unwrap();
 
         let after: i64 = conn.query_row("PRAGMA user_version", [], |r| r.get(0)).unwrap();
-        assert_eq!(after, 1);
+        assert_eq!(after, 12);
 
         // Pre-existing data survives the migration replay.
         let prompt: String = conn
@@ -199,6 +630,202 @@ mod tests {
         assert_eq!(prompt, "old");
     }
 
+    #[test]
+    fn migrations_v2_checks_status_and_mode_values() {
+        use crate::models::{ItemStatus, JobMode, JobStatus};
+
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+
+        for (i, status) in JobStatus::ALL.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO jobs (id, status, prompt) VALUES (?1, ?2, 'p')",
+                params![format!("j{}", i), status],
+            )
+            .unwrap();
+        }
+        for mode in JobMode::ALL {
+            conn.execute(
+                "INSERT INTO jobs (id, mode, prompt) VALUES (?1, ?2, 'p')",
+                params![mode.as_str(), mode],
+            )
+            .unwrap();
+        }
+        for status in ItemStatus::ALL {
+            conn.execute(
+                "INSERT INTO job_items (id, job_id, status) VALUES (?1, 'j0', ?2)",
+                params![status.as_str(), status],
+            )
+            .unwrap();
+        }
+
+        assert!(conn
+            .execute("INSERT INTO jobs (id, status, prompt) VALUES ('x', 'done', 'p')", [])
+            .is_err());
+        assert!(conn
+            .execute("INSERT INTO jobs (id, mode, prompt) VALUES ('y', 'video', 'p')", [])
+            .is_err());
+        assert!(conn
+            .execute("INSERT INTO job_items (id, job_id, status) VALUES ('z', 'j0', 'downloading')", [])
+            .is_err());
+    }
+
+    #[test]
+    fn migrations_v2_coerces_unknown_legacy_values() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::migrate_v1(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        conn.execute(
+            "INSERT INTO jobs (id, status, mode, prompt) VALUES ('j1', 'exploded', 'sketch', 'p')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO job_items (id, job_id, status, output_image_path) VALUES ('i1', 'j1', 'weird', '/x.png')",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let (status, mode): (String, String) = conn
+            .query_row("SELECT status, mode FROM jobs WHERE id = 'j1'", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((status.as_str(), mode.as_str()), ("failed", "text-to-image"));
+        let (item_status, output): (String, String) = conn
+            .query_row(
+                "SELECT status, output_image_path FROM job_items WHERE id = 'i1'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((item_status.as_str(), output.as_str()), ("failed", "/x.png"));
+    }
+
+    #[test]
+    fn migrations_v3_backfills_in_flight_batches() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::migrate_v1(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO jobs (id, status, prompt, batch_job_name, total_items)
+                VALUES ('live', 'downloading', 'p', 'batches/live', 2),
+                       ('done', 'completed', 'p', 'batches/done', 1),
+                       ('new', 'pending', 'p', NULL, 1);
+            INSERT INTO job_items (id, job_id, status) VALUES
+                ('l1', 'live', 'pending'), ('l2', 'live', 'pending'),
+                ('d1', 'done', 'completed'), ('n1', 'new', 'pending');
+            "#,
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let batches: Vec<(String, String, String, i64)> = conn
+            .prepare("SELECT batch_name, job_id, status, item_count FROM job_batches ORDER BY job_id")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            batches,
+            vec![
+                ("batches/done".into(), "done".into(), "completed".into(), 1),
+                ("batches/live".into(), "live".into(), "processing".into(), 2),
+            ]
+        );
+        let item_batch: Option<String> = conn
+            .query_row("SELECT batch_name FROM job_items WHERE id = 'l1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(item_batch.as_deref(), Some("batches/live"));
+        let unsubmitted: Option<String> = conn
+            .query_row("SELECT batch_name FROM job_items WHERE id = 'n1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(unsubmitted, None);
+    }
+
+    #[test]
+    fn migrations_v6_moves_model_setting_onto_jobs() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::migrate_v1(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO config (key, value) VALUES ('gemini_model', 'gemini-2.5-flash-image');
+            INSERT INTO jobs (id, prompt) VALUES ('old', 'p');
+            "#,
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let model: String = conn
+            .query_row("SELECT model FROM jobs WHERE id = 'old'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(model, "gemini-2.5-flash-image");
+        let setting: String = conn
+            .query_row("SELECT value FROM config WHERE key = 'default_model'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(setting, "gemini-2.5-flash-image");
+    }
+
+    #[test]
+    fn migrations_v6_gives_unsent_jobs_the_default_model() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::migrate_v1(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO jobs (id, status, prompt, batch_job_name)
+                VALUES ('sent', 'completed', 'p', 'batches/sent'),
+                       ('unsent', 'pending', 'p', NULL);
+            "#,
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+
+        let model = |id: &str| -> String {
+            conn.query_row("SELECT model FROM jobs WHERE id = ?1", params![id], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(model("sent"), "gemini-3.1-pro-preview");
+        assert_eq!(model("unsent"), crate::gemini::DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn migrations_v9_backfills_item_outputs() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::migrate_v1(&conn).unwrap();
+        conn.execute_batch("PRAGMA user_version = 1").unwrap();
+        conn.execute_batch(
+            r#"
+            INSERT INTO jobs (id, status, prompt) VALUES ('j1', 'completed', 'p');
+            INSERT INTO job_items (id, job_id, status, output_image_path) VALUES
+                ('done', 'j1', 'completed', '/results/a.jpg'),
+                ('failed', 'j1', 'failed', NULL);
+            "#,
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+        let outputs: Vec<(String, i64, String, String)> = conn
+            .prepare("SELECT item_id, candidate_index, path, mime_type FROM job_item_outputs")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            outputs,
+            [("done".to_string(), 0, "/results/a.jpg".to_string(), "image/jpeg".to_string())]
+        );
+    }
+
     fn seed_job(conn: &Connection, id: &str, status: &str) {
         conn.execute(
             "INSERT INTO jobs (id, status, mode, prompt) VALUES (?1, ?2, 'text-to-image', 'p')",
@@ -0,0 +1,236 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+
+/// Error returned by every command.
+///
+/// Serialized as `{kind, message, details}`: the frontend branches on
+/// `kind` (e.g. sends the user to re-enter their key on `auth`), shows
+/// `message` as-is, and reads `details` for the extra fields of `api`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AppError {
+    /// SQLite failed, or the connection lock was poisoned.
+    #[error("Database error: {0}")]
+    Db(String),
+    /// Local file or directory access failed.
+    #[error("{0}")]
+    Io(String),
+    /// No usable response: DNS, connect, timeout, TLS, or a body that
+    /// isn't what the endpoint should return.
+    #[error("{0}")]
+    Http(String),
+    /// Gemini answered with an error. `code` is its `status` string,
+    /// e.g. `RESOURCE_EXHAUSTED`.
+    #[error("API request failed ({status}): {message}")]
+    Api {
+        status: u16,
+        code: Option<String>,
+        message: String,
+    },
+    /// The input was rejected before anything was done.
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    NotFound(String),
+    /// The request is valid but the record's state doesn't allow it,
+    /// e.g. submitting a job that is already processing.
+    #[error("{0}")]
+    Conflict(String),
+    /// No API key is configured, or Gemini refused the one that is.
+    #[error("{0}")]
+    Auth(String),
+    /// Submitting would take spend past a configured budget. The command
+    /// can be retried with its override flag to submit anyway.
+    #[error("{0}")]
+    Budget(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Db(_) => "db",
+            AppError::Io(_) => "io",
+            AppError::Http(_) => "http",
+            AppError::Api { .. } => "api",
+            AppError::Validation(_) => "validation",
+            AppError::NotFound(_) => "not_found",
+            AppError::Conflict(_) => "conflict",
+            AppError::Auth(_) => "auth",
+            AppError::Budget(_) => "budget",
+        }
+    }
+
+    /// The same error with its message rewritten, e.g. to add context.
+    pub fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            AppError::Db(m) => AppError::Db(f(m)),
+            AppError::Io(m) => AppError::Io(f(m)),
+            AppError::Http(m) => AppError::Http(f(m)),
+            AppError::Api {
+                status,
+                code,
+                message,
+            } => AppError::Api {
+                status,
+                code,
+                message: f(message),
+            },
+            AppError::Validation(m) => AppError::Validation(f(m)),
+            AppError::NotFound(m) => AppError::NotFound(f(m)),
+            AppError::Conflict(m) => AppError::Conflict(f(m)),
+            AppError::Auth(m) => AppError::Auth(f(m)),
+            AppError::Budget(m) => AppError::Budget(f(m)),
+        }
+    }
+
+    fn details(&self) -> Value {
+        match self {
+            AppError::Api { status, code, .. } => json!({"status": status, "code": code}),
+            _ => Value::Null,
+        }
+    }
+
+    /// Classifies a non-2xx Gemini response. The body is the standard
+    /// `{error: {code, message, status, details}}`; anything else is kept
+    /// verbatim as the message.
+    pub fn from_api_response(status: u16, body: &str) -> Self {
+        let parsed: Value = serde_json::from_str(body).unwrap_or_default();
+        let error = &parsed["error"];
+        let message = error["message"].as_str().unwrap_or(body).to_string();
+        let code = error["status"].as_str().map(str::to_string);
+        // A bad key comes back as 400 INVALID_ARGUMENT, so also look for
+        // the API_KEY_INVALID reason Gemini attaches to it.
+        let key_rejected = error["details"]
+            .as_array()
+            .is_some_and(|d| d.iter().any(|d| d["reason"] == "API_KEY_INVALID"))
+            || message.contains("API key not valid");
+        if matches!(status, 401 | 403) || key_rejected {
+            return AppError::Auth(format!("Gemini rejected the API key: {}", message));
+        }
+        AppError::Api {
+            status,
+            code,
+            message,
+        }
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 3)?;
+        s.serialize_field("kind", self.kind())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.serialize_field("details", &self.details())?;
+        s.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound("Record not found".into()),
+            e => AppError::Db(e.to_string()),
+        }
+    }
+}
+
+impl<T> From<std::sync::PoisonError<T>> for AppError {
+    fn from(e: std::sync::PoisonError<T>) -> Self {
+        AppError::Db(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Io(format!("Malformed JSON: {}", e))
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Http(e.to_string())
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        AppError::Io(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_kind_message_and_details() {
+        let err = AppError::Api {
+            status: 429,
+            code: Some("RESOURCE_EXHAUSTED".into()),
+            message: "Quota exceeded".into(),
+        };
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({
+                "kind": "api",
+                "message": "API request failed (429): Quota exceeded",
+                "details": {"status": 429, "code": "RESOURCE_EXHAUSTED"},
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(AppError::NotFound("Job not found".into())).unwrap(),
+            json!({"kind": "not_found", "message": "Job not found", "details": null})
+        );
+    }
+
+    #[test]
+    fn api_response_with_a_bad_key_is_auth() {
+        let body = json!({"error": {
+            "code": 400,
+            "message": "API key not valid. Please pass a valid API key.",
+            "status": "INVALID_ARGUMENT",
+            "details": [{"reason": "API_KEY_INVALID"}],
+        }})
+        .to_string();
+        assert_eq!(AppError::from_api_response(400, &body).kind(), "auth");
+        assert_eq!(AppError::from_api_response(403, "Forbidden").kind(), "auth");
+    }
+
+    #[test]
+    fn api_response_keeps_status_code_and_message() {
+        let body =
+            json!({"error": {"code": 404, "message": "Batch not found", "status": "NOT_FOUND"}})
+                .to_string();
+        assert_eq!(
+            AppError::from_api_response(404, &body),
+            AppError::Api {
+                status: 404,
+                code: Some("NOT_FOUND".into()),
+                message: "Batch not found".into(),
+            }
+        );
+        assert_eq!(
+            AppError::from_api_response(502, "Bad Gateway"),
+            AppError::Api {
+                status: 502,
+                code: None,
+                message: "Bad Gateway".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_rows_are_not_found() {
+        assert_eq!(
+            AppError::from(rusqlite::Error::QueryReturnedNoRows).kind(),
+            "not_found"
+        );
+    }
+}
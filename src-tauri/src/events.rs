@@ -0,0 +1,49 @@
+use crate::commands::{load_item, load_job};
+use crate::db::get_db;
+use crate::models::Job;
+use tauri::{AppHandle, Emitter, Runtime};
+
+/// Payload: `Job`. Fired whenever a job row changes in the backend.
+pub const JOB_UPDATED: &str = "job-updated";
+/// Payload: `JobItem`. Fired as each item's result is recorded.
+pub const ITEM_UPDATED: &str = "item-updated";
+
+// Emit failures are logged, not returned: a missed event only delays the
+// UI, and must not fail the DB write that preceded it.
+
+/// Emits `job` as-is, e.g. with in-flight progress overlaid.
+pub fn emit_job_value<R: Runtime>(app: &AppHandle<R>, job: &Job) {
+    if let Err(e) = app.emit(JOB_UPDATED, job) {
+        log::warn!("Failed to emit {} for {}: {}", JOB_UPDATED, job.id, e);
+    }
+}
+
+/// Emits the current DB row for `job_id`.
+pub fn emit_job<R: Runtime>(app: &AppHandle<R>, job_id: &str) {
+    let job = {
+        let db = get_db(app);
+        let Ok(conn) = db.conn.lock() else { return };
+        load_job(&conn, job_id)
+    };
+    match job {
+        Ok(job) => emit_job_value(app, &job),
+        Err(e) => log::warn!("Failed to load job {} for event: {}", job_id, e),
+    }
+}
+
+/// Emits the current DB row for `item_id`.
+pub fn emit_item<R: Runtime>(app: &AppHandle<R>, item_id: &str) {
+    let item = {
+        let db = get_db(app);
+        let Ok(conn) = db.conn.lock() else { return };
+        load_item(&conn, item_id)
+    };
+    match item {
+        Ok(item) => {
+            if let Err(e) = app.emit(ITEM_UPDATED, &item) {
+                log::warn!("Failed to emit {} for {}: {}", ITEM_UPDATED, item_id, e);
+            }
+        }
+        Err(e) => log::warn!("Failed to load item {} for event: {}", item_id, e),
+    }
+}
@@ -0,0 +1,649 @@
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::http::get_http_client;
+use crate::paths::{get_api_key, get_default_model, get_gemini_base_url, get_job_api_key};
+use crate::gemini::{is_retryable_error, is_retryable_status, retry_after, RetryPolicy};
+use reqwest::{Client, RequestBuilder, Response};
+use rusqlite::{params, OptionalExtension};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Runtime};
+
+pub const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+pub const DEFAULT_MODEL: &str = "gemini-3.1-flash-image-preview";
+
+/// A file stored by the Files API, as returned by `upload_file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeminiFile {
+    /// Resource name (`files/...`), used to reference batch input files.
+    pub name: String,
+    /// URI for `file_data` parts in requests.
+    pub uri: Option<String>,
+    /// RFC 3339; the API deletes files 48 hours after upload.
+    pub expiration_time: Option<String>,
+}
+
+/// Where a resumable upload session stands, per `query_upload`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadProgress {
+    /// Still open; the next chunk starts at `received`.
+    Active { received: usize },
+    /// Already finalized into this file.
+    Final(GeminiFile),
+}
+
+/// Chunk size for resumable uploads. Every chunk but the last must be a
+/// multiple of 256 KiB.
+pub const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Thin wrapper over the Gemini REST endpoints used by the batch commands.
+///
+/// Owns URL construction and auth headers so commands never format
+/// endpoints inline. The base URL and default model come from config,
+/// which lets tests point at a local stand-in server and deployments at a
+/// proxy; jobs pick their own model with `with_model`. Transient
+/// failures (rate limits, unavailable, dropped connections) are retried
+/// with backoff per the app's `RetryPolicy`.
+#[derive(Clone)]
+pub struct GeminiClient {
+    http: Client,
+    base_url: String,
+    model: String,
+    api_key: String,
+    retry: RetryPolicy,
+    upload_chunk_size: usize,
+}
+
+impl GeminiClient {
+    pub fn new(
+        http: Client,
+        base_url: impl Into<String>,
+        model: impl Into<String>,
+        api_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            model: model.into(),
+            api_key: api_key.into(),
+            retry: RetryPolicy::default(),
+            upload_chunk_size: UPLOAD_CHUNK_SIZE,
+        }
+    }
+
+    /// Client using the stored API key, the configured base URL and the
+    /// default model.
+    pub fn from_app<R: Runtime>(app: &AppHandle<R>) -> AppResult<Self> {
+        let api_key = get_api_key(app)?;
+        Self::with_key(app, api_key)
+    }
+
+    /// Client using the key of the profile that submitted `job_id`.
+    pub fn for_job<R: Runtime>(app: &AppHandle<R>, job_id: &str) -> AppResult<Self> {
+        let api_key = get_job_api_key(app, job_id)?;
+        Self::with_key(app, api_key)
+    }
+
+    /// Client for following up on `batch_name`, with the key of the
+    /// profile whose job created it.
+    pub fn for_batch<R: Runtime>(app: &AppHandle<R>, batch_name: &str) -> AppResult<Self> {
+        let job_id: Option<String> = {
+            let db = get_db(app);
+            let conn = db.conn.lock()?;
+            conn.query_row(
+                "SELECT job_id FROM job_batches WHERE batch_name = ?1",
+                params![batch_name],
+                |row| row.get(0),
+            )
+            .optional()?
+        };
+        match job_id {
+            Some(job_id) => Self::for_job(app, &job_id),
+            None => Self::from_app(app),
+        }
+    }
+
+    /// Client using a caller-supplied key, e.g. a candidate being validated.
+    pub fn with_key<R: Runtime>(app: &AppHandle<R>, api_key: String) -> AppResult<Self> {
+        let http = get_http_client(app)?;
+        let retry = *app.state::<RetryPolicy>().inner();
+        Ok(Self::new(
+            http,
+            get_gemini_base_url(app)?,
+            get_default_model(app)?,
+            api_key,
+        )
+        .with_retry_policy(retry))
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_upload_chunk_size(mut self, size: usize) -> Self {
+        self.upload_chunk_size = size;
+        self
+    }
+
+    /// The same client, generating with `model` instead.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Uploads `bytes` through the resumable Files API in one go.
+    pub async fn upload_file(
+        &self,
+        display_name: &str,
+        mime_type: &str,
+        bytes: Vec<u8>,
+    ) -> AppResult<GeminiFile> {
+        let upload_url = self
+            .start_upload(display_name, mime_type, bytes.len())
+            .await?;
+        self.upload_from(&upload_url, &bytes, 0, |_| Ok(())).await
+    }
+
+    /// Opens a resumable upload session for `size` bytes and returns its
+    /// URL. The URL carries its own auth and stays valid for about a week,
+    /// so callers can persist it and resume with `query_upload`.
+    pub async fn start_upload(
+        &self,
+        display_name: &str,
+        mime_type: &str,
+        size: usize,
+    ) -> AppResult<String> {
+        let init_resp = self
+            .send(true, || {
+                self.http
+                    .post(format!("{}/upload/v1beta/files", self.base_url))
+                    .header("x-goog-api-key", &self.api_key)
+                    .header("X-Goog-Upload-Protocol", "resumable")
+                    .header("X-Goog-Upload-Command", "start")
+                    .header("X-Goog-Upload-Header-Content-Length", size.to_string())
+                    .header("X-Goog-Upload-Header-Content-Type", mime_type)
+                    .header("Content-Type", "application/json")
+                    .json(&json!({"file": {"display_name": display_name}}))
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Upload init failed: {}", e)))?;
+        let init_resp = check_status(init_resp).await?;
+
+        init_resp
+            .headers()
+            .get("x-goog-upload-url")
+            .ok_or_else(|| AppError::Http("No upload URL in response".to_string()))?
+            .to_str()
+            .map(str::to_string)
+            .map_err(|e| AppError::Http(e.to_string()))
+    }
+
+    /// Sends `bytes[offset..]` to an upload session in `upload_chunk_size`
+    /// pieces, finalizing with the last one. `on_chunk` gets the new
+    /// offset after each accepted chunk so progress can be persisted.
+    pub async fn upload_from(
+        &self,
+        upload_url: &str,
+        bytes: &[u8],
+        mut offset: usize,
+        mut on_chunk: impl FnMut(usize) -> AppResult<()>,
+    ) -> AppResult<GeminiFile> {
+        loop {
+            let end = (offset + self.upload_chunk_size).min(bytes.len());
+            let last = end == bytes.len();
+            let resp = self
+                .send(false, || {
+                    self.http
+                        .put(upload_url)
+                        .header(
+                            "X-Goog-Upload-Command",
+                            if last { "upload, finalize" } else { "upload" },
+                        )
+                        .header("X-Goog-Upload-Offset", offset.to_string())
+                        .header("Content-Length", (end - offset).to_string())
+                        .body(bytes[offset..end].to_vec())
+                })
+                .await
+                .map_err(|e| AppError::Http(format!("Upload failed: {}", e)))?;
+            let resp = check_status(resp).await?;
+            offset = end;
+            if last {
+                let upload_result: Value = resp.json().await?;
+                return gemini_file(&upload_result["file"]);
+            }
+            on_chunk(offset)?;
+        }
+    }
+
+    /// Asks an upload session how far it got.
+    pub async fn query_upload(&self, upload_url: &str) -> AppResult<UploadProgress> {
+        let resp = self
+            .send(true, || {
+                self.http
+                    .post(upload_url)
+                    .header("X-Goog-Upload-Command", "query")
+                    .header("Content-Length", "0")
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Upload query failed: {}", e)))?;
+        let resp = check_status(resp).await?;
+
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        match header("x-goog-upload-status").as_deref() {
+            Some("active") => {
+                let received = header("x-goog-upload-size-received")
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| {
+                        AppError::Http("No received size in upload query response".to_string())
+                    })?;
+                Ok(UploadProgress::Active { received })
+            }
+            Some("final") => {
+                let upload_result: Value = resp.json().await?;
+                Ok(UploadProgress::Final(gemini_file(&upload_result["file"])?))
+            }
+            other => Err(AppError::Http(format!(
+                "Upload session is {}",
+                other.unwrap_or("unknown")
+            ))),
+        }
+    }
+
+    /// Creates a batch over an uploaded JSONL file and returns the batch
+    /// name (`batches/...`).
+    pub async fn create_batch(&self, display_name: &str, file_name: &str) -> AppResult<String> {
+        let resp = self
+            .send(false, || {
+                self.http
+                    .post(format!(
+                        "{}/v1beta/models/{}:batchGenerateContent",
+                        self.base_url, self.model
+                    ))
+                    .header("x-goog-api-key", &self.api_key)
+                    .header("Content-Type", "application/json")
+                    .json(&json!({
+                        "batch": {
+                            "display_name": display_name,
+                            "input_config": {
+                                "requests": {
+                                    "file_name": file_name
+                                }
+                            }
+                        }
+                    }))
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Batch submit failed: {}", e)))?;
+        let resp = check_status(resp).await?;
+
+        let batch_result: Value = resp.json().await?;
+        batch_result["name"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| AppError::Http("No batch name in response".to_string()))
+    }
+
+    /// Runs one request synchronously and returns the `GenerateContentResponse`.
+    /// `request` is the same object a batch JSONL line carries under `request`.
+    pub async fn generate_content(&self, request: &Value) -> AppResult<Value> {
+        let resp = self
+            .send(false, || {
+                self.http
+                    .post(format!(
+                        "{}/v1beta/models/{}:generateContent",
+                        self.base_url, self.model
+                    ))
+                    .header("x-goog-api-key", &self.api_key)
+                    .header("Content-Type", "application/json")
+                    .json(request)
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Generate failed: {}", e)))?;
+        let resp = check_status(resp).await?;
+        Ok(resp.json().await?)
+    }
+
+    /// Fetches the batch resource. Callers must validate `batch_name`.
+    pub async fn get_batch(&self, batch_name: &str) -> AppResult<Value> {
+        let resp = self
+            .send(true, || {
+                self.http
+                    .get(format!("{}/v1beta/{}", self.base_url, batch_name))
+                    .header("x-goog-api-key", &self.api_key)
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Poll failed: {}", e)))?;
+        let resp = check_status(resp).await?;
+        Ok(resp.json().await?)
+    }
+
+    /// Cancels a batch. One that has already finished can't be cancelled,
+    /// which Gemini reports as FAILED_PRECONDITION; that counts as success.
+    pub async fn cancel_batch(&self, batch_name: &str) -> AppResult<()> {
+        let resp = self
+            .send(true, || {
+                self.http
+                    .post(format!("{}/v1beta/{}:cancel", self.base_url, batch_name))
+                    .header("x-goog-api-key", &self.api_key)
+            })
+            .await
+            .map_err(|e| AppError::Http(format!("Cancel failed: {}", e)))?;
+        match check_status(resp).await {
+            Ok(_) => Ok(()),
+            Err(AppError::Api { code: Some(code), .. }) if code == "FAILED_PRECONDITION" => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Starts downloading a result file. Returns the response once the
+    /// status has been checked so callers can consume the body as they like.
+    pub async fn download_file(&self, file_name: &str) -> AppResult<Response> {
+        let resp = self
+            .send(true, || {
+                self.http
+                    .get(format!(
+                        "{}/download/v1beta/{}:download?alt=media",
+                        self.base_url, file_name
+                    ))
+                    .header("x-goog-api-key", &self.api_key)
+            })
+            .await?;
+        check_status(resp).await
+    }
+
+    /// Every model visible to the key (`models/...` resources), following
+    /// `nextPageToken` until the listing is exhausted.
+    pub async fn list_models(&self) -> AppResult<Vec<Value>> {
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let resp = self
+                .send(true, || {
+                    let req = self
+                        .http
+                        .get(format!("{}/v1beta/models", self.base_url))
+                        .header("x-goog-api-key", &self.api_key)
+                        .query(&[("pageSize", "1000")]);
+                    match &page_token {
+                        Some(token) => req.query(&[("pageToken", token)]),
+                        None => req,
+                    }
+                })
+                .await
+                .map_err(|e| AppError::Http(format!("Listing models failed: {}", e)))?;
+            let resp = check_status(resp).await?;
+            let page: Value = resp.json().await?;
+            models.extend(page["models"].as_array().cloned().unwrap_or_default());
+            match page["nextPageToken"].as_str().filter(|t| !t.is_empty()) {
+                Some(token) => page_token = Some(token.to_string()),
+                None => return Ok(models),
+            }
+        }
+    }
+
+    /// Looks up `model` once, without retrying, for diagnostics: the HTTP
+    /// status and body of whatever answered, or why nothing did, and how
+    /// long it took.
+    pub async fn probe_model(&self, model: &str) -> (Result<(u16, Value), String>, Duration) {
+        let started = Instant::now();
+        let result = async {
+            let resp = self
+                .http
+                .get(format!("{}/v1beta/models/{}", self.base_url, model))
+                .header("x-goog-api-key", &self.api_key)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            let status = resp.status().as_u16();
+            // An error page that isn't JSON still tells us the status.
+            let body = resp.json::<Value>().await.unwrap_or_default();
+            Ok((status, body))
+        }
+        .await;
+        (result, started.elapsed())
+    }
+
+    /// Sends the request `build` makes, retrying transient failures (see
+    /// `is_retryable_status`) per the client's `RetryPolicy`. `build` runs
+    /// once per attempt. The final response is returned whatever its
+    /// status, so callers still go through `check_status`.
+    async fn send(
+        &self,
+        idempotent: bool,
+        build: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let mut retry = 0;
+        loop {
+            let outcome = build().send().await;
+            let wait = match &outcome {
+                Ok(resp) if is_retryable_status(resp.status(), idempotent) => {
+                    self.retry.delay(retry, retry_after(resp.headers()))
+                }
+                Err(e) if is_retryable_error(e, idempotent) => self.retry.delay(retry, None),
+                _ => None,
+            };
+            let Some(wait) = wait else {
+                return outcome;
+            };
+            let reason = match &outcome {
+                Ok(resp) => resp.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            log::warn!("Gemini request failed ({}); retrying in {:?}", reason, wait);
+            tokio::time::sleep(wait).await;
+            retry += 1;
+        }
+    }
+}
+
+/// Parses the `file` object of an upload response.
+fn gemini_file(file: &Value) -> AppResult<GeminiFile> {
+    Ok(GeminiFile {
+        name: file["name"]
+            .as_str()
+            .ok_or_else(|| AppError::Http("No file name in upload response".to_string()))?
+            .to_string(),
+        uri: file["uri"].as_str().map(str::to_string),
+        expiration_time: file["expirationTime"].as_str().map(str::to_string),
+    })
+}
+
+/// Passes 2xx responses through; turns anything else into an `Api` error
+/// (or `Auth`, if the key was refused) carrying the status and message.
+async fn check_status(resp: Response) -> AppResult<Response> {
+    if resp.status().is_success() {
+        return Ok(resp);
+    }
+    let status = resp.status();
+    let body = resp.text().await.unwrap_or_default();
+    Err(AppError::from_api_response(status.as_u16(), &body))
+}
+
+/// Google's reason for an error response: the `ErrorInfo` reason in
+/// `details` (e.g. `API_KEY_INVALID`, `SERVICE_DISABLED`), else the
+/// canonical `status` (e.g. `RESOURCE_EXHAUSTED`).
+pub fn api_error_reason(body: &Value) -> Option<String> {
+    let error = &body["error"];
+    error["details"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find_map(|d| d["reason"].as_str())
+        .or_else(|| error["status"].as_str())
+        .map(str::to_string)
+}
+
+/// Model ids are interpolated into request paths, so allow only the
+/// characters real ids use (`gemini-3.1-flash-image-preview`).
+pub fn validate_model(model: &str) -> AppResult<()> {
+    let valid = !model.is_empty()
+        && model.len() <= 128
+        && model
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'))
+        && !model.contains("..");
+    if !valid {
+        return Err(AppError::Validation(format!("Invalid model: {}", model)));
+    }
+    Ok(())
+}
+
+/// Accepts only absolute http(s) URLs for the configurable base URL.
+pub fn validate_base_url(url: &str) -> AppResult<()> {
+    let invalid = || AppError::Validation(format!("Invalid Gemini base URL: {}", url));
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gemini::mock::{MockGemini, MOCK_API_KEY, MOCK_RETRY_POLICY};
+
+    #[test]
+    fn new_trims_trailing_slash() {
+        let client = GeminiClient::new(Client::new(), "http://127.0.0.1:8080/", DEFAULT_MODEL, "k");
+        assert_eq!(client.base_url, "http://127.0.0.1:8080");
+        assert_eq!(client.model, DEFAULT_MODEL);
+    }
+
+    #[test]
+    fn validate_base_url_accepts_http_and_https() {
+        assert!(validate_base_url(DEFAULT_BASE_URL).is_ok());
+        assert!(validate_base_url("http://127.0.0.1:8080").is_ok());
+        assert!(validate_base_url("https://gateway.corp.example/gemini").is_ok());
+    }
+
+    #[test]
+    fn validate_model_rejects_path_characters() {
+        assert!(validate_model(DEFAULT_MODEL).is_ok());
+        assert!(validate_model("gemini-2.5-flash-image").is_ok());
+        assert!(validate_model("").is_err());
+        assert!(validate_model("../files/x").is_err());
+        assert!(validate_model("models/gemini").is_err());
+        assert!(validate_model("gemini:batchGenerateContent").is_err());
+    }
+
+    #[test]
+    fn with_model_overrides_the_default() {
+        let client = GeminiClient::new(Client::new(), DEFAULT_BASE_URL, DEFAULT_MODEL, "k")
+            .with_model("gemini-2.5-flash-image");
+        assert_eq!(client.model, "gemini-2.5-flash-image");
+    }
+
+    #[test]
+    fn validate_base_url_rejects_other_schemes() {
+        assert!(validate_base_url("file:///etc/passwd").is_err());
+        assert!(validate_base_url("ftp://example.com").is_err());
+        assert!(validate_base_url("generativelanguage.googleapis.com").is_err());
+        assert!(validate_base_url("").is_err());
+    }
+
+    #[tokio::test]
+    async fn interrupted_upload_continues_from_the_received_offset() {
+        let mock = MockGemini::start().await;
+        let client = GeminiClient::new(Client::new(), &mock.base_url, DEFAULT_MODEL, MOCK_API_KEY)
+            .with_retry_policy(MOCK_RETRY_POLICY)
+            .with_upload_chunk_size(4);
+        let bytes = b"0123456789".to_vec();
+        let url = client.start_upload("lines", "application/jsonl", bytes.len()).await.unwrap();
+
+        // Drop the connection for good from the second chunk on.
+        let mut offsets = Vec::new();
+        let err = client
+            .upload_from(&url, &bytes, 0, |offset| {
+                offsets.push(offset);
+                mock.state.lock().unwrap().fail_upload_chunks = usize::MAX;
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Upload interrupted"), "{}", err);
+        assert_eq!(offsets, vec![4]);
+        assert_eq!(client.query_upload(&url).await.unwrap(), UploadProgress::Active { received: 4 });
+
+        mock.state.lock().unwrap().fail_upload_chunks = 0;
+
+        let file = client.upload_from(&url, &bytes, 4, |_| Ok(())).await.unwrap();
+        assert!(file.uri.is_some());
+        assert_eq!(client.query_upload(&url).await.unwrap(), UploadProgress::Final(file));
+    }
+
+    async fn mock_client(errors: &[(u16, Option<&str>)]) -> (MockGemini, GeminiClient) {
+        let mock = MockGemini::start().await;
+        mock.state.lock().unwrap().transient_errors =
+            errors.iter().map(|(s, r)| (*s, r.map(str::to_string))).collect();
+        let client = GeminiClient::new(Client::new(), &mock.base_url, DEFAULT_MODEL, MOCK_API_KEY)
+            .with_retry_policy(MOCK_RETRY_POLICY);
+        (mock, client)
+    }
+
+    fn request_count(mock: &MockGemini) -> usize {
+        mock.state.lock().unwrap().requests.len()
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let (mock, client) = mock_client(&[(503, None), (429, None)]).await;
+        assert_eq!(client.list_models().await.unwrap().len(), 4);
+        assert_eq!(request_count(&mock), 4);
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_honored() {
+        let (mock, client) = mock_client(&[(429, Some("1"))]).await;
+        let started = std::time::Instant::now();
+        client.get_batch("batches/missing").await.unwrap_err();
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+        assert_eq!(request_count(&mock), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_after_beyond_max_delay_fails_fast() {
+        let (mock, client) = mock_client(&[(429, Some("3600"))]).await;
+        let err = client.list_models().await.unwrap_err();
+        assert!(matches!(err, AppError::Api { status: 429, .. }), "{}", err);
+        assert_eq!(request_count(&mock), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let (mock, client) = mock_client(&[(503, None); 4]).await;
+        let err = client.list_models().await.unwrap_err();
+        assert!(matches!(err, AppError::Api { status: 503, .. }), "{}", err);
+        assert_eq!(
+            request_count(&mock),
+            MOCK_RETRY_POLICY.max_attempts as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_retried() {
+        let (mock, client) = mock_client(&[(400, None), (404, None)]).await;
+        client.list_models().await.unwrap_err();
+        assert_eq!(request_count(&mock), 1);
+    }
+
+    #[tokio::test]
+    async fn non_idempotent_requests_retry_only_when_turned_away() {
+        // A 500 may have created the batch; a 503 certainly didn't.
+        let (mock, client) = mock_client(&[(500, None)]).await;
+        let err = client.create_batch("b", "files/x").await.unwrap_err();
+        assert!(matches!(err, AppError::Api { status: 500, .. }), "{}", err);
+        assert_eq!(request_count(&mock), 1);
+
+        let (mock, client) = mock_client(&[(503, None), (500, None)]).await;
+        client.create_batch("b", "files/x").await.unwrap_err();
+        assert_eq!(request_count(&mock), 2);
+    }
+}
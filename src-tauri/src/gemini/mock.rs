@@ -0,0 +1,790 @@
+//! In-process stand-in for the Gemini Files, Batch and generateContent
+//! endpoints, used by
+//! tests to drive the real commands end to end without hitting Google.
+//!
+//! Speaks just enough HTTP/1.1 for reqwest: one request per connection,
+//! `Connection: close` on every response.
+
+use crate::db::Database;
+use crate::gemini::RetryPolicy;
+use crate::commands::{
+    create_profile, create_t2i_job, download_results, get_job, poll_batch, submit_batch,
+};
+use crate::http::HttpClient;
+use crate::models::{CreateT2IJobRequest, JobWithItems};
+use crate::secrets::{EncryptedFileStore, Secrets};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::params;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::test::MockRuntime;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const MOCK_API_KEY: &str = "mock-api-key";
+
+/// Retry policy of mock apps: real retries, without the real waits.
+pub const MOCK_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base_delay: Duration::from_millis(1),
+    max_delay: Duration::from_secs(2),
+};
+
+/// Mutable server state. Tests script behaviour by locking `MockGemini::state`.
+#[derive(Default)]
+pub struct MockState {
+    /// Batch states handed out on successive GETs of a new batch. When the
+    /// script runs dry the batch stays in its last state. Empty means
+    /// "succeed on the first poll".
+    pub script: Vec<String>,
+    /// Per-item failures keyed by JSONL `key` (or, for generateContent,
+    /// by the prompt text), returned as `{error: {...}}`.
+    pub item_errors: HashMap<String, String>,
+    /// Per-item responses with no image part, keyed like `item_errors`.
+    pub item_text_only: HashMap<String, String>,
+    /// Per-item responses whose image data isn't valid base64, keyed like
+    /// `item_errors`.
+    pub item_bad_image: HashSet<String>,
+    /// Per-item number of image candidates to return instead of one,
+    /// keyed like `item_errors`.
+    pub item_candidates: HashMap<String, usize>,
+    /// Bodies of every generateContent call, in arrival order.
+    pub generate_requests: Vec<Value>,
+    /// Every request as `"METHOD /path"`, in arrival order.
+    pub requests: Vec<String>,
+    /// Upload chunks to reject with a 503 before accepting any, to
+    /// simulate a dropped connection mid-upload.
+    pub fail_upload_chunks: usize,
+    /// Transient failures answered, in order, to the next requests of
+    /// any kind: `(status, Retry-After)`.
+    pub transient_errors: VecDeque<(u16, Option<String>)>,
+    /// Result file downloads to break off halfway through the line with
+    /// this index, in order, to simulate a connection dropped mid-file.
+    pub truncate_downloads: VecDeque<usize>,
+    /// Batches whose cancel fails with a 500, as if Gemini were down.
+    pub refuse_cancel: HashSet<String>,
+    /// Models the key may not use, answered with a 403 carrying this
+    /// `ErrorInfo` reason, e.g. `SERVICE_DISABLED`.
+    pub denied_models: HashMap<String, String>,
+    uploads: HashMap<String, Vec<u8>>,
+    /// Finalized upload sessions, answered on `query`.
+    finalized_uploads: HashMap<String, Value>,
+    files: HashMap<String, Vec<u8>>,
+    batches: HashMap<String, MockBatch>,
+    next_id: u64,
+}
+
+struct MockBatch {
+    input_file: String,
+    pending_states: VecDeque<String>,
+    state: String,
+    result_file: Option<String>,
+    success: usize,
+    failed: usize,
+}
+
+impl MockState {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Uploaded JSONL for a batch, one parsed line per request.
+    pub fn batch_requests(&self, batch_name: &str) -> Vec<Value> {
+        let Some(batch) = self.batches.get(batch_name) else {
+            return Vec::new();
+        };
+        let bytes = self.files.get(&batch.input_file).cloned().unwrap_or_default();
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect()
+    }
+
+    /// Deletes a succeeded batch's result file, as Gemini does once it
+    /// expires, so downloading it answers 404.
+    pub fn expire_results(&mut self, batch_name: &str) {
+        if let Some(file) = self.batches.get(batch_name).and_then(|b| b.result_file.clone()) {
+            self.files.remove(&file);
+        }
+    }
+
+    pub fn batch_state(&self, batch_name: &str) -> Option<String> {
+        self.batches.get(batch_name).map(|b| b.state.clone())
+    }
+
+    /// Replaces the remaining state script of an existing batch.
+    pub fn script_batch(&mut self, batch_name: &str, states: &[&str]) {
+        if let Some(batch) = self.batches.get_mut(batch_name) {
+            batch.pending_states = states.iter().map(|s| s.to_string()).collect();
+        }
+    }
+}
+
+pub struct MockGemini {
+    pub base_url: String,
+    pub state: Arc<Mutex<MockState>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockGemini {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let task = {
+            let state = state.clone();
+            let base_url = base_url.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let state = state.clone();
+                    let base_url = base_url.clone();
+                    tokio::spawn(async move {
+                        let _ = serve(stream, &state, &base_url).await;
+                    });
+                }
+            })
+        };
+
+        Self { base_url, state, task }
+    }
+
+    /// Replaces the state script used by batches created from now on.
+    pub fn script(&self, states: &[&str]) {
+        self.state.lock().unwrap().script = states.iter().map(|s| s.to_string()).collect();
+    }
+
+    /// A mock Tauri app wired to this server: fresh database in a temp
+    /// dir, shared HTTP client, and config pointing at the mock with a
+    /// valid key and temp uploads/results directories.
+    pub fn app(&self) -> TestApp {
+        let dir = std::env::temp_dir().join(format!("nanobanana-test-{}", uuid::Uuid::new_v4()));
+        let mut context = tauri::test::mock_context(tauri::test::noop_assets());
+        context.config_mut().identifier = "com.nanobanana.studio.test".into();
+        let app = tauri::test::mock_builder().build(context).unwrap();
+
+        let db = Database::new(dir.clone()).unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            for (key, value) in [
+                ("gemini_base_url", self.base_url.clone()),
+                ("results_dir", dir.join("results").to_string_lossy().to_string()),
+                ("uploads_dir", dir.join("uploads").to_string_lossy().to_string()),
+            ] {
+                conn.execute(
+                    "INSERT INTO config (key, value) VALUES (?1, ?2)",
+                    params![key, value],
+                )
+                .unwrap();
+            }
+        }
+        let secrets = Secrets::with_store(EncryptedFileStore::new(dir.clone()));
+        create_profile(&db.conn.lock().unwrap(), secrets.store(), "Default", MOCK_API_KEY)
+            .unwrap();
+        app.manage(db);
+        app.manage(secrets);
+        app.manage(HttpClient::new(reqwest::Client::new()));
+        app.manage(MOCK_RETRY_POLICY);
+
+        TestApp { app, dir }
+    }
+}
+
+impl Drop for MockGemini {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Mock Tauri app plus its scratch directory, removed on drop.
+pub struct TestApp {
+    pub app: tauri::App<MockRuntime>,
+    pub dir: PathBuf,
+}
+
+impl TestApp {
+    pub fn handle(&self) -> AppHandle<MockRuntime> {
+        self.app.handle().clone()
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A square text-to-image job of `prompts` at `output_size`, on the
+/// default model.
+pub fn t2i_request(prompts: &[&str], output_size: &str) -> CreateT2IJobRequest {
+    CreateT2IJobRequest {
+        prompts: prompts.iter().map(|p| p.to_string()).collect(),
+        output_size: output_size.to_string(),
+        temperature: 1.0,
+        aspect_ratio: "1:1".to_string(),
+        model: None,
+    }
+}
+
+/// Creates the job `t2i_request` describes.
+pub fn t2i_job(
+    app: &AppHandle<MockRuntime>,
+    prompts: &[&str],
+    output_size: &str,
+) -> JobWithItems {
+    create_t2i_job(app.clone(), t2i_request(prompts, output_size)).unwrap()
+}
+
+/// Submits `job_id` as a batch, polls its newest batch once and downloads
+/// the results, returning the job as it ends up. With the default script
+/// the batch succeeds on that first poll.
+pub async fn complete_job(app: &AppHandle<MockRuntime>, job_id: &str) -> JobWithItems {
+    submit_batch(app.clone(), job_id.to_string(), None).await.unwrap();
+    let job = get_job(app.clone(), job_id.to_string()).unwrap().job;
+    let batch_name = job.batch_job_name.unwrap();
+    poll_batch(app.clone(), batch_name.clone()).await.unwrap();
+    download_results(app.clone(), batch_name, job_id.to_string()).await.unwrap();
+    get_job(app.clone(), job_id.to_string()).unwrap()
+}
+
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+struct Reply {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn json(status: u16, value: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "application/json".into())],
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({"error": {"code": status, "message": message}}))
+    }
+
+    /// An error shaped like Google's, with a canonical `status` and an
+    /// `ErrorInfo` reason.
+    fn google_error(status: u16, canonical: &str, reason: &str, message: &str) -> Self {
+        Self::json(
+            status,
+            json!({"error": {
+                "code": status,
+                "message": message,
+                "status": canonical,
+                "details": [{
+                    "@type": "type.googleapis.com/google.rpc.ErrorInfo",
+                    "reason": reason,
+                    "domain": "googleapis.com",
+                }],
+            }}),
+        )
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: &Mutex<MockState>, base_url: &str) -> std::io::Result<()> {
+    let Some(req) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+    let reply = handle(&req, state, base_url);
+
+    let mut head = format!("HTTP/1.1 {} MOCK\r\n", reply.status);
+    for (k, v) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", k, v));
+    }
+    // A reply may claim more than it sends, to drop the connection early.
+    if !reply.headers.iter().any(|(k, _)| k == "Content-Length") {
+        head.push_str(&format!("Content-Length: {}\r\n", reply.body.len()));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&reply.body).await?;
+    stream.shutdown().await
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let len: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < len {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Ok(Some(Request { method, path, headers, body }))
+}
+
+fn handle(req: &Request, state: &Mutex<MockState>, base_url: &str) -> Reply {
+    let mut state = state.lock().unwrap();
+    state.requests.push(format!("{} {}", req.method, req.path));
+    if let Some((status, retry_after)) = state.transient_errors.pop_front() {
+        let mut reply = Reply::error(status, "Transient failure");
+        reply.headers.extend(retry_after.map(|v| ("Retry-After".to_string(), v)));
+        return reply;
+    }
+
+    let path = req.path.split('?').next().unwrap_or_default();
+
+    // Upload session URLs carry their own auth, like the real API.
+    if let Some(session) = path.strip_prefix("/upload/session/") {
+        return upload_chunk(&mut state, session, req, base_url);
+    }
+
+    if req.headers.get("x-goog-api-key").map(String::as_str) != Some(MOCK_API_KEY) {
+        return Reply::google_error(
+            400,
+            "INVALID_ARGUMENT",
+            "API_KEY_INVALID",
+            "API key not valid. Please pass a valid API key.",
+        );
+    }
+    let denied = path
+        .strip_prefix("/v1beta/models/")
+        .and_then(|m| state.denied_models.get(m.split(':').next().unwrap_or(m)));
+    if let Some(reason) = denied {
+        return Reply::google_error(403, "PERMISSION_DENIED", reason, "Permission denied.");
+    }
+
+    match (req.method.as_str(), path) {
+        ("POST", "/upload/v1beta/files") => {
+            let id = format!("upload-{}", state.next_id());
+            state.uploads.insert(id.clone(), Vec::new());
+            Reply {
+                status: 200,
+                headers: vec![(
+                    "x-goog-upload-url".into(),
+                    format!("{}/upload/session/{}", base_url, id),
+                )],
+                body: Vec::new(),
+            }
+        }
+        ("POST", p) if p.starts_with("/v1beta/models/") && p.ends_with(":batchGenerateContent") => {
+            create_batch(&mut state, &req.body)
+        }
+        ("POST", p) if p.starts_with("/v1beta/models/") && p.ends_with(":generateContent") => {
+            generate_content(&mut state, &req.body)
+        }
+        ("GET", "/v1beta/models") => list_models(&req.path),
+        ("GET", p) if p.starts_with("/v1beta/models/") => {
+            let model = p.trim_start_matches("/v1beta/");
+            Reply::json(200, json!({"name": model, "supportedGenerationMethods": ["generateContent", "batchGenerateContent"]}))
+        }
+        ("POST", p) if p.starts_with("/v1beta/batches/") && p.ends_with(":cancel") => {
+            let name = p.trim_start_matches("/v1beta/").trim_end_matches(":cancel");
+            if state.refuse_cancel.contains(name) {
+                return Reply::error(500, "Internal error");
+            }
+            match state.batches.get_mut(name) {
+                Some(batch) if is_terminal(&batch.state) => Reply::json(
+                    400,
+                    json!({"error": {
+                        "code": 400,
+                        "message": "Batch is already in a terminal state.",
+                        "status": "FAILED_PRECONDITION",
+                    }}),
+                ),
+                Some(batch) => {
+                    batch.state = "JOB_STATE_CANCELLED".into();
+                    batch.pending_states.clear();
+                    Reply::json(200, json!({}))
+                }
+                None => Reply::error(404, "Batch not found"),
+            }
+        }
+        ("GET", p) if p.starts_with("/v1beta/batches/") => {
+            let name = p.trim_start_matches("/v1beta/").to_string();
+            get_batch(&mut state, &name)
+        }
+        ("GET", p) if p.starts_with("/download/v1beta/files/") && p.ends_with(":download") => {
+            let name = p.trim_start_matches("/download/v1beta/").trim_end_matches(":download");
+            let Some(mut body) = state.files.get(name).cloned() else {
+                return Reply::error(404, "File not found");
+            };
+            let mut headers = vec![("Content-Type".into(), "application/octet-stream".into())];
+            if let Some(line) = state.truncate_downloads.pop_front() {
+                headers.push(("Content-Length".into(), body.len().to_string()));
+                let mut lines = body.split(|&b| b == b'\n');
+                let start: usize = lines.clone().take(line).map(|l| l.len() + 1).sum();
+                let len = lines.nth(line).map_or(0, <[u8]>::len);
+                body.truncate(start + len / 2);
+            }
+            Reply {
+                status: 200,
+                headers,
+                body,
+            }
+        }
+        _ => Reply::error(404, "Not found"),
+    }
+}
+
+fn upload_chunk(state: &mut MockState, session: &str, req: &Request, base_url: &str) -> Reply {
+    let command = req.headers.get("x-goog-upload-command").cloned().unwrap_or_default();
+    if command == "query" {
+        return query_upload(state, session);
+    }
+    if state.fail_upload_chunks > 0 {
+        state.fail_upload_chunks -= 1;
+        return Reply::error(503, "Upload interrupted");
+    }
+    let Some(buf) = state.uploads.get_mut(session) else {
+        return Reply::error(404, "Upload session not found");
+    };
+    let offset: usize = req
+        .headers
+        .get("x-goog-upload-offset")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if offset != buf.len() {
+        return Reply::error(400, "Upload offset mismatch");
+    }
+    buf.extend_from_slice(&req.body);
+
+    if !command.contains("finalize") {
+        return Reply {
+            status: 200,
+            headers: vec![("x-goog-upload-status".into(), "active".into())],
+            body: Vec::new(),
+        };
+    }
+
+    let bytes = state.uploads.remove(session).unwrap_or_default();
+    let name = format!("files/{}", session);
+    let size = bytes.len();
+    state.files.insert(name.clone(), bytes);
+    let expires = chrono::Utc::now() + chrono::Duration::hours(48);
+    let file = json!({
+        "name": name,
+        "uri": format!("{}/v1beta/{}", base_url, name),
+        "sizeBytes": size.to_string(),
+        "expirationTime": expires.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        "state": "ACTIVE",
+    });
+    state.finalized_uploads.insert(session.to_string(), file.clone());
+    Reply::json(200, json!({"file": file}))
+}
+
+fn query_upload(state: &MockState, session: &str) -> Reply {
+    if let Some(buf) = state.uploads.get(session) {
+        return Reply {
+            status: 200,
+            headers: vec![
+                ("x-goog-upload-status".into(), "active".into()),
+                ("x-goog-upload-size-received".into(), buf.len().to_string()),
+            ],
+            body: Vec::new(),
+        };
+    }
+    match state.finalized_uploads.get(session) {
+        Some(file) => {
+            let mut reply = Reply::json(200, json!({"file": file}));
+            reply.headers.push(("x-goog-upload-status".into(), "final".into()));
+            reply
+        }
+        None => Reply::error(404, "Upload session not found"),
+    }
+}
+
+fn create_batch(state: &mut MockState, body: &[u8]) -> Reply {
+    let body: Value = serde_json::from_slice(body).unwrap_or_default();
+    let Some(input_file) = body["batch"]["input_config"]["requests"]["file_name"].as_str() else {
+        return Reply::error(400, "Missing input file");
+    };
+    if !state.files.contains_key(input_file) {
+        return Reply::error(400, "Input file not found");
+    }
+
+    let name = format!("batches/mock-{}", state.next_id());
+    let mut pending_states: VecDeque<String> = state.script.iter().cloned().collect();
+    if pending_states.is_empty() {
+        pending_states.push_back("JOB_STATE_SUCCEEDED".into());
+    }
+    state.batches.insert(
+        name.clone(),
+        MockBatch {
+            input_file: input_file.to_string(),
+            pending_states,
+            state: "JOB_STATE_PENDING".into(),
+            result_file: None,
+            success: 0,
+            failed: 0,
+        },
+    );
+    Reply::json(200, json!({"name": name, "metadata": {"state": "JOB_STATE_PENDING"}}))
+}
+
+fn is_terminal(batch_state: &str) -> bool {
+    matches!(
+        batch_state,
+        "JOB_STATE_SUCCEEDED" | "JOB_STATE_FAILED" | "JOB_STATE_CANCELLED" | "JOB_STATE_EXPIRED"
+    )
+}
+
+fn get_batch(state: &mut MockState, name: &str) -> Reply {
+    let needs_results = {
+        let Some(batch) = state.batches.get_mut(name) else {
+            return Reply::error(404, "Batch not found");
+        };
+        if let Some(next) = batch.pending_states.pop_front() {
+            batch.state = next;
+        }
+        batch.state == "JOB_STATE_SUCCEEDED" && batch.result_file.is_none()
+    };
+    if needs_results {
+        build_results(state, name);
+    }
+
+    let batch = &state.batches[name];
+    let total = state.batch_requests(name).len();
+    let mut body = json!({
+        "name": name,
+        "state": batch.state,
+        "batchStats": {
+            "totalRequestCount": total.to_string(),
+            "successRequestCount": batch.success.to_string(),
+            "failedRequestCount": batch.failed.to_string(),
+        }
+    });
+    if let Some(file) = &batch.result_file {
+        body["dest"] = json!({"fileName": file});
+    }
+    Reply::json(200, body)
+}
+
+/// Renders the result JSONL for a succeeded batch, honouring the
+/// per-item error and text-only scripts.
+fn build_results(state: &mut MockState, name: &str) {
+    let mut lines = Vec::new();
+    let (mut success, mut failed) = (0, 0);
+    for (i, request) in state.batch_requests(name).iter().enumerate() {
+        let key = request["key"].as_str().unwrap_or_default().to_string();
+        let mut line = item_result(state, &key, i);
+        if line.get("error").is_some() {
+            failed += 1;
+        } else {
+            success += 1;
+        }
+        line["key"] = json!(key);
+        lines.push(line.to_string());
+    }
+
+    let file = format!("files/{}-results", name.trim_start_matches("batches/"));
+    state.files.insert(file.clone(), lines.join("\n").into_bytes());
+    let batch = state.batches.get_mut(name).unwrap();
+    batch.result_file = Some(file);
+    batch.success = success;
+    batch.failed = failed;
+}
+
+/// A two-page model listing mixing image and text-only models.
+fn list_models(path_and_query: &str) -> Reply {
+    let model = |id: &str, methods: &[&str]| {
+        json!({"name": format!("models/{}", id), "displayName": id, "supportedGenerationMethods": methods})
+    };
+    if path_and_query.contains("pageToken=page-2") {
+        return Reply::json(
+            200,
+            json!({"models": [
+                model("gemini-2.5-flash-image", &["generateContent", "countTokens"]),
+                model("imagen-4.0-generate-001", &["predict"]),
+            ]}),
+        );
+    }
+    Reply::json(
+        200,
+        json!({
+            "models": [
+                model("gemini-2.5-flash", &["generateContent", "batchGenerateContent"]),
+                model(crate::gemini::DEFAULT_MODEL, &["generateContent", "batchGenerateContent"]),
+            ],
+            "nextPageToken": "page-2",
+        }),
+    )
+}
+
+/// `{error}` or `{response}` for one request, per the item scripts.
+/// `index` varies the generated image's colour.
+fn item_result(state: &MockState, key: &str, index: usize) -> Value {
+    if let Some(message) = state.item_errors.get(key) {
+        json!({"error": {"code": 400, "message": message, "status": "INVALID_ARGUMENT"}})
+    } else if let Some(text) = state.item_text_only.get(key) {
+        json!({"response": {
+            "candidates": [{"content": {"parts": [{"text": text}]}}],
+            "usageMetadata": {
+                "promptTokenCount": 10,
+                "candidatesTokenCount": 5,
+                "candidatesTokensDetails": [{"modality": "TEXT", "tokenCount": 5}],
+                "totalTokenCount": 15,
+            },
+        }})
+    } else {
+        let count = state.item_candidates.get(key).copied().unwrap_or(1);
+        let bad_image = state.item_bad_image.contains(key);
+        let candidates: Vec<Value> = (0..count)
+            .map(|c| {
+                let shade = ((index + c) * 37 % 256) as u8;
+                let data = if bad_image {
+                    "not base64!".to_string()
+                } else {
+                    STANDARD.encode(png(2, 2, [shade, 128, 255 - shade]))
+                };
+                json!({"content": {"parts": [
+                    {"text": "Here is your image."},
+                    {"inlineData": {"mimeType": "image/png", "data": data}}
+                ]}})
+            })
+            .collect();
+        json!({"response": {
+            "candidates": candidates,
+            "usageMetadata": {
+                "promptTokenCount": 10,
+                "candidatesTokenCount": 1125 * count,
+                "candidatesTokensDetails": [
+                    {"modality": "TEXT", "tokenCount": 5 * count},
+                    {"modality": "IMAGE", "tokenCount": 1120 * count},
+                ],
+                "totalTokenCount": 10 + 1125 * count,
+            },
+        }})
+    }
+}
+
+/// Synchronous generation. Item scripts are keyed by the prompt text,
+/// since a generateContent request carries no `key`.
+fn generate_content(state: &mut MockState, body: &[u8]) -> Reply {
+    let body: Value = serde_json::from_slice(body).unwrap_or_default();
+    let prompt = body["contents"][0]["parts"][0]["text"].as_str().unwrap_or_default().to_string();
+    state.generate_requests.push(body);
+
+    let index = state.generate_requests.len();
+    let result = item_result(state, &prompt, index);
+    match result["error"]["message"].as_str() {
+        Some(message) => Reply::error(400, message),
+        None => Reply::json(200, result["response"].clone()),
+    }
+}
+
+/// Encodes a solid-colour RGB PNG. Uses stored (uncompressed) deflate
+/// blocks so no compression dependency is needed.
+pub fn png(width: u32, height: u32, rgb: [u8; 3]) -> Vec<u8> {
+    // Each scanline: filter byte 0 (none) followed by the pixels.
+    let mut row = vec![0u8];
+    for _ in 0..width {
+        row.extend_from_slice(&rgb);
+    }
+    let raw = row.repeat(height as usize);
+
+    let mut zlib = vec![0x78, 0x01];
+    let mut chunks = raw.chunks(0xFFFF).peekable();
+    while let Some(block) = chunks.next() {
+        zlib.push(u8::from(chunks.peek().is_none()));
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = Vec::new();
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", ihdr), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(&data);
+        let crc = crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_has_signature_and_chunks() {
+        let bytes = png(2, 2, [255, 0, 0]);
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert!(bytes.ends_with(&[0xAE, 0x42, 0x60, 0x82]), "IEND CRC is fixed");
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn adler32_matches_reference() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+}
@@ -0,0 +1,9 @@
+pub mod client;
+#[cfg(test)]
+pub mod mock;
+pub mod request;
+pub mod retry;
+
+pub use client::*;
+pub use request::*;
+pub use retry::*;
@@ -0,0 +1,312 @@
+use crate::error::{AppError, AppResult};
+use serde_json::{json, Value};
+
+/// Output sizes accepted by the image model, lowest resolution first.
+pub const OUTPUT_SIZES: &[&str] = &["0.5K", "1K", "2K", "4K"];
+
+/// Aspect ratios accepted by the image model.
+pub const ASPECT_RATIOS: &[&str] = &[
+    "1:1", "16:9", "9:16", "4:3", "3:4", "3:2", "2:3", "4:5", "5:4", "21:9",
+];
+
+/// Pixel dimensions sent as `imageConfig.imageSize`. Mirrors the "Image Size
+/// Mappings" table in docs/planning/application-specification.md: rows follow
+/// `OUTPUT_SIZES`, columns follow `ASPECT_RATIOS`.
+const IMAGE_SIZES: [[&str; 10]; 4] = [
+    [
+        "512x512", "512x288", "288x512", "512x384", "384x512", "512x341", "341x512", "512x640",
+        "640x512", "512x219",
+    ],
+    [
+        "1024x1024", "1024x576", "576x1024", "1024x768", "768x1024", "1024x683", "683x1024",
+        "1024x1280", "1280x1024", "1024x439",
+    ],
+    [
+        "2048x2048", "2048x1152", "1152x2048", "2048x1536", "1536x2048", "2048x1365",
+        "1365x2048", "2048x2560", "2560x2048", "2048x878",
+    ],
+    [
+        "4096x4096", "4096x2304", "2304x4096", "4096x3072", "3072x4096", "4096x2731",
+        "2731x4096", "4096x5120", "5120x4096", "4096x1756",
+    ],
+];
+
+/// Looks up the `imageSize` for an output size / aspect ratio pair,
+/// rejecting anything outside the supported table.
+pub fn image_size(output_size: &str, aspect_ratio: &str) -> AppResult<&'static str> {
+    let row = OUTPUT_SIZES
+        .iter()
+        .position(|s| *s == output_size)
+        .ok_or_else(|| AppError::Validation(format!("Unsupported output size: {}", output_size)))?;
+    let col = ASPECT_RATIOS
+        .iter()
+        .position(|r| *r == aspect_ratio)
+        .ok_or_else(|| {
+            AppError::Validation(format!("Unsupported aspect ratio: {}", aspect_ratio))
+        })?;
+    Ok(IMAGE_SIZES[row][col])
+}
+
+/// Builds the `imageConfig` block of `generation_config`.
+pub fn image_config(output_size: &str, aspect_ratio: &str) -> AppResult<Value> {
+    Ok(json!({
+        "imageSize": image_size(output_size, aspect_ratio)?,
+        "aspectRatio": aspect_ratio,
+    }))
+}
+
+/// Builds a `GenerateContentRequest`. `parts` is passed through untouched
+/// so the prompt text stays exactly what the user typed.
+pub fn generate_request(parts: Vec<Value>, temperature: f64, image_config: &Value) -> Value {
+    json!({
+        "contents": [{"parts": parts}],
+        "generation_config": {
+            "temperature": temperature,
+            "responseModalities": ["TEXT", "IMAGE"],
+            "imageConfig": image_config
+        }
+    })
+}
+
+/// Builds one JSONL line for a batch request: `generate_request` keyed by
+/// the item id.
+pub fn batch_request_line(
+    key: &str,
+    parts: Vec<Value>,
+    temperature: f64,
+    image_config: &Value,
+) -> Value {
+    json!({
+        "key": key,
+        "request": generate_request(parts, temperature, image_config)
+    })
+}
+
+/// Upper bounds for one batch's JSONL input. Jobs that exceed either are
+/// split across as many batches as needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    pub max_items: usize,
+    pub max_bytes: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        // Keeps each upload (and the copy held in memory while building
+        // it) well under the Files API limits even for 4K I2I inputs.
+        Self {
+            max_items: 200,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// One batch's worth of JSONL, plus the keys of the items it carries.
+#[derive(Debug, Default)]
+pub struct JsonlChunk {
+    pub keys: Vec<String>,
+    pub body: String,
+}
+
+/// Groups JSONL lines into chunks that respect `BatchLimits`, so callers
+/// can build and upload one chunk at a time instead of the whole job.
+pub struct JsonlChunker {
+    limits: BatchLimits,
+    current: JsonlChunk,
+}
+
+impl JsonlChunker {
+    pub fn new(limits: BatchLimits) -> Self {
+        Self {
+            limits,
+            current: JsonlChunk::default(),
+        }
+    }
+
+    /// Adds a line. Returns the previous chunk if this line didn't fit in
+    /// it. A line larger than `max_bytes` on its own still gets a chunk to
+    /// itself; the API, not the chunker, decides whether it's too big.
+    pub fn push(&mut self, key: String, line: String) -> Option<JsonlChunk> {
+        let current = &self.current;
+        let separator = usize::from(!current.body.is_empty());
+        let full = !current.keys.is_empty()
+            && (current.keys.len() >= self.limits.max_items
+                || current.body.len() + separator + line.len() > self.limits.max_bytes);
+        let flushed = full.then(|| std::mem::take(&mut self.current));
+
+        if !self.current.body.is_empty() {
+            self.current.body.push('\n');
+        }
+        self.current.body.push_str(&line);
+        self.current.keys.push(key);
+        flushed
+    }
+
+    /// The last, partially filled chunk, if any lines were pushed.
+    pub fn finish(self) -> Option<JsonlChunk> {
+        (!self.current.keys.is_empty()).then_some(self.current)
+    }
+}
+
+/// Splits a streamed JSONL body into lines as chunks arrive. Only the
+/// trailing partial line is buffered, so memory is bounded by the longest
+/// line rather than the whole file.
+#[derive(Debug, Default)]
+pub struct JsonlLines {
+    partial: Vec<u8>,
+}
+
+impl JsonlLines {
+    /// Adds a chunk and returns the lines it completed, without their line
+    /// endings. Blank lines are dropped.
+    pub fn push(&mut self, mut chunk: &[u8]) -> Vec<Vec<u8>> {
+        let mut lines = Vec::new();
+        while let Some(end) = chunk.iter().position(|&b| b == b'\n') {
+            self.partial.extend_from_slice(&chunk[..end]);
+            lines.extend(non_blank(std::mem::take(&mut self.partial)));
+            chunk = &chunk[end + 1..];
+        }
+        self.partial.extend_from_slice(chunk);
+        lines
+    }
+
+    /// The last line, if the body didn't end with a newline.
+    pub fn finish(self) -> Option<Vec<u8>> {
+        non_blank(self.partial)
+    }
+}
+
+fn non_blank(mut line: Vec<u8>) -> Option<Vec<u8>> {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    (!line.iter().all(u8::is_ascii_whitespace)).then_some(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_size_matches_spec_table() {
+        assert_eq!(image_size("1K", "1:1").unwrap(), "1024x1024");
+        assert_eq!(image_size("1K", "16:9").unwrap(), "1024x576");
+        assert_eq!(image_size("0.5K", "21:9").unwrap(), "512x219");
+        assert_eq!(image_size("2K", "3:2").unwrap(), "2048x1365");
+        assert_eq!(image_size("4K", "4:5").unwrap(), "4096x5120");
+    }
+
+    #[test]
+    fn image_size_covers_every_supported_pair() {
+        for size in OUTPUT_SIZES {
+            for ratio in ASPECT_RATIOS {
+                assert!(image_size(size, ratio).is_ok(), "{} {}", size, ratio);
+            }
+        }
+    }
+
+    #[test]
+    fn image_size_rejects_unknown_size() {
+        assert!(image_size("8K", "1:1").is_err());
+        assert!(image_size("", "1:1").is_err());
+        assert!(image_size("1k", "1:1").is_err());
+    }
+
+    #[test]
+    fn image_size_rejects_unknown_ratio() {
+        assert!(image_size("1K", "7:3").is_err());
+        assert!(image_size("1K", "").is_err());
+    }
+
+    #[test]
+    fn image_config_emits_size_and_ratio() {
+        let config = image_config("2K", "9:16").unwrap();
+        assert_eq!(config["imageSize"], "1152x2048");
+        assert_eq!(config["aspectRatio"], "9:16");
+    }
+
+    #[test]
+    fn batch_request_line_keeps_prompt_verbatim() {
+        let config = image_config("1K", "16:9").unwrap();
+        let line = batch_request_line("item-1", vec![json!({"text": "a red fox"})], 1.0, &config);
+        assert_eq!(line["key"], "item-1");
+        assert_eq!(line["request"]["contents"][0]["parts"][0]["text"], "a red fox");
+        let gen = &line["request"]["generation_config"];
+        assert_eq!(gen["temperature"], 1.0);
+        assert_eq!(gen["imageConfig"]["imageSize"], "1024x576");
+        assert_eq!(gen["imageConfig"]["aspectRatio"], "16:9");
+    }
+
+    fn chunk_all(limits: BatchLimits, lines: &[&str]) -> Vec<JsonlChunk> {
+        let mut chunker = JsonlChunker::new(limits);
+        let mut chunks = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            chunks.extend(chunker.push(i.to_string(), line.to_string()));
+        }
+        chunks.extend(chunker.finish());
+        chunks
+    }
+
+    #[test]
+    fn chunker_splits_by_item_count() {
+        let limits = BatchLimits { max_items: 2, max_bytes: usize::MAX };
+        let chunks = chunk_all(limits, &["a", "b", "c", "d", "e"]);
+        let keys: Vec<Vec<String>> = chunks.iter().map(|c| c.keys.clone()).collect();
+        assert_eq!(keys, vec![vec!["0", "1"], vec!["2", "3"], vec!["4"]]);
+        assert_eq!(chunks[0].body, "a\nb");
+    }
+
+    #[test]
+    fn chunker_splits_by_bytes_including_newlines() {
+        // "aaaa\nbbbb" is 9 bytes; a third line would need 14.
+        let limits = BatchLimits { max_items: 100, max_bytes: 9 };
+        let chunks = chunk_all(limits, &["aaaa", "bbbb", "cccc"]);
+        let bodies: Vec<&str> = chunks.iter().map(|c| c.body.as_str()).collect();
+        assert_eq!(bodies, vec!["aaaa\nbbbb", "cccc"]);
+    }
+
+    #[test]
+    fn chunker_gives_oversized_line_its_own_chunk() {
+        let limits = BatchLimits { max_items: 100, max_bytes: 4 };
+        let chunks = chunk_all(limits, &["ab", "oversized", "cd"]);
+        let bodies: Vec<&str> = chunks.iter().map(|c| c.body.as_str()).collect();
+        assert_eq!(bodies, vec!["ab", "oversized", "cd"]);
+    }
+
+    #[test]
+    fn chunker_with_no_lines_yields_nothing() {
+        assert!(chunk_all(BatchLimits::default(), &[]).is_empty());
+    }
+
+    fn split(body: &[u8], chunk_size: usize) -> Vec<String> {
+        let mut splitter = JsonlLines::default();
+        let mut lines: Vec<Vec<u8>> = body
+            .chunks(chunk_size)
+            .flat_map(|chunk| splitter.push(chunk))
+            .collect();
+        lines.extend(splitter.finish());
+        lines
+            .into_iter()
+            .map(|l| String::from_utf8(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn lines_split_the_same_at_any_chunk_boundary() {
+        let body = b"{\"key\":\"a\"}\r\n\n{\"key\":\"b\"}\n  \n{\"key\":\"c\"}";
+        for chunk_size in 1..=body.len() {
+            assert_eq!(
+                split(body, chunk_size),
+                ["{\"key\":\"a\"}", "{\"key\":\"b\"}", "{\"key\":\"c\"}"],
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn lines_of_an_empty_body_yield_nothing() {
+        assert!(split(b"", 1).is_empty());
+        assert!(split(b"\n\n", 1).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// How `GeminiClient` retries transient failures. Managed as app state
+/// next to the shared `reqwest::Client`, so tests can shorten the delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first. 1 disables retries.
+    pub max_attempts: u32,
+    /// Backoff ceiling before the first retry; doubles with each attempt.
+    pub base_delay: Duration,
+    /// Longest single wait. A `Retry-After` beyond this fails the request
+    /// instead of stalling the command.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (0 for the first retry), or `None`
+    /// to give up. The server's `Retry-After` wins when present; otherwise
+    /// "full jitter": a random delay up to `base_delay * 2^retry`, capped
+    /// at `max_delay`, so clients backing off together don't retry in step.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry + 1 >= self.max_attempts {
+            return None;
+        }
+        if let Some(wait) = retry_after {
+            return (wait <= self.max_delay).then_some(wait);
+        }
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        Some(ceiling.mul_f64(jitter()))
+    }
+}
+
+/// Whether a response with `status` is worth retrying. 429 and 503 mean
+/// the request was turned away before it was acted on, so any request may
+/// repeat; the other 5xx/timeouts may have done the work and are retried
+/// only for `idempotent` requests (not e.g. creating a batch).
+pub fn is_retryable_status(status: StatusCode, idempotent: bool) -> bool {
+    match status.as_u16() {
+        429 | 503 => true,
+        408 | 500 | 502 | 504 => idempotent,
+        _ => false,
+    }
+}
+
+/// Whether a transport error is worth retrying. A failed connect never
+/// reached the server; a timeout or dropped connection might have, so
+/// those are retried only for `idempotent` requests.
+pub fn is_retryable_error(error: &reqwest::Error, idempotent: bool) -> bool {
+    error.is_connect() || (idempotent && (error.is_timeout() || error.is_request()))
+}
+
+/// Parses `Retry-After`, either delta-seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Uniform in [0, 1). std's `RandomState` is randomly keyed per instance,
+/// which is plenty for spreading retries without pulling in `rand`.
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn delay_backs_off_within_the_cap_and_stops_after_max_attempts() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+        };
+        for _ in 0..20 {
+            assert!(policy.delay(0, None).unwrap() < Duration::from_secs(1));
+            assert!(policy.delay(1, None).unwrap() < Duration::from_secs(2));
+            assert!(policy.delay(2, None).unwrap() < Duration::from_secs(3));
+        }
+        assert_eq!(policy.delay(3, None), None);
+    }
+
+    #[test]
+    fn delay_honors_retry_after_up_to_max_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(30))),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(61))), None);
+    }
+
+    #[test]
+    fn classifies_statuses() {
+        for code in [429, 503] {
+            assert!(is_retryable_status(StatusCode::from_u16(code).unwrap(), false));
+        }
+        for code in [408, 500, 502, 504] {
+            let status = StatusCode::from_u16(code).unwrap();
+            assert!(is_retryable_status(status, true));
+            assert!(!is_retryable_status(status, false));
+        }
+        for code in [400, 401, 403, 404, 409] {
+            assert!(!is_retryable_status(StatusCode::from_u16(code).unwrap(), true));
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&later).unwrap());
+        let wait = retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::error::{AppError, AppResult};
+use crate::settings::Settings;
+use reqwest::{Certificate, Client, NoProxy, Proxy};
+use std::fs;
+use std::sync::RwLock;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// A client for the network settings. Fails if the CA bundle can't be
+/// read or holds no certificates.
+pub fn build_client(settings: &Settings) -> AppResult<Client> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .timeout(Duration::from_secs(settings.request_timeout_secs));
+    if let Some(url) = &settings.https_proxy {
+        let proxy = Proxy::https(url)
+            .map_err(|e| AppError::Validation(format!("Invalid proxy URL {}: {}", url, e)))?
+            .no_proxy(settings.no_proxy.as_deref().and_then(NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+    if let Some(path) = &settings.ca_bundle_path {
+        let invalid = |e: &dyn std::fmt::Display| {
+            AppError::Validation(format!("Can't use CA bundle {}: {}", path, e))
+        };
+        let pem = fs::read(path).map_err(|e| invalid(&e))?;
+        let certs = Certificate::from_pem_bundle(&pem).map_err(|e| invalid(&e))?;
+        if certs.is_empty() {
+            return Err(invalid(&"no PEM certificates found"));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    Ok(builder.build()?)
+}
+
+pub(crate) fn validate_proxy_url(url: &str) -> AppResult<()> {
+    let invalid = || AppError::Validation(format!("Invalid proxy URL: {}", url));
+    let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// The shared HTTP client, managed as Tauri state. Replaced whole when a
+/// network setting changes; requests already under way finish on the
+/// client they started with.
+pub struct HttpClient {
+    client: RwLock<Client>,
+}
+
+impl HttpClient {
+    pub fn new(client: Client) -> Self {
+        HttpClient {
+            client: RwLock::new(client),
+        }
+    }
+
+    /// A handle on the current client; cheap, as clients share their pool.
+    pub fn get(&self) -> AppResult<Client> {
+        Ok(self.client.read()?.clone())
+    }
+
+    pub fn replace(&self, client: Client) -> AppResult<()> {
+        *self.client.write()? = client;
+        Ok(())
+    }
+}
+
+pub fn get_http_client<R: Runtime>(app: &AppHandle<R>) -> AppResult<Client> {
+    app.state::<HttpClient>().inner().get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_a_proxied_client() {
+        let settings = Settings {
+            https_proxy: Some("http://proxy.corp.example:3128".into()),
+            no_proxy: Some("localhost, .internal".into()),
+            connect_timeout_secs: 30,
+            ..Settings::default()
+        };
+        assert!(build_client(&settings).is_ok());
+        assert!(validate_proxy_url("proxy.corp.example:3128").is_err());
+        assert!(validate_proxy_url("ftp://proxy.corp.example").is_err());
+    }
+
+    #[test]
+    fn a_ca_bundle_without_certificates_is_refused() {
+        let path = std::env::temp_dir().join(format!("nanobanana-ca-{}.pem", uuid::Uuid::new_v4()));
+        fs::write(&path, "not a certificate").unwrap();
+        let settings = Settings {
+            ca_bundle_path: Some(path.to_string_lossy().to_string()),
+            ..Settings::default()
+        };
+        let err = build_client(&settings).unwrap_err();
+        assert!(err.to_string().contains("no PEM certificates"), "{}", err);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(build_client(&settings).unwrap_err().kind(), "validation");
+    }
+}
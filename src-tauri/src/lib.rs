@@ -1,10 +1,20 @@
 mod commands;
 mod db;
+mod error;
+mod events;
+mod gemini;
+mod http;
 mod models;
 pub mod paths;
+mod poller;
+mod pricing;
+mod secrets;
+mod settings;
 
 use db::Database;
-use std::time::Duration;
+use http::HttpClient;
+use secrets::Secrets;
+use settings::Settings;
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -34,19 +44,43 @@ pub fn run() {
             };
             app.handle().plugin(log_builder.build())?;
 
-            // Shared HTTP client: explicit timeouts so a stalled Gemini
-            // endpoint can't wedge a batch command forever.
-            let http = reqwest::Client::builder()
-                .connect_timeout(Duration::from_secs(10))
-                .timeout(Duration::from_secs(600))
-                .build()?;
-            app.manage(http);
+            // Retry policy for transient Gemini errors (429, 503, ...).
+            app.manage(gemini::RetryPolicy::default());
 
             // Initialize database
             let app_data_dir = app.path().app_data_dir()?;
-            let db = Database::new(app_data_dir)
+            let db = Database::new(app_data_dir.clone())
                 .map_err(|e| format!("Failed to initialize database: {}", e))?;
+
+            // Credentials are kept out of the database; move a key saved
+            // there by an earlier version.
+            let secrets = Secrets::new(app_data_dir);
+            {
+                let conn = db.conn.lock().map_err(|e| e.to_string())?;
+                let migrated = secrets::migrate_api_key(&conn, secrets.store())
+                    .and_then(|()| commands::adopt_legacy_key(&conn, secrets.store()));
+                if let Err(e) = migrated {
+                    log::error!("Could not move the API key to the secret store: {}", e);
+                }
+            }
+
+            // Shared HTTP client, built from the proxy, CA and timeout
+            // settings and rebuilt when they change. A CA bundle that has
+            // since gone missing mustn't keep the app from starting.
+            let http = {
+                let conn = db.conn.lock().map_err(|e| e.to_string())?;
+                Settings::load(&conn).and_then(|settings| http::build_client(&settings))
+            };
+            let http = match http {
+                Ok(http) => http,
+                Err(e) => {
+                    log::error!("Network settings unusable, falling back to defaults: {}", e);
+                    http::build_client(&Settings::default())?
+                }
+            };
+            app.manage(HttpClient::new(http));
             app.manage(db);
+            app.manage(secrets);
 
             // Create required directories
             let app_data_dir = app.path().app_data_dir()?;
@@ -55,6 +89,10 @@ pub fn run() {
             let pictures_dir = app.path().picture_dir()?;
             std::fs::create_dir_all(pictures_dir.join("Nana Studio")).ok();
 
+            // Batch progress is driven from here rather than the webview,
+            // so jobs advance even with the window closed or reloading.
+            tauri::async_runtime::spawn(poller::run(app.handle().clone()));
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -66,18 +104,27 @@ pub fn run() {
             commands::get_config,
             commands::save_config,
             commands::delete_config,
-            commands::get_setting,
-            commands::save_setting,
-            commands::get_all_settings,
+            commands::get_settings,
+            commands::update_settings,
             commands::get_default_results_dir,
             commands::upload_images,
             commands::get_image,
             commands::delete_upload,
             commands::submit_batch,
+            commands::generate_now,
             commands::poll_batch,
             commands::download_results,
             commands::cancel_batch,
             commands::validate_api_key,
+            commands::list_models,
+            commands::get_spend_summary,
+            commands::get_usage_report,
+            commands::export_usage_csv,
+            commands::list_profiles,
+            commands::add_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::set_active_profile,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
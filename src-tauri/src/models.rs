@@ -1,11 +1,154 @@
+use crate::pricing;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
-use rusqlite;
+
+/// Declares a fieldless enum stored as TEXT: the same spelling is used
+/// by serde, `Display`/`FromStr` and rusqlite, so the DB, the IPC payload
+/// and the frontend types can't drift apart.
+macro_rules! text_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($variant:ident => $text:literal,)+ }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum $name {
+            $(#[serde(rename = $text)] $variant,)+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant),+];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $text,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, String> {
+                match s {
+                    $($text => Ok($name::$variant),)+
+                    other => Err(format!("Unknown {}: '{}'", stringify!($name), other)),
+                }
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::from(self.as_str()))
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                value.as_str()?.parse().map_err(|e: String| FromSqlError::Other(e.into()))
+            }
+        }
+    };
+}
+
+text_enum! {
+    /// Lifecycle of a job row. Changes go through `transition_job`, which
+    /// checks `JobStatus::can_transition_to`.
+    pub enum JobStatus {
+        Pending => "pending",
+        Processing => "processing",
+        Downloading => "downloading",
+        Completed => "completed",
+        Failed => "failed",
+        Cancelled => "cancelled",
+    }
+}
+
+text_enum! {
+    pub enum ItemStatus {
+        Pending => "pending",
+        Completed => "completed",
+        Failed => "failed",
+    }
+}
+
+text_enum! {
+    pub enum JobMode {
+        TextToImage => "text-to-image",
+        ImageToImage => "image-to-image",
+    }
+}
+
+text_enum! {
+    /// How a job's requests were sent. Batch requests are billed at the
+    /// discounted batch rate; interactive ones (`generate_now`) are not.
+    pub enum JobDelivery {
+        Batch => "batch",
+        Interactive => "interactive",
+    }
+}
+
+impl JobStatus {
+    /// Every legal `(from, to)` job status change. Anything else, e.g.
+    /// completed -> downloading, is a bug and is rejected. Sub-batch rows
+    /// in `job_batches` follow the same table.
+    pub const TRANSITIONS: &'static [(JobStatus, JobStatus)] = &[
+        // submit_batch / generate_now
+        (JobStatus::Pending, JobStatus::Processing),
+        // download_results claims the job, then records the outcome
+        (JobStatus::Processing, JobStatus::Downloading),
+        (JobStatus::Downloading, JobStatus::Completed),
+        (JobStatus::Downloading, JobStatus::Failed),
+        // crash recovery on startup
+        (JobStatus::Downloading, JobStatus::Processing),
+        // batch ended without results, or the user cancelled
+        (JobStatus::Processing, JobStatus::Failed),
+        (JobStatus::Processing, JobStatus::Cancelled),
+        // the last of a split job's batches ended without results, or
+        // generate_now finished
+        (JobStatus::Processing, JobStatus::Completed),
+        (JobStatus::Pending, JobStatus::Cancelled),
+        // retry / resubmit whatever didn't complete
+        (JobStatus::Completed, JobStatus::Processing),
+        (JobStatus::Failed, JobStatus::Processing),
+        (JobStatus::Cancelled, JobStatus::Processing),
+    ];
+
+    pub fn can_transition_to(self, next: JobStatus) -> bool {
+        Self::TRANSITIONS.contains(&(self, next))
+    }
+
+    /// Submitted work the backend is still responsible for.
+    pub fn is_in_flight(self) -> bool {
+        matches!(self, JobStatus::Processing | JobStatus::Downloading)
+    }
+}
+
+/// Column list matching `Job::from_row`, for `SELECT {JOB_COLUMNS} FROM jobs`.
+pub const JOB_COLUMNS: &str = "id, status, mode, prompt, output_size, temperature, aspect_ratio,
+    batch_job_name, batch_temp_file, total_items, completed_items, failed_items,
+    created_at, updated_at, delivery, model,
+    (SELECT SUM(cost) FROM job_items WHERE job_items.job_id = jobs.id), profile_id,
+    (SELECT COUNT(*) FROM job_items
+     WHERE job_items.job_id = jobs.id AND job_items.delivery = 'interactive')";
+
+/// Column list matching `JobItem::from_row`.
+pub const JOB_ITEM_COLUMNS: &str = "id, job_id, input_prompt, input_image_path, output_image_path,
+    status, error, created_at, updated_at, response_text, finish_reason, block_reason,
+    safety_ratings, prompt_tokens, text_output_tokens, image_output_tokens, thoughts_tokens,
+    cost";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
     pub id: String,
-    pub status: String,
-    pub mode: String,
+    pub status: JobStatus,
+    pub mode: JobMode,
+    pub delivery: JobDelivery,
+    /// Gemini model id the job generates with, fixed at creation.
+    pub model: String,
     pub prompt: String,
     pub output_size: String,
     pub temperature: f64,
@@ -17,6 +160,13 @@ pub struct Job {
     pub failed_items: i32,
     pub created_at: String,
     pub updated_at: String,
+    /// What the job should cost per `pricing::PRICING`, or `None` for a
+    /// model missing from the table.
+    pub estimated_cost: Option<f64>,
+    /// Sum of what its items' responses billed, once any have come back.
+    pub actual_cost: Option<f64>,
+    /// Key profile that submitted the job; `None` until it is submitted.
+    pub profile_id: Option<String>,
 }
 
 impl Job {
@@ -36,7 +186,22 @@ impl Job {
             failed_items: row.get(11)?,
             created_at: row.get(12)?,
             updated_at: row.get(13)?,
-        })
+            delivery: row.get(14)?,
+            model: row.get(15)?,
+            estimated_cost: None,
+            actual_cost: row.get(16)?,
+            profile_id: row.get(17)?,
+        }
+        .with_estimate(row.get(18)?))
+    }
+
+    /// Fills in `estimated_cost` from the job's model, size and item
+    /// count, `interactive_items` of them at the full rate.
+    pub fn with_estimate(mut self, interactive_items: i64) -> Job {
+        self.estimated_cost = pricing::model_pricing(&self.model).and_then(|p| {
+            p.images_cost(&self.output_size, i64::from(self.total_items), interactive_items)
+        });
+        self
     }
 }
 
@@ -47,16 +212,166 @@ pub struct JobItem {
     pub input_prompt: Option<String>,
     pub input_image_path: Option<String>,
     pub output_image_path: Option<String>,
-    pub status: String,
+    pub status: ItemStatus,
     pub error: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// The response's text parts: commentary next to the image, or the
+    /// model's explanation when it declined to draw.
+    pub response_text: Option<String>,
+    /// `finishReason` of the first candidate, e.g. `STOP` or `IMAGE_SAFETY`.
+    pub finish_reason: Option<String>,
+    /// `promptFeedback.blockReason`: the prompt itself was blocked, so no
+    /// candidates came back.
+    pub block_reason: Option<String>,
+    pub safety_ratings: SafetyRatings,
+    /// Tokens its responses billed, every attempt included, once any
+    /// has come back.
+    pub usage: Option<TokenUsage>,
+    /// What `usage` costs in USD, if the job's model is priced.
+    pub cost: Option<f64>,
+    /// Every image the item produced, in response order;
+    /// `output_image_path` is the first of them. Not part of the row:
+    /// `from_row` leaves it empty for the loaders to fill.
+    pub outputs: Vec<JobItemOutput>,
+}
+
+impl JobItem {
+    pub fn from_row(row: &rusqlite::Row) -> rusqlite::Result<JobItem> {
+        Ok(JobItem {
+            id: row.get(0)?,
+            job_id: row.get(1)?,
+            input_prompt: row.get(2)?,
+            input_image_path: row.get(3)?,
+            output_image_path: row.get(4)?,
+            status: row.get(5)?,
+            error: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+            response_text: row.get(9)?,
+            finish_reason: row.get(10)?,
+            block_reason: row.get(11)?,
+            safety_ratings: row.get(12)?,
+            usage: match row.get::<_, Option<i64>>(13)? {
+                Some(prompt_tokens) => Some(TokenUsage {
+                    prompt_tokens,
+                    text_output_tokens: row.get(14)?,
+                    image_output_tokens: row.get(15)?,
+                    thoughts_tokens: row.get(16)?,
+                }),
+                None => None,
+            },
+            cost: row.get(17)?,
+            outputs: Vec::new(),
+        })
+    }
+}
+
+/// A response's `usageMetadata`, with output split by modality since
+/// image tokens bill at a different rate than text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: i64,
+    pub text_output_tokens: i64,
+    pub image_output_tokens: i64,
+    pub thoughts_tokens: i64,
+}
+
+/// One entry of a candidate's (or the prompt's) `safetyRatings`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafetyRating {
+    pub category: String,
+    #[serde(default)]
+    pub probability: String,
+    /// Whether this category is what blocked the response.
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// An item's safety ratings, stored as a JSON array.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SafetyRatings(pub Vec<SafetyRating>);
+
+impl ToSql for SafetyRatings {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(&self.0)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        Ok(ToSqlOutput::from(json))
+    }
+}
+
+impl FromSql for SafetyRatings {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+/// Column list matching `JobItemOutput::from_row`.
+pub const JOB_ITEM_OUTPUT_COLUMNS: &str = "item_id, candidate_index, path, mime_type, created_at";
+
+/// One saved image of an item. `candidate_index` is the position of the
+/// response candidate it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobItemOutput {
+    pub item_id: String,
+    pub candidate_index: i32,
+    pub path: String,
+    pub mime_type: String,
+    pub created_at: String,
+}
+
+impl JobItemOutput {
+    pub fn from_row(row: &rusqlite::Row) -> rusqlite::Result<JobItemOutput> {
+        Ok(JobItemOutput {
+            item_id: row.get(0)?,
+            candidate_index: row.get(1)?,
+            path: row.get(2)?,
+            mime_type: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }
+}
+
+/// Column list matching `JobBatch::from_row`.
+pub const JOB_BATCH_COLUMNS: &str = "batch_name, job_id, status, item_count,
+    completed_requests, failed_requests, created_at, updated_at";
+
+/// One Gemini batch submitted for a job. Large jobs are split across
+/// several; `completed_requests`/`failed_requests` are the latest stats
+/// reported while the batch is in flight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobBatch {
+    pub batch_name: String,
+    pub job_id: String,
+    pub status: JobStatus,
+    pub item_count: i32,
+    pub completed_requests: i32,
+    pub failed_requests: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl JobBatch {
+    pub fn from_row(row: &rusqlite::Row) -> rusqlite::Result<JobBatch> {
+        Ok(JobBatch {
+            batch_name: row.get(0)?,
+            job_id: row.get(1)?,
+            status: row.get(2)?,
+            item_count: row.get(3)?,
+            completed_requests: row.get(4)?,
+            failed_requests: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobWithItems {
     pub job: Job,
     pub items: Vec<JobItem>,
+    pub batches: Vec<JobBatch>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +380,9 @@ pub struct CreateT2IJobRequest {
     pub output_size: String,
     pub temperature: f64,
     pub aspect_ratio: String,
+    /// Defaults to the `default_model` setting.
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +392,74 @@ pub struct CreateI2IJobRequest {
     pub output_size: String,
     pub temperature: f64,
     pub aspect_ratio: String,
+    /// Defaults to the `default_model` setting.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// An image-capable model as offered by `list_models`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageModel {
+    /// Model id without the `models/` prefix, as stored on jobs.
+    pub id: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    /// Whether the model accepts `batchGenerateContent`.
+    pub supports_batch: bool,
+}
+
+/// Spend so far this day and month (local time), in USD, against the
+/// configured budgets. A budget of `None` is unlimited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpendSummary {
+    /// Recorded item costs since local midnight.
+    pub spent_today: f64,
+    /// Recorded item costs since the first of the month.
+    pub spent_this_month: f64,
+    /// Estimated cost of items already submitted but not yet billed,
+    /// counted against both periods.
+    pub in_flight: f64,
+    pub daily_budget: Option<f64>,
+    pub monthly_budget: Option<f64>,
+    /// What is left of each budget after spend and in-flight items,
+    /// never below zero.
+    pub daily_remaining: Option<f64>,
+    pub monthly_remaining: Option<f64>,
+}
+
+/// One line of the usage report: the jobs created on `day` (local time)
+/// with the same model, output size and mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageRow {
+    /// `YYYY-MM-DD`.
+    pub day: String,
+    pub model: String,
+    pub output_size: String,
+    pub mode: JobMode,
+    pub jobs: i64,
+    pub items: i64,
+    pub completed_items: i64,
+    pub failed_items: i64,
+    /// Share of `items` that completed, and that failed; items still
+    /// pending count towards neither.
+    pub success_rate: f64,
+    pub failure_rate: f64,
+    /// From the pricing table; `None` for a model it doesn't list.
+    pub estimated_cost: Option<f64>,
+    /// Sum of recorded item costs; `None` until any item has one.
+    pub actual_cost: Option<f64>,
+}
+
+/// A named API key. The key itself never leaves the backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyProfile {
+    pub id: String,
+    pub name: String,
+    /// Masked like `ConfigStatus::masked`; `None` if no key is stored.
+    pub masked: Option<String>,
+    /// Whether new submissions use this profile.
+    pub active: bool,
+    pub created_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,6 +468,35 @@ pub struct ConfigStatus {
     pub masked: Option<String>,
 }
 
+/// What checking an API key found. Everything describes the requested
+/// model except `models`, which covers each model the app knows about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyValidation {
+    /// The key can use the requested model.
+    pub valid: bool,
+    /// The API answered at all; `false` means a network problem, not a
+    /// bad key.
+    pub reachable: bool,
+    pub http_status: Option<u16>,
+    /// Google's reason for a refusal, e.g. `API_KEY_INVALID`,
+    /// `SERVICE_DISABLED` or `RESOURCE_EXHAUSTED`.
+    pub reason: Option<String>,
+    /// The API's error message, or the network error.
+    pub message: Option<String>,
+    pub latency_ms: u64,
+    pub models: Vec<ModelAccess>,
+}
+
+/// Whether the key can use one model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelAccess {
+    pub model: String,
+    pub accessible: bool,
+    pub http_status: Option<u16>,
+    pub reason: Option<String>,
+    pub latency_ms: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadedFile {
     pub id: String,
@@ -89,10 +504,194 @@ pub struct UploadedFile {
     pub name: String,
 }
 
+/// Lifecycle state of a Gemini batch, serialized as the API's
+/// `JOB_STATE_*` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchState {
+    #[serde(rename = "JOB_STATE_PENDING")]
+    Pending,
+    #[serde(rename = "JOB_STATE_RUNNING")]
+    Running,
+    #[serde(rename = "JOB_STATE_SUCCEEDED")]
+    Succeeded,
+    #[serde(rename = "JOB_STATE_FAILED")]
+    Failed,
+    #[serde(rename = "JOB_STATE_CANCELLED")]
+    Cancelled,
+    #[serde(rename = "JOB_STATE_EXPIRED")]
+    Expired,
+}
+
+/// What a batch that ended without results does to its job: the job's
+/// new status, and the error recorded on items that never ran. `None`
+/// leaves those items `pending` so the job can be resubmitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchSettlement {
+    pub job_status: JobStatus,
+    pub item_error: Option<&'static str>,
+}
+
+impl BatchState {
+    /// Parses the API's state string. Accepts both the `JOB_STATE_` and
+    /// `BATCH_STATE_` spellings; anything unrecognised (including
+    /// `*_UNSPECIFIED`) is treated as still pending.
+    pub fn parse(state: &str) -> BatchState {
+        let name = state
+            .strip_prefix("JOB_STATE_")
+            .or_else(|| state.strip_prefix("BATCH_STATE_"))
+            .unwrap_or(state);
+        match name {
+            "RUNNING" => BatchState::Running,
+            "SUCCEEDED" => BatchState::Succeeded,
+            "FAILED" => BatchState::Failed,
+            "CANCELLED" => BatchState::Cancelled,
+            "EXPIRED" => BatchState::Expired,
+            _ => BatchState::Pending,
+        }
+    }
+
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, BatchState::Pending | BatchState::Running)
+    }
+
+    /// Transition for a terminal state that produced no results.
+    /// `Succeeded` goes through `download_results` instead, and in-flight
+    /// states don't move the job, so both return `None`.
+    pub fn settlement(self) -> Option<BatchSettlement> {
+        match self {
+            BatchState::Pending | BatchState::Running | BatchState::Succeeded => None,
+            BatchState::Failed => Some(BatchSettlement {
+                job_status: JobStatus::Failed,
+                item_error: Some("Batch failed"),
+            }),
+            BatchState::Expired => Some(BatchSettlement {
+                job_status: JobStatus::Failed,
+                item_error: Some("Batch expired before completing"),
+            }),
+            BatchState::Cancelled => Some(BatchSettlement {
+                job_status: JobStatus::Cancelled,
+                item_error: None,
+            }),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchStatus {
-    pub state: String,
+    pub state: BatchState,
     pub total_requests: i32,
     pub completed_requests: i32,
     pub failed_requests: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_state_parses_both_prefixes() {
+        assert_eq!(BatchState::parse("JOB_STATE_RUNNING"), BatchState::Running);
+        assert_eq!(BatchState::parse("BATCH_STATE_SUCCEEDED"), BatchState::Succeeded);
+        assert_eq!(BatchState::parse("JOB_STATE_EXPIRED"), BatchState::Expired);
+        assert_eq!(BatchState::parse("JOB_STATE_UNSPECIFIED"), BatchState::Pending);
+        assert_eq!(BatchState::parse(""), BatchState::Pending);
+    }
+
+    #[test]
+    fn batch_state_serializes_as_api_name() {
+        assert_eq!(
+            serde_json::to_value(BatchState::Cancelled).unwrap(),
+            serde_json::json!("JOB_STATE_CANCELLED")
+        );
+    }
+
+    #[test]
+    fn in_flight_and_succeeded_states_do_not_settle() {
+        for state in [BatchState::Pending, BatchState::Running, BatchState::Succeeded] {
+            assert_eq!(state.settlement(), None, "{:?}", state);
+        }
+        assert!(!BatchState::Running.is_terminal());
+        assert!(BatchState::Succeeded.is_terminal());
+    }
+
+    #[test]
+    fn failed_batch_fails_job_and_items() {
+        let s = BatchState::Failed.settlement().unwrap();
+        assert_eq!(s.job_status, JobStatus::Failed);
+        assert_eq!(s.item_error, Some("Batch failed"));
+    }
+
+    #[test]
+    fn expired_batch_fails_job_and_items() {
+        let s = BatchState::Expired.settlement().unwrap();
+        assert_eq!(s.job_status, JobStatus::Failed);
+        assert_eq!(s.item_error, Some("Batch expired before completing"));
+    }
+
+    #[test]
+    fn cancelled_batch_keeps_items_pending() {
+        let s = BatchState::Cancelled.settlement().unwrap();
+        assert_eq!(s.job_status, JobStatus::Cancelled);
+        assert_eq!(s.item_error, None);
+    }
+
+    #[test]
+    fn text_enums_round_trip_through_strings() {
+        for status in JobStatus::ALL {
+            assert_eq!(status.as_str().parse::<JobStatus>().unwrap(), *status);
+            assert_eq!(
+                serde_json::to_value(status).unwrap(),
+                serde_json::json!(status.as_str())
+            );
+        }
+        assert_eq!("image-to-image".parse::<JobMode>().unwrap(), JobMode::ImageToImage);
+        assert_eq!("interactive".parse::<JobDelivery>().unwrap(), JobDelivery::Interactive);
+        assert!("downloading".parse::<ItemStatus>().is_err());
+    }
+
+    #[test]
+    fn text_enums_round_trip_through_sqlite() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let back: (JobStatus, JobMode) = conn
+            .query_row(
+                "SELECT ?1, ?2",
+                rusqlite::params![JobStatus::Downloading, JobMode::TextToImage],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(back, (JobStatus::Downloading, JobMode::TextToImage));
+
+        let bad: rusqlite::Result<ItemStatus> = conn.query_row("SELECT 'bogus'", [], |r| r.get(0));
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn job_transitions_allow_the_normal_lifecycle() {
+        use JobStatus::*;
+        for (from, to) in [
+            (Pending, Processing),
+            (Processing, Downloading),
+            (Downloading, Completed),
+            (Downloading, Processing),
+            (Processing, Cancelled),
+            (Failed, Processing),
+        ] {
+            assert!(from.can_transition_to(to), "{} -> {}", from, to);
+        }
+    }
+
+    #[test]
+    fn job_transitions_reject_illegal_moves() {
+        use JobStatus::*;
+        for (from, to) in [
+            (Completed, Downloading),
+            (Pending, Downloading),
+            (Pending, Completed),
+            (Cancelled, Completed),
+            (Downloading, Cancelled),
+            (Processing, Processing),
+        ] {
+            assert!(!from.can_transition_to(to), "{} -> {}", from, to);
+        }
+    }
+}
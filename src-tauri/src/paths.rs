@@ -1,66 +1,82 @@
+use crate::commands::{active_profile_id, profile_secret};
 use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::secrets::get_secrets;
+use crate::settings::load_settings;
+use rusqlite::{params, OptionalExtension};
 use std::path::PathBuf;
-use tauri::{AppHandle, Manager};
-
-pub fn get_uploads_dir(app: &AppHandle) -> Result<PathBuf, String> {
-    let db = get_db(app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let custom: Option<String> = conn
-        .query_row(
-            "SELECT value FROM config WHERE key = 'uploads_dir'",
-            [],
-            |row| row.get(0),
-        )
-        .ok();
-    let raw = if let Some(dir) = custom.filter(|d| !d.is_empty()) {
-        PathBuf::from(dir)
-    } else {
-        app.path()
-            .app_data_dir()
-            .map_err(|e| e.to_string())?
-            .join("uploads")
+use tauri::{AppHandle, Manager, Runtime};
+
+pub fn get_uploads_dir<R: Runtime>(app: &AppHandle<R>) -> AppResult<PathBuf> {
+    let raw = match load_settings(app)?.uploads_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => app.path().app_data_dir()?.join("uploads"),
     };
-    std::fs::create_dir_all(&raw).map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&raw)?;
     // Canonicalize so callers can safely starts_with() compare against
     // canonicalized input paths. On macOS this resolves /var -> /private/var.
-    raw.canonicalize().map_err(|e| e.to_string())
+    Ok(raw.canonicalize()?)
+}
+
+pub fn get_results_dir<R: Runtime>(app: &AppHandle<R>) -> AppResult<PathBuf> {
+    let path = match load_settings(app)?.results_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => app.path().picture_dir()?.join("Nana Studio"),
+    };
+    std::fs::create_dir_all(&path)?;
+    Ok(path)
 }
 
-pub fn get_results_dir(app: &AppHandle) -> Result<PathBuf, String> {
-    let db = get_db(app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    let custom: Option<String> = conn
-        .query_row(
-            "SELECT value FROM config WHERE key = 'results_dir'",
-            [],
+/// Key of the active profile, which new submissions use.
+pub fn get_api_key<R: Runtime>(app: &AppHandle<R>) -> AppResult<String> {
+    let active = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        active_profile_id(&conn)?
+    };
+    match active {
+        Some(id) => get_profile_api_key(app, &id),
+        None => Err(AppError::Auth("API key not configured".to_string())),
+    }
+}
+
+/// Key of the profile that submitted `job_id`, so its batches are
+/// followed up with the key that created them. Jobs with no recorded
+/// profile (not submitted yet, or from before profiles) use the active one.
+pub fn get_job_api_key<R: Runtime>(app: &AppHandle<R>, job_id: &str) -> AppResult<String> {
+    let profile: Option<String> = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.query_row(
+            "SELECT profile_id FROM jobs WHERE id = ?1",
+            params![job_id],
             |row| row.get(0),
         )
-        .ok();
-    if let Some(dir) = custom {
-        if !dir.is_empty() {
-            let path = PathBuf::from(dir);
-            std::fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-            return Ok(path);
-        }
+        .optional()?
+        .flatten()
+    };
+    match profile {
+        Some(id) => get_profile_api_key(app, &id),
+        None => get_api_key(app),
     }
-    let default = app
-        .path()
-        .picture_dir()
-        .map_err(|e| e.to_string())?
-        .join("Nana Studio");
-    std::fs::create_dir_all(&default).map_err(|e| e.to_string())?;
-    Ok(default)
 }
 
-pub fn get_api_key(app: &AppHandle) -> Result<String, String> {
-    let db = get_db(app);
-    let conn = db.conn.lock().map_err(|e| e.to_string())?;
-    conn.query_row(
-        "SELECT value FROM config WHERE key = 'gemini_api_key'",
-        [],
-        |row| row.get::<_, String>(0),
-    )
-    .map_err(|_| "API key not configured".to_string())
+fn get_profile_api_key<R: Runtime>(app: &AppHandle<R>, profile_id: &str) -> AppResult<String> {
+    get_secrets(app)
+        .get(&profile_secret(profile_id))?
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| AppError::Auth("API key not configured".to_string()))
+}
+
+/// Gemini API base URL: the `gemini_base_url` setting, Google's public
+/// endpoint unless changed.
+pub fn get_gemini_base_url<R: Runtime>(app: &AppHandle<R>) -> AppResult<String> {
+    Ok(load_settings(app)?.gemini_base_url)
+}
+
+/// Image model for new jobs: the `default_model` setting.
+pub fn get_default_model<R: Runtime>(app: &AppHandle<R>) -> AppResult<String> {
+    Ok(load_settings(app)?.default_model)
 }
 
 /// MIME type from file extension. Default is `image/png` to match Gemini API expectations.
@@ -74,9 +90,11 @@ pub fn mime_from_ext(ext: &str) -> &'static str {
     }
 }
 
-pub fn validate_batch_name(name: &str) -> Result<(), String> {
+pub fn validate_batch_name(name: &str) -> AppResult<()> {
     if !name.starts_with("batches/") || name.contains("..") || name.contains("://") {
-        return Err("Invalid batch name format".to_string());
+        return Err(AppError::Validation(
+            "Invalid batch name format".to_string(),
+        ));
     }
     Ok(())
 }
@@ -0,0 +1,269 @@
+use crate::commands::{batch_status, download_results, load_job, settle_batch, submit_job};
+use crate::db::get_db;
+use crate::error::AppResult;
+use crate::events;
+use crate::gemini::{BatchLimits, GeminiClient};
+use crate::models::{BatchState, JobStatus};
+use crate::paths::validate_batch_name;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Runtime};
+
+/// How often the loop wakes to look for jobs that are due.
+const TICK: Duration = Duration::from_secs(2);
+/// Interval between polls of a healthy batch.
+const BASE_INTERVAL: Duration = Duration::from_secs(10);
+/// Ceiling for the per-job backoff after consecutive poll failures.
+const MAX_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Delay before the next poll after `failures` consecutive errors:
+/// 10s, 20s, 40s, ... capped at 5 minutes.
+fn next_delay(failures: u32) -> Duration {
+    BASE_INTERVAL
+        .saturating_mul(2u32.saturating_pow(failures))
+        .min(MAX_INTERVAL)
+}
+
+#[derive(Default)]
+struct Schedule {
+    next_due: Option<Instant>,
+    failures: u32,
+}
+
+/// Background task, spawned once from `lib.rs` setup. Watches the
+/// batches of jobs in `processing`, polls each with its own backoff,
+/// downloads on success and settles terminal states, so progress no
+/// longer depends on a webview being open. Submissions cut off by the
+/// last shutdown are picked up first.
+pub async fn run<R: Runtime>(app: AppHandle<R>) {
+    resume_uploads(&app).await;
+
+    let mut schedule: HashMap<String, Schedule> = HashMap::new();
+    loop {
+        tokio::time::sleep(TICK).await;
+
+        let batches = match processing_batches(&app) {
+            Ok(batches) => batches,
+            Err(e) => {
+                log::warn!("Poller could not list processing batches: {}", e);
+                continue;
+            }
+        };
+        schedule.retain(|name, _| batches.iter().any(|(_, batch_name)| batch_name == name));
+
+        for (job_id, batch_name) in batches {
+            let entry = schedule.entry(batch_name.clone()).or_default();
+            if entry.next_due.is_some_and(|due| due > Instant::now()) {
+                continue;
+            }
+            match poll_job(&app, &job_id, &batch_name).await {
+                Ok(()) => entry.failures = 0,
+                Err(e) => {
+                    entry.failures = entry.failures.saturating_add(1);
+                    log::warn!("Polling {} for job {} failed: {}", batch_name, job_id, e);
+                }
+            }
+            entry.next_due = Some(Instant::now() + next_delay(entry.failures));
+        }
+    }
+}
+
+/// Resubmits every job that has an upload session left open, which
+/// finishes that upload and then submits the job's remaining items.
+async fn resume_uploads<R: Runtime>(app: &AppHandle<R>) {
+    let job_ids = match interrupted_uploads(app) {
+        Ok(ids) => ids,
+        Err(e) => {
+            log::warn!("Poller could not list interrupted uploads: {}", e);
+            return;
+        }
+    };
+    for job_id in job_ids {
+        if let Err(e) = submit_job(app, &job_id, BatchLimits::default()).await {
+            log::warn!("Resuming the upload of job {} failed: {}", job_id, e);
+        }
+    }
+}
+
+fn interrupted_uploads<R: Runtime>(app: &AppHandle<R>) -> AppResult<Vec<String>> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare("SELECT job_id FROM job_uploads ORDER BY created_at")?;
+    let job_ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(job_ids)
+}
+
+/// `(job_id, batch_name)` for every in-flight batch of a processing job.
+fn processing_batches<R: Runtime>(app: &AppHandle<R>) -> AppResult<Vec<(String, String)>> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    let mut stmt = conn.prepare(
+        "SELECT b.job_id, b.batch_name FROM job_batches b JOIN jobs j ON j.id = b.job_id
+             WHERE b.status = ?1 AND j.status = ?1
+             ORDER BY b.created_at, b.batch_name",
+    )?;
+    let jobs = stmt
+        .query_map(params![JobStatus::Processing], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(jobs)
+}
+
+/// One poll of one of a job's batches: fetch it, then download, settle,
+/// or report progress depending on its state.
+pub(crate) async fn poll_job<R: Runtime>(
+    app: &AppHandle<R>,
+    job_id: &str,
+    batch_name: &str,
+) -> AppResult<()> {
+    validate_batch_name(batch_name)?;
+    let batch = GeminiClient::for_job(app, job_id)?.get_batch(batch_name).await?;
+    let status = batch_status(&batch);
+
+    if status.state == BatchState::Succeeded {
+        return download_results(app.clone(), batch_name.to_string(), job_id.to_string()).await;
+    }
+    if let Some(settlement) = status.state.settlement() {
+        settle_batch(app, batch_name, settlement)?;
+        events::emit_job(app, job_id);
+        return Ok(());
+    }
+
+    // The job's own counts only cover items already downloaded; overlay
+    // the latest stats of every batch still in flight.
+    let job = {
+        let db = get_db(app);
+        let conn = db.conn.lock()?;
+        conn.execute(
+            "UPDATE job_batches SET completed_requests = ?1, failed_requests = ?2, updated_at = ?3
+             WHERE batch_name = ?4",
+            params![
+                status.completed_requests,
+                status.failed_requests,
+                chrono::Utc::now().to_rfc3339(),
+                batch_name
+            ],
+        )?;
+        let mut job = load_job(&conn, job_id)?;
+        let (completed, failed): (i32, i32) = conn.query_row(
+            "SELECT COALESCE(SUM(completed_requests), 0), COALESCE(SUM(failed_requests), 0)
+                 FROM job_batches WHERE job_id = ?1 AND status = ?2",
+            params![job_id, JobStatus::Processing],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        job.completed_items += completed;
+        job.failed_items += failed;
+        job
+    };
+    events::emit_job_value(app, &job);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{get_job, submit_batch};
+    use crate::gemini::mock::{t2i_job, MockGemini};
+    use crate::models::ItemStatus;
+    use tauri::test::MockRuntime;
+
+    #[test]
+    fn next_delay_backs_off_and_caps() {
+        assert_eq!(next_delay(0), Duration::from_secs(10));
+        assert_eq!(next_delay(1), Duration::from_secs(20));
+        assert_eq!(next_delay(3), Duration::from_secs(80));
+        assert_eq!(next_delay(5), MAX_INTERVAL);
+        assert_eq!(next_delay(u32::MAX), MAX_INTERVAL);
+    }
+
+    async fn submitted_job(app: &AppHandle<MockRuntime>) -> (String, String) {
+        let created = t2i_job(app, &["one", "two"], "1K");
+        submit_batch(app.clone(), created.job.id.clone(), None).await.unwrap();
+        let job = get_job(app.clone(), created.job.id).unwrap().job;
+        (job.id, job.batch_job_name.unwrap())
+    }
+
+    #[tokio::test]
+    async fn poll_job_downloads_on_success() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING", "JOB_STATE_SUCCEEDED"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let (job_id, batch_name) = submitted_job(&app).await;
+
+        poll_job(&app, &job_id, &batch_name).await.unwrap();
+        assert_eq!(get_job(app.clone(), job_id.clone()).unwrap().job.status, JobStatus::Processing);
+
+        poll_job(&app, &job_id, &batch_name).await.unwrap();
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Completed);
+        assert!(done.items.iter().all(|i| i.output_image_path.is_some()));
+    }
+
+    #[tokio::test]
+    async fn startup_resumes_interrupted_uploads() {
+        let mock = MockGemini::start().await;
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let job_id = t2i_job(&app, &["one"], "1K").job.id;
+        mock.state.lock().unwrap().fail_upload_chunks = usize::MAX;
+        submit_batch(app.clone(), job_id.clone(), None).await.unwrap_err();
+        assert_eq!(interrupted_uploads(&app).unwrap(), vec![job_id.clone()]);
+
+        mock.state.lock().unwrap().fail_upload_chunks = 0;
+
+        resume_uploads(&app).await;
+        assert!(interrupted_uploads(&app).unwrap().is_empty());
+        let job = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(job.job.status, JobStatus::Processing);
+        assert_eq!(job.batches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_job_fails_expired_batch() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_EXPIRED"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let (job_id, batch_name) = submitted_job(&app).await;
+
+        poll_job(&app, &job_id, &batch_name).await.unwrap();
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Failed);
+        assert_eq!(done.job.failed_items, 2);
+        assert!(done
+            .items
+            .iter()
+            .all(|i| i.error.as_deref() == Some("Batch expired before completing")));
+    }
+
+    #[tokio::test]
+    async fn poll_job_cancelled_batch_keeps_items_pending() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_CANCELLED"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let (job_id, batch_name) = submitted_job(&app).await;
+
+        poll_job(&app, &job_id, &batch_name).await.unwrap();
+        let done = get_job(app.clone(), job_id).unwrap();
+        assert_eq!(done.job.status, JobStatus::Cancelled);
+        assert!(done.items.iter().all(|i| i.status == ItemStatus::Pending));
+    }
+
+    #[tokio::test]
+    async fn processing_batches_lists_only_submitted_processing() {
+        let mock = MockGemini::start().await;
+        mock.script(&["JOB_STATE_RUNNING"]);
+        let test_app = mock.app();
+        let app = test_app.handle();
+        let (job_id, batch_name) = submitted_job(&app).await;
+        t2i_job(&app, &["unsubmitted"], "1K");
+
+        assert_eq!(processing_batches(&app).unwrap(), vec![(job_id, batch_name)]);
+    }
+}
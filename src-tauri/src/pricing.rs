@@ -0,0 +1,124 @@
+use crate::gemini::OUTPUT_SIZES;
+use crate::models::{JobDelivery, TokenUsage};
+
+/// Batch jobs bill at this fraction of the standard (interactive) rate.
+pub const BATCH_DISCOUNT: f64 = 0.5;
+
+/// Google's list prices for one image model, in USD per million tokens at
+/// the standard rate. Update alongside the published pricing page.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub model: &'static str,
+    /// Prompt tokens, text and image input alike.
+    pub input: f64,
+    /// Text output, thinking included.
+    pub text_output: f64,
+    pub image_output: f64,
+    /// Output tokens one image bills as, per `OUTPUT_SIZES` entry.
+    pub image_tokens: [u32; 4],
+}
+
+pub const PRICING: &[ModelPricing] = &[
+    ModelPricing {
+        model: "gemini-3.1-flash-image-preview",
+        input: 0.5,
+        text_output: 3.0,
+        image_output: 60.0,
+        image_tokens: [747, 1120, 1680, 2520],
+    },
+    ModelPricing {
+        model: "gemini-3-pro-image-preview",
+        input: 2.0,
+        text_output: 12.0,
+        image_output: 120.0,
+        image_tokens: [1120, 1120, 1120, 2000],
+    },
+    ModelPricing {
+        model: "gemini-2.5-flash-image",
+        input: 0.3,
+        text_output: 2.5,
+        image_output: 30.0,
+        image_tokens: [1290, 1290, 1290, 1290],
+    },
+];
+
+/// Pricing for `model`, or `None` for a model not in the table, whose
+/// cost is then left unknown rather than guessed.
+pub fn model_pricing(model: &str) -> Option<&'static ModelPricing> {
+    let model = model.strip_prefix("models/").unwrap_or(model);
+    PRICING.iter().find(|p| p.model == model)
+}
+
+fn rate(delivery: JobDelivery) -> f64 {
+    match delivery {
+        JobDelivery::Batch => BATCH_DISCOUNT,
+        JobDelivery::Interactive => 1.0,
+    }
+}
+
+impl ModelPricing {
+    /// What the tokens of one response bill as.
+    pub fn cost(&self, usage: &TokenUsage, delivery: JobDelivery) -> f64 {
+        let dollars = usage.prompt_tokens as f64 * self.input
+            + (usage.text_output_tokens + usage.thoughts_tokens) as f64 * self.text_output
+            + usage.image_output_tokens as f64 * self.image_output;
+        dollars / 1_000_000.0 * rate(delivery)
+    }
+
+    /// Expected cost of one image of `output_size`, output tokens only:
+    /// the prompt and any text are small next to the image.
+    pub fn image_cost(&self, output_size: &str, delivery: JobDelivery) -> Option<f64> {
+        let index = OUTPUT_SIZES.iter().position(|s| *s == output_size)?;
+        let tokens = self.image_tokens[index] as f64;
+        Some(tokens * self.image_output / 1_000_000.0 * rate(delivery))
+    }
+
+    /// Expected cost of `items` images of `output_size`, `interactive` of
+    /// them sent with `generate_now` and the rest in batches.
+    pub fn images_cost(&self, output_size: &str, items: i64, interactive: i64) -> Option<f64> {
+        let batch = self.image_cost(output_size, JobDelivery::Batch)?;
+        let full = self.image_cost(output_size, JobDelivery::Interactive)?;
+        Some(batch * (items - interactive) as f64 + full * interactive as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn image_cost_matches_published_batch_prices() {
+        let flash = model_pricing("gemini-3.1-flash-image-preview").unwrap();
+        let batch = |size| flash.image_cost(size, JobDelivery::Batch).unwrap();
+        assert!(close(batch("0.5K"), 0.02241));
+        assert!(close(batch("1K"), 0.0336));
+        assert!(close(batch("2K"), 0.0504));
+        assert!(close(batch("4K"), 0.0756));
+        assert!(close(flash.image_cost("1K", JobDelivery::Interactive).unwrap(), 0.0672));
+        assert_eq!(flash.image_cost("8K", JobDelivery::Batch), None);
+        assert!(close(flash.images_cost("1K", 3, 1).unwrap(), 0.0336 * 2.0 + 0.0672));
+    }
+
+    #[test]
+    fn cost_bills_each_token_kind_at_its_rate() {
+        let pro = model_pricing("models/gemini-3-pro-image-preview").unwrap();
+        let usage = TokenUsage {
+            prompt_tokens: 1_000,
+            text_output_tokens: 200,
+            image_output_tokens: 1_120,
+            thoughts_tokens: 300,
+        };
+        // 1000 * $2 + 500 * $12 + 1120 * $120, per million.
+        assert!(close(pro.cost(&usage, JobDelivery::Interactive), 0.1424));
+        assert!(close(pro.cost(&usage, JobDelivery::Batch), 0.0712));
+    }
+
+    #[test]
+    fn unknown_models_have_no_pricing() {
+        assert!(model_pricing("gemini-3.1-pro-preview").is_none());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::error::{AppError, AppResult};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Name of the lone Gemini API key of earlier versions, in the `config`
+/// row and then the secret store; profile keys are stored under it plus
+/// the profile id.
+pub const API_KEY_SECRET: &str = "gemini_api_key";
+
+/// Keyring service the app's credentials are filed under.
+const KEYRING_SERVICE: &str = "com.nanobanana.studio";
+
+const KEY_FILE: &str = "secrets.key";
+const DATA_FILE: &str = "secrets.enc";
+const NONCE_LEN: usize = 12;
+
+/// Somewhere to keep credentials out of the SQLite database.
+pub trait SecretStore: Send + Sync {
+    fn get(&self, name: &str) -> AppResult<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> AppResult<()>;
+    /// Removing a secret that isn't there is not an error.
+    fn delete(&self, name: &str) -> AppResult<()>;
+}
+
+/// The OS credential store: Keychain, Windows Credential Manager, or the
+/// Secret Service on Linux.
+pub struct KeyringStore {
+    service: String,
+}
+
+fn keyring_error(e: keyring::Error) -> AppError {
+    AppError::Io(format!("Credential store error: {}", e))
+}
+
+impl KeyringStore {
+    /// The store for `service`, or `None` when the platform has no usable
+    /// one, e.g. a headless Linux session without a Secret Service.
+    pub fn open(service: &str) -> Option<Self> {
+        let probe = keyring::Entry::new(service, "probe").ok()?;
+        match probe.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Some(KeyringStore {
+                service: service.to_string(),
+            }),
+            Err(e) => {
+                log::info!("OS credential store unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    fn entry(&self, name: &str) -> AppResult<keyring::Entry> {
+        keyring::Entry::new(&self.service, name).map_err(keyring_error)
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, name: &str) -> AppResult<Option<String>> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> AppResult<()> {
+        self.entry(name)?.set_password(value).map_err(keyring_error)
+    }
+
+    fn delete(&self, name: &str) -> AppResult<()> {
+        match self.entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(keyring_error(e)),
+        }
+    }
+}
+
+/// Fallback when there is no OS store: all secrets in one file, sealed
+/// with ChaCha20-Poly1305 under a random key kept beside it (mode 0600 on
+/// Unix). This keeps keys out of the database, its backups and anything
+/// that copies it, but not from someone who can read the app data dir.
+pub struct EncryptedFileStore {
+    dir: PathBuf,
+    /// Serializes the read-modify-write of `set` and `delete`.
+    lock: Mutex<()>,
+}
+
+impl EncryptedFileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        EncryptedFileStore {
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The cipher under the saved key, or `None` before anything is set.
+    fn cipher(&self) -> AppResult<Option<ChaCha20Poly1305>> {
+        let path = self.dir.join(KEY_FILE);
+        match fs::read(&path) {
+            Ok(bytes) if bytes.len() == 32 => {
+                Ok(Some(ChaCha20Poly1305::new(Key::from_slice(&bytes))))
+            }
+            Ok(_) => Err(AppError::Io(format!(
+                "Corrupt secret key file: {}",
+                path.display()
+            ))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn cipher_or_create(&self) -> AppResult<ChaCha20Poly1305> {
+        if let Some(cipher) = self.cipher()? {
+            return Ok(cipher);
+        }
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        fs::create_dir_all(&self.dir)?;
+        write_private(&self.dir.join(KEY_FILE), &key)?;
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn load(&self, cipher: &ChaCha20Poly1305) -> AppResult<HashMap<String, String>> {
+        let sealed = match fs::read(self.dir.join(DATA_FILE)) {
+            Ok(sealed) => sealed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+        if sealed.len() < NONCE_LEN {
+            return Err(AppError::Io("Secret file is truncated".to_string()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plain = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| AppError::Io("Secret file could not be decrypted".to_string()))?;
+        serde_json::from_slice(&plain)
+            .map_err(|e| AppError::Io(format!("Secret file is unreadable: {}", e)))
+    }
+
+    fn save(&self, cipher: &ChaCha20Poly1305, secrets: &HashMap<String, String>) -> AppResult<()> {
+        let plain = serde_json::to_vec(secrets)
+            .map_err(|e| AppError::Io(format!("Failed to encode secrets: {}", e)))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|_| AppError::Io("Failed to encrypt secrets".to_string()))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        // Write beside and rename, so a crash can't leave half a file.
+        let tmp = self.dir.join(format!("{}.tmp", DATA_FILE));
+        write_private(&tmp, &sealed)?;
+        fs::rename(&tmp, self.dir.join(DATA_FILE))?;
+        Ok(())
+    }
+
+    fn update(&self, f: impl FnOnce(&mut HashMap<String, String>)) -> AppResult<()> {
+        let _guard = self.lock.lock()?;
+        let cipher = self.cipher_or_create()?;
+        let mut secrets = self.load(&cipher)?;
+        f(&mut secrets);
+        self.save(&cipher, &secrets)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn get(&self, name: &str) -> AppResult<Option<String>> {
+        let _guard = self.lock.lock()?;
+        match self.cipher()? {
+            Some(cipher) => Ok(self.load(&cipher)?.remove(name)),
+            None => Ok(None),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> AppResult<()> {
+        self.update(|secrets| {
+            secrets.insert(name.to_string(), value.to_string());
+        })
+    }
+
+    fn delete(&self, name: &str) -> AppResult<()> {
+        self.update(|secrets| {
+            secrets.remove(name);
+        })
+    }
+}
+
+/// Writes `bytes` to a new file only the current user can read.
+fn write_private(path: &Path, bytes: &[u8]) -> AppResult<()> {
+    use std::io::Write;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(bytes)?;
+    Ok(())
+}
+
+/// The app's secret store, managed as Tauri state.
+pub struct Secrets {
+    store: Box<dyn SecretStore>,
+}
+
+impl Secrets {
+    /// The OS credential store if there is one, else the encrypted file
+    /// in `app_data_dir`.
+    pub fn new(app_data_dir: PathBuf) -> Self {
+        match KeyringStore::open(KEYRING_SERVICE) {
+            Some(store) => Secrets::with_store(store),
+            None => {
+                log::warn!("No OS credential store; keeping secrets in an encrypted file");
+                Secrets::with_store(EncryptedFileStore::new(app_data_dir))
+            }
+        }
+    }
+
+    pub fn with_store(store: impl SecretStore + 'static) -> Self {
+        Secrets {
+            store: Box::new(store),
+        }
+    }
+
+    pub fn store(&self) -> &dyn SecretStore {
+        self.store.as_ref()
+    }
+}
+
+pub fn get_secrets<R: Runtime>(app: &AppHandle<R>) -> &dyn SecretStore {
+    app.state::<Secrets>().inner().store()
+}
+
+/// Moves an API key that an earlier version stored in plaintext in the
+/// `config` table into `store`, then deletes the row. The row is only
+/// deleted once the store has the key, so a failure is retried on the
+/// next launch.
+pub fn migrate_api_key(conn: &Connection, store: &dyn SecretStore) -> AppResult<()> {
+    let legacy: Option<String> = conn
+        .query_row(
+            "SELECT value FROM config WHERE key = ?1",
+            params![API_KEY_SECRET],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(key) = legacy else {
+        return Ok(());
+    };
+    // A key saved since (through the store) wins over the stale row.
+    if !key.is_empty() && store.get(API_KEY_SECRET)?.is_none() {
+        store.set(API_KEY_SECRET, &key)?;
+    }
+    conn.execute("DELETE FROM config WHERE key = ?1", params![API_KEY_SECRET])?;
+    log::info!("Moved the API key from the database to the secret store");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("nanobanana-secrets-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn encrypted_file_round_trips_without_plaintext_on_disk() {
+        let dir = temp_dir();
+        let store = EncryptedFileStore::new(dir.clone());
+        assert_eq!(store.get("a").unwrap(), None);
+
+        store.set("a", "AIzaSecretValue").unwrap();
+        store.set("b", "other").unwrap();
+        assert_eq!(store.get("a").unwrap().as_deref(), Some("AIzaSecretValue"));
+        let sealed = fs::read(dir.join(DATA_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("AIzaSecretValue"));
+
+        // A fresh instance reads what the last one wrote.
+        let reopened = EncryptedFileStore::new(dir.clone());
+        reopened.delete("a").unwrap();
+        reopened.delete("missing").unwrap();
+        assert_eq!(store.get("a").unwrap(), None);
+        assert_eq!(store.get("b").unwrap().as_deref(), Some("other"));
+
+        fs::write(dir.join(KEY_FILE), [7u8; 32]).unwrap();
+        assert_eq!(store.get("b").unwrap_err().kind(), "io");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_moves_the_key_out_of_the_database() {
+        let dir = temp_dir();
+        let db = Database::new(dir.clone()).unwrap();
+        let store = EncryptedFileStore::new(dir.clone());
+        let conn = db.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO config (key, value) VALUES (?1, 'legacy-key')",
+            params![API_KEY_SECRET],
+        )
+        .unwrap();
+
+        migrate_api_key(&conn, &store).unwrap();
+        assert_eq!(
+            store.get(API_KEY_SECRET).unwrap().as_deref(),
+            Some("legacy-key")
+        );
+        let rows: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM config WHERE key = ?1",
+                params![API_KEY_SECRET],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(rows, 0);
+
+        // Nothing left to move; a second run is a no-op.
+        migrate_api_key(&conn, &store).unwrap();
+        assert_eq!(
+            store.get(API_KEY_SECRET).unwrap().as_deref(),
+            Some("legacy-key")
+        );
+        drop(conn);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,449 @@
+use crate::db::get_db;
+use crate::error::{AppError, AppResult};
+use crate::gemini::{
+    validate_base_url, validate_model, ASPECT_RATIOS, DEFAULT_BASE_URL, DEFAULT_MODEL, OUTPUT_SIZES,
+};
+use crate::http::validate_proxy_url;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+const MAX_TEMPERATURE: f64 = 2.0;
+const MAX_TIMEOUT_SECS: u64 = 3600;
+
+/// User settings, each stored as a `config` row named after its field.
+/// The defaults here are the only ones; the frontend reads them through
+/// `get_settings`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// One of `OUTPUT_SIZES`.
+    pub default_output_size: String,
+    /// One of `ASPECT_RATIOS`.
+    pub default_aspect_ratio: String,
+    /// 0 to 2.
+    pub default_temperature: f64,
+    pub default_model: String,
+    /// Where results are saved; `None` is `Pictures/Nana Studio`.
+    pub results_dir: Option<String>,
+    /// Where uploaded inputs are copied; `None` is the app data dir's
+    /// `uploads`.
+    pub uploads_dir: Option<String>,
+    pub gemini_base_url: String,
+    /// Spending limits in USD; `None` is unlimited.
+    pub daily_budget: Option<f64>,
+    pub monthly_budget: Option<f64>,
+    /// Proxy for HTTPS requests, e.g. `http://proxy.corp.example:3128`.
+    pub https_proxy: Option<String>,
+    /// Hosts that bypass the proxy, comma-separated as in `NO_PROXY`.
+    pub no_proxy: Option<String>,
+    /// PEM file of CA certificates to trust on top of the system's, for
+    /// proxies that intercept TLS.
+    pub ca_bundle_path: Option<String>,
+    /// 1 to 3600.
+    pub connect_timeout_secs: u64,
+    /// Whole-request limit, so a stalled endpoint can't wedge a command;
+    /// 1 to 3600.
+    pub request_timeout_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            default_output_size: "1K".to_string(),
+            default_aspect_ratio: "16:9".to_string(),
+            default_temperature: 1.0,
+            default_model: DEFAULT_MODEL.to_string(),
+            results_dir: None,
+            uploads_dir: None,
+            gemini_base_url: DEFAULT_BASE_URL.to_string(),
+            daily_budget: None,
+            monthly_budget: None,
+            https_proxy: None,
+            no_proxy: None,
+            ca_bundle_path: None,
+            connect_timeout_secs: 10,
+            // Long enough for a large results file on a slow link.
+            request_timeout_secs: 600,
+        }
+    }
+}
+
+impl Settings {
+    /// The saved settings. A value that doesn't parse or is out of range,
+    /// e.g. saved by a version that checked less, is logged and left at
+    /// its default.
+    pub fn load(conn: &Connection) -> AppResult<Self> {
+        let mut stmt = conn.prepare("SELECT key, value FROM config")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut settings = Settings::default();
+        for (key, value) in rows {
+            if let Err(e) = settings.set_stored(&key, value.trim()) {
+                log::warn!("Ignoring saved setting {}: {}", key, e);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Parses one stored row. Empty is how earlier versions cleared a
+    /// setting, so it keeps the default; rows that aren't settings (the
+    /// active profile, say) are skipped.
+    fn set_stored(&mut self, key: &str, value: &str) -> AppResult<()> {
+        if value.is_empty() {
+            return Ok(());
+        }
+        let text = || Some(value.to_string());
+        match key {
+            "default_output_size" => {
+                check_choice(value, OUTPUT_SIZES, "output size")?;
+                self.default_output_size = value.to_string();
+            }
+            "default_aspect_ratio" => {
+                check_choice(value, ASPECT_RATIOS, "aspect ratio")?;
+                self.default_aspect_ratio = value.to_string();
+            }
+            "default_temperature" => {
+                self.default_temperature = check_temperature(parse(value)?)?;
+            }
+            "default_model" => {
+                validate_model(value)?;
+                self.default_model = value.to_string();
+            }
+            "results_dir" => self.results_dir = Some(check_absolute(value, "Results folder")?),
+            "uploads_dir" => self.uploads_dir = Some(check_absolute(value, "Uploads folder")?),
+            "gemini_base_url" => {
+                validate_base_url(value)?;
+                self.gemini_base_url = value.to_string();
+            }
+            "daily_budget" => self.daily_budget = Some(check_budget(parse(value)?)?),
+            "monthly_budget" => self.monthly_budget = Some(check_budget(parse(value)?)?),
+            "https_proxy" => {
+                validate_proxy_url(value)?;
+                self.https_proxy = text();
+            }
+            "no_proxy" => self.no_proxy = text(),
+            "ca_bundle_path" => self.ca_bundle_path = Some(check_absolute(value, "CA bundle")?),
+            "connect_timeout_secs" => self.connect_timeout_secs = check_timeout(parse(value)?)?,
+            "request_timeout_secs" => self.request_timeout_secs = check_timeout(parse(value)?)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Every setting as its `config` row; `None` means no row.
+    fn rows(&self) -> [(&'static str, Option<String>); 14] {
+        [
+            (
+                "default_output_size",
+                Some(self.default_output_size.clone()),
+            ),
+            (
+                "default_aspect_ratio",
+                Some(self.default_aspect_ratio.clone()),
+            ),
+            (
+                "default_temperature",
+                Some(self.default_temperature.to_string()),
+            ),
+            ("default_model", Some(self.default_model.clone())),
+            ("results_dir", self.results_dir.clone()),
+            ("uploads_dir", self.uploads_dir.clone()),
+            ("gemini_base_url", Some(self.gemini_base_url.clone())),
+            ("daily_budget", self.daily_budget.map(|b| b.to_string())),
+            ("monthly_budget", self.monthly_budget.map(|b| b.to_string())),
+            ("https_proxy", self.https_proxy.clone()),
+            ("no_proxy", self.no_proxy.clone()),
+            ("ca_bundle_path", self.ca_bundle_path.clone()),
+            (
+                "connect_timeout_secs",
+                Some(self.connect_timeout_secs.to_string()),
+            ),
+            (
+                "request_timeout_secs",
+                Some(self.request_timeout_secs.to_string()),
+            ),
+        ]
+    }
+
+    pub fn save(&self, conn: &Connection) -> AppResult<()> {
+        let tx = conn.unchecked_transaction()?;
+        for (key, value) in self.rows() {
+            match value {
+                Some(value) => tx.execute(
+                    "INSERT INTO config (key, value) VALUES (?1, ?2)
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    params![key, value],
+                )?,
+                None => tx.execute("DELETE FROM config WHERE key = ?1", params![key])?,
+            };
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// These settings with `changes` applied. Keys are field names, and
+    /// `null` restores a field's default.
+    pub fn apply(&self, changes: Map<String, Value>) -> AppResult<Settings> {
+        let defaults = serde_json::to_value(Settings::default())?;
+        let mut merged = serde_json::to_value(self)?;
+        for (key, value) in changes {
+            let value = match value {
+                Value::Null => defaults.get(&key).cloned().unwrap_or(Value::Null),
+                value => value,
+            };
+            merged[key.as_str()] = value;
+        }
+        serde_json::from_value(merged)
+            .map_err(|e| AppError::Validation(format!("Invalid settings: {}", e)))
+    }
+
+    /// Checks every field, and that the chosen folders can be written to.
+    pub fn validate(&self) -> AppResult<()> {
+        check_choice(&self.default_output_size, OUTPUT_SIZES, "output size")?;
+        check_choice(&self.default_aspect_ratio, ASPECT_RATIOS, "aspect ratio")?;
+        check_temperature(self.default_temperature)?;
+        validate_model(&self.default_model)?;
+        validate_base_url(&self.gemini_base_url)?;
+        for (dir, what) in [
+            (&self.results_dir, "Results folder"),
+            (&self.uploads_dir, "Uploads folder"),
+        ] {
+            if let Some(dir) = dir {
+                check_writable_dir(dir, what)?;
+            }
+        }
+        for budget in [self.daily_budget, self.monthly_budget]
+            .into_iter()
+            .flatten()
+        {
+            check_budget(budget)?;
+        }
+        if let Some(url) = &self.https_proxy {
+            validate_proxy_url(url)?;
+        }
+        if let Some(path) = &self.ca_bundle_path {
+            check_absolute(path, "CA bundle")?;
+        }
+        check_timeout(self.connect_timeout_secs)?;
+        check_timeout(self.request_timeout_secs)?;
+        Ok(())
+    }
+
+    /// Whether `other` builds the same HTTP client.
+    pub fn same_network(&self, other: &Settings) -> bool {
+        self.https_proxy == other.https_proxy
+            && self.no_proxy == other.no_proxy
+            && self.ca_bundle_path == other.ca_bundle_path
+            && self.connect_timeout_secs == other.connect_timeout_secs
+            && self.request_timeout_secs == other.request_timeout_secs
+    }
+}
+
+pub fn load_settings<R: Runtime>(app: &AppHandle<R>) -> AppResult<Settings> {
+    let db = get_db(app);
+    let conn = db.conn.lock()?;
+    Settings::load(&conn)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> AppResult<T> {
+    value
+        .parse()
+        .map_err(|_| AppError::Validation(format!("Expected a number, got '{}'", value)))
+}
+
+fn check_choice(value: &str, choices: &[&str], what: &str) -> AppResult<()> {
+    if choices.contains(&value) {
+        return Ok(());
+    }
+    Err(AppError::Validation(format!(
+        "Unsupported {}: {} (expected one of {})",
+        what,
+        value,
+        choices.join(", ")
+    )))
+}
+
+fn check_temperature(temperature: f64) -> AppResult<f64> {
+    if (0.0..=MAX_TEMPERATURE).contains(&temperature) {
+        return Ok(temperature);
+    }
+    Err(AppError::Validation(format!(
+        "Temperature must be from 0 to {}, got {}",
+        MAX_TEMPERATURE, temperature
+    )))
+}
+
+fn check_budget(budget: f64) -> AppResult<f64> {
+    if budget.is_finite() && budget >= 0.0 {
+        return Ok(budget);
+    }
+    Err(AppError::Validation(format!(
+        "Budget must be a non-negative amount in USD, got {}",
+        budget
+    )))
+}
+
+fn check_timeout(secs: u64) -> AppResult<u64> {
+    if (1..=MAX_TIMEOUT_SECS).contains(&secs) {
+        return Ok(secs);
+    }
+    Err(AppError::Validation(format!(
+        "Timeout must be whole seconds from 1 to {}, got {}",
+        MAX_TIMEOUT_SECS, secs
+    )))
+}
+
+fn check_absolute(path: &str, what: &str) -> AppResult<String> {
+    if Path::new(path).is_absolute() {
+        return Ok(path.to_string());
+    }
+    Err(AppError::Validation(format!(
+        "{} must be an absolute path: {}",
+        what, path
+    )))
+}
+
+/// Creates `dir` if need be and proves a file can be written in it.
+fn check_writable_dir(dir: &str, what: &str) -> AppResult<()> {
+    check_absolute(dir, what)?;
+    let unwritable = |e: std::io::Error| {
+        AppError::Validation(format!("{} {} is not writable: {}", what, dir, e))
+    };
+    fs::create_dir_all(dir).map_err(unwritable)?;
+    let probe = Path::new(dir).join(format!(".write-test-{}", uuid::Uuid::new_v4()));
+    fs::write(&probe, b"").map_err(unwritable)?;
+    fs::remove_file(&probe).ok();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use serde_json::json;
+
+    fn changes(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn apply_checks_types_and_null_restores_the_default() {
+        let settings = Settings::default()
+            .apply(changes(
+                json!({"default_temperature": 0.5, "daily_budget": 3}),
+            ))
+            .unwrap();
+        assert_eq!(settings.default_temperature, 0.5);
+        assert_eq!(settings.daily_budget, Some(3.0));
+
+        let restored = settings
+            .apply(changes(
+                json!({"default_temperature": null, "daily_budget": null}),
+            ))
+            .unwrap();
+        assert_eq!(restored, Settings::default());
+
+        for bad in [
+            json!({"default_temperature": "banana"}),
+            json!({"connect_timeout_secs": -1}),
+            json!({"gemini_api_key": "AIza"}),
+        ] {
+            let err = settings.apply(changes(bad.clone())).unwrap_err();
+            assert_eq!(err.kind(), "validation", "{}", bad);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        let dir =
+            std::env::temp_dir().join(format!("nanobanana-settings-{}", uuid::Uuid::new_v4()));
+        let results = dir.join("results").to_string_lossy().to_string();
+        let ok = Settings {
+            results_dir: Some(results.clone()),
+            ..Settings::default()
+        };
+        ok.validate().unwrap();
+        assert!(Path::new(&results).is_dir());
+
+        let bad = [
+            Settings {
+                default_temperature: 2.5,
+                ..Settings::default()
+            },
+            Settings {
+                default_output_size: "3K".into(),
+                ..Settings::default()
+            },
+            Settings {
+                default_aspect_ratio: "7:5".into(),
+                ..Settings::default()
+            },
+            Settings {
+                results_dir: Some("relative/results".into()),
+                ..Settings::default()
+            },
+            Settings {
+                monthly_budget: Some(-1.0),
+                ..Settings::default()
+            },
+            Settings {
+                request_timeout_secs: 0,
+                ..Settings::default()
+            },
+        ];
+        for settings in bad {
+            assert_eq!(settings.validate().unwrap_err().kind(), "validation");
+        }
+        // A file where the folder should be.
+        let file = dir.join("file").to_string_lossy().to_string();
+        fs::write(&file, b"x").unwrap();
+        let not_a_dir = Settings {
+            uploads_dir: Some(file),
+            ..Settings::default()
+        };
+        assert!(not_a_dir.validate().is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn load_keeps_defaults_for_bad_rows_and_round_trips_saves() {
+        let dir =
+            std::env::temp_dir().join(format!("nanobanana-settings-{}", uuid::Uuid::new_v4()));
+        let db = Database::new(dir.clone()).unwrap();
+        let conn = db.conn.lock().unwrap();
+        for (key, value) in [
+            ("default_temperature", "banana"),
+            ("default_output_size", "2K"),
+            ("results_dir", ""),
+            ("active_profile_id", "p1"),
+        ] {
+            conn.execute(
+                "INSERT INTO config (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .unwrap();
+        }
+        let loaded = Settings::load(&conn).unwrap();
+        assert_eq!(loaded.default_temperature, 1.0);
+        assert_eq!(loaded.default_output_size, "2K");
+        assert_eq!(loaded.results_dir, None);
+
+        let changed = Settings {
+            default_temperature: 0.2,
+            daily_budget: Some(12.5),
+            no_proxy: Some("localhost".into()),
+            ..loaded
+        };
+        changed.save(&conn).unwrap();
+        assert_eq!(Settings::load(&conn).unwrap(), changed);
+        drop(conn);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -23,6 +23,8 @@ const baseJob = {
   temperature: 1,
   aspect_ratio: '16:9' as const,
   batch_job_name: null,
+  delivery: 'batch',
+  model: 'gemini-3.1-flash-image-preview',
   total_items: 2,
   completed_items: 2,
   failed_items: 0,
@@ -33,7 +35,7 @@ const baseJob = {
 const failedJob = { ...baseJob, status: 'failed' as const, completed_items: 0, failed_items: 2 };
 
 function resetJobsStore() {
-  jobs.stopPolling();
+  jobs.stopListening();
   for (const j of get(jobs)) jobs.removeJob(j.id);
 }
 
@@ -76,7 +78,7 @@ describe('JobCard — retry', () => {
     jobs.addJob(failedJob);
     // Stop polling immediately — the addJob above scheduled it, but we
     // want deterministic timer control in these tests.
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('clicking retry dispatches submit_batch and flips status to processing', async () => {
@@ -88,7 +90,7 @@ describe('JobCard — retry', () => {
 
     expect(invoke).toHaveBeenCalledWith('submit_batch', { jobId: 'test-1' });
     expect(get(jobs).find((j) => j.id === 'test-1')?.status).toBe('processing');
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('surfaces a toast when retry fails', async () => {
@@ -112,7 +114,7 @@ describe('JobCard — delete confirm flow', () => {
     resetJobsStore();
     toasts.clear();
     jobs.addJob(baseJob);
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('first click enters confirm state without calling delete_job', async () => {
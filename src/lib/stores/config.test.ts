@@ -1,7 +1,8 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
-import { config } from './config';
+import { config, describeKeyValidation } from './config';
+import type { KeyValidation } from '$lib/types';
 
 vi.mock('@tauri-apps/api/core');
 
@@ -34,14 +35,51 @@ describe('config store', () => {
   });
 
   it('validates API key via backend', async () => {
-    vi.mocked(invoke).mockResolvedValueOnce(true);
+    const validation: KeyValidation = {
+      valid: true,
+      reachable: true,
+      http_status: 200,
+      reason: null,
+      message: null,
+      latency_ms: 120,
+      models: [],
+    };
+    vi.mocked(invoke).mockResolvedValueOnce(validation);
 
-    const valid = await config.validate('test-key');
+    const result = await config.validate('test-key');
 
-    expect(valid).toBe(true);
+    expect(result.valid).toBe(true);
     expect(invoke).toHaveBeenCalledWith('validate_api_key', { apiKey: 'test-key' });
   });
 
+  it('describes why a key failed validation', () => {
+    const base: KeyValidation = {
+      valid: false,
+      reachable: true,
+      http_status: 400,
+      reason: 'API_KEY_INVALID',
+      message: 'API key not valid.',
+      latency_ms: 80,
+      models: [],
+    };
+    expect(describeKeyValidation(base)).toMatch(/Invalid API key/);
+    expect(describeKeyValidation({ ...base, reason: 'SERVICE_DISABLED' })).toMatch(/not enabled/);
+    const offline = {
+      ...base,
+      reachable: false,
+      http_status: null,
+      reason: null,
+      message: 'connection refused',
+    };
+    expect(describeKeyValidation(offline)).toBe(
+      'Could not reach Gemini — check your network connection (connection refused)',
+    );
+    const denied = { ...base, http_status: 403, reason: 'PERMISSION_DENIED', message: 'Denied.' };
+    expect(describeKeyValidation(denied)).toBe(
+      'Gemini refused this key (HTTP 403, PERMISSION_DENIED): Denied.',
+    );
+  });
+
   it('saves API key and reloads config', async () => {
     vi.mocked(invoke)
       .mockResolvedValueOnce(undefined) // saveConfig
@@ -1,6 +1,6 @@
 // src/lib/stores/config.ts
 import { writable } from 'svelte/store';
-import type { ConfigStatus } from '$lib/types';
+import type { ConfigStatus, KeyValidation } from '$lib/types';
 import * as cmd from '$lib/utils/commands';
 
 function createConfigStore() {
@@ -21,10 +21,28 @@ function createConfigStore() {
       await cmd.deleteConfig();
       set({ has_key: false, masked: null });
     },
-    async validate(apiKey: string): Promise<boolean> {
+    async validate(apiKey: string): Promise<KeyValidation> {
       return cmd.validateApiKey(apiKey);
     },
   };
 }
 
 export const config = createConfigStore();
+
+/** Why a key failed validation, in words a user can act on. */
+export function describeKeyValidation(v: KeyValidation): string {
+  if (!v.reachable) {
+    const detail = v.message ? ` (${v.message})` : '';
+    return `Could not reach Gemini — check your network connection${detail}`;
+  }
+  switch (v.reason) {
+    case 'API_KEY_INVALID':
+      return 'Invalid API key — Gemini did not recognise it';
+    case 'SERVICE_DISABLED':
+      return 'The Generative Language API is not enabled for this key\'s Google Cloud project';
+    case 'RESOURCE_EXHAUSTED':
+      return 'This key has run out of quota — try again later or check its limits';
+  }
+  const status = v.http_status ? ` (HTTP ${v.http_status}${v.reason ? `, ${v.reason}` : ''})` : '';
+  return `Gemini refused this key${status}${v.message ? `: ${v.message}` : ''}`;
+}
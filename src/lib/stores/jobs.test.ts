@@ -1,7 +1,8 @@
 import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest';
 import { get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
-import type { Job } from '$lib/types';
+import { listen } from '@tauri-apps/api/event';
+import type { Job, JobItem } from '$lib/types';
 
 const mockJob: Job = {
   id: 'test-1',
@@ -12,11 +13,16 @@ const mockJob: Job = {
   temperature: 1,
   aspect_ratio: '1:1',
   batch_job_name: null,
+  delivery: 'batch',
+  model: 'gemini-3.1-flash-image-preview',
   total_items: 1,
   completed_items: 0,
   failed_items: 0,
   created_at: '2026-01-01T00:00:00Z',
   updated_at: '2026-01-01T00:00:00Z',
+  estimated_cost: 0.0336,
+  actual_cost: null,
+  profile_id: null,
 };
 
 function mockEnv(opts: { browser: boolean; dev: boolean }) {
@@ -47,16 +53,16 @@ describe('jobs store', () => {
     await jobs.loadJobs();
     expect(get(jobs)).toEqual([mockJob]);
     expect(invoke).toHaveBeenCalledWith('get_jobs', { status: undefined });
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
-  it('addJob inserts at the head and starts polling', async () => {
+  it('addJob inserts at the head', async () => {
     vi.mocked(invoke).mockResolvedValue([]);
     mockEnv({ browser: true, dev: false });
     const { jobs } = await freshJobsStore();
     jobs.addJob(mockJob);
     expect(get(jobs)).toContainEqual(mockJob);
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('activeJobsCount derives from pending/processing', async () => {
@@ -65,7 +71,7 @@ describe('jobs store', () => {
     const { jobs, activeJobsCount } = await freshJobsStore();
     jobs.addJob(mockJob);
     expect(get(activeJobsCount)).toBe(1);
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('removeJob removes by id', async () => {
@@ -74,7 +80,7 @@ describe('jobs store', () => {
     jobs.addJob(mockJob);
     jobs.removeJob(mockJob.id);
     expect(get(jobs)).toHaveLength(0);
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('updateJob replaces by id', async () => {
@@ -83,7 +89,7 @@ describe('jobs store', () => {
     jobs.addJob(mockJob);
     jobs.updateJob({ ...mockJob, status: 'completed' });
     expect(get(jobs)[0].status).toBe('completed');
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('loadMocks populates only in dev', async () => {
@@ -96,6 +102,62 @@ describe('jobs store', () => {
     const { jobs: devJobs } = await freshJobsStore();
     devJobs.loadMocks([mockJob]);
     expect(get(devJobs)).toEqual([mockJob]);
-    devJobs.stopPolling();
+    devJobs.stopListening();
+  });
+
+  async function listeningStore() {
+    mockEnv({ browser: true, dev: false });
+    const mod = await freshJobsStore();
+    await mod.jobs.startListening();
+    const handler = (event: string) =>
+      vi.mocked(listen).mock.calls.find(([name]) => name === event)![1] as (e: {
+        payload: unknown;
+      }) => void;
+    return { ...mod, handler };
+  }
+
+  it('startListening subscribes to backend job and item events', async () => {
+    const { jobs } = await listeningStore();
+    expect(listen).toHaveBeenCalledWith('job-updated', expect.any(Function));
+    expect(listen).toHaveBeenCalledWith('item-updated', expect.any(Function));
+    jobs.stopListening();
+  });
+
+  it('job-updated replaces a known job and inserts an unknown one', async () => {
+    const { jobs, handler } = await listeningStore();
+    jobs.addJob(mockJob);
+
+    handler('job-updated')({ payload: { ...mockJob, status: 'processing' } });
+    expect(get(jobs)).toHaveLength(1);
+    expect(get(jobs)[0].status).toBe('processing');
+
+    handler('job-updated')({ payload: { ...mockJob, id: 'test-2' } });
+    expect(get(jobs).map((j) => j.id)).toEqual(['test-2', 'test-1']);
+    jobs.stopListening();
+  });
+
+  it('item-updated is exposed through itemUpdates', async () => {
+    const { jobs, itemUpdates, handler } = await listeningStore();
+    const item: JobItem = {
+      id: 'item-1',
+      job_id: mockJob.id,
+      input_prompt: 'p',
+      input_image_path: null,
+      output_image_path: '/tmp/out.png',
+      status: 'completed',
+      error: null,
+      created_at: mockJob.created_at,
+      updated_at: mockJob.updated_at,
+      response_text: null,
+      finish_reason: null,
+      block_reason: null,
+      safety_ratings: [],
+      usage: null,
+      cost: null,
+      outputs: [],
+    };
+    handler('item-updated')({ payload: item });
+    expect(get(itemUpdates)).toEqual(item);
+    jobs.stopListening();
   });
 });
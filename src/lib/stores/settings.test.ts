@@ -2,9 +2,27 @@ import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { get } from 'svelte/store';
 import { invoke } from '@tauri-apps/api/core';
 import { settings } from './settings';
+import type { Settings } from '$lib/types';
 
 vi.mock('@tauri-apps/api/core');
 
+const saved: Settings = {
+  default_output_size: '1K',
+  default_aspect_ratio: '16:9',
+  default_temperature: 1,
+  default_model: 'gemini-3.1-flash-image-preview',
+  results_dir: null,
+  uploads_dir: null,
+  gemini_base_url: 'https://generativelanguage.googleapis.com',
+  daily_budget: null,
+  monthly_budget: null,
+  https_proxy: null,
+  no_proxy: null,
+  ca_bundle_path: null,
+  connect_timeout_secs: 10,
+  request_timeout_secs: 600,
+};
+
 describe('settings store', () => {
   beforeEach(() => {
     vi.resetAllMocks();
@@ -19,64 +37,65 @@ describe('settings store', () => {
 
   it('loads saved settings from backend', async () => {
     vi.mocked(invoke).mockResolvedValueOnce({
+      ...saved,
       default_output_size: '2K',
       default_aspect_ratio: '1:1',
-      default_temperature: '0.5',
+      default_temperature: 0.5,
+      default_model: 'gemini-2.5-flash-image',
     });
 
     await settings.load();
 
     const s = get(settings);
+    expect(invoke).toHaveBeenCalledWith('get_settings');
     expect(s.output_size).toBe('2K');
     expect(s.aspect_ratio).toBe('1:1');
     expect(s.temperature).toBe(0.5);
+    expect(s.model).toBe('gemini-2.5-flash-image');
   });
 
-  it('validates loaded size/ratio against the literal list', async () => {
+  it('falls back for a size or ratio the pickers do not offer', async () => {
     vi.mocked(invoke).mockResolvedValueOnce({
-      default_output_size: 'XXXL',
+      ...saved,
+      default_output_size: '0.5K',
       default_aspect_ratio: '21:9',
-      default_temperature: '1',
     });
 
     await settings.load();
 
     const s = get(settings);
-    // Invalid values must fall back to defaults, not be coerced through.
     expect(s.output_size).toBe('1K');
     expect(s.aspect_ratio).toBe('16:9');
   });
 
-  it('falls back to defaults on load error', async () => {
+  it('keeps current values on load error', async () => {
+    vi.mocked(invoke).mockResolvedValueOnce({ ...saved, default_temperature: 0.3 });
+    await settings.load();
     vi.mocked(invoke).mockRejectedValueOnce(new Error('DB error'));
 
     await settings.load();
 
-    const s = get(settings);
-    expect(s.output_size).toBe('1K');
-    expect(s.aspect_ratio).toBe('16:9');
-    expect(s.temperature).toBe(1);
+    expect(get(settings).temperature).toBe(0.3);
   });
 
   it('persists update to backend', async () => {
-    vi.mocked(invoke).mockResolvedValue(undefined);
+    vi.mocked(invoke).mockResolvedValueOnce({ ...saved, default_output_size: '4K' });
 
     await settings.update({ output_size: '4K' });
 
-    expect(invoke).toHaveBeenCalledWith('save_setting', {
-      key: 'default_output_size',
-      value: '4K',
+    expect(invoke).toHaveBeenCalledWith('update_settings', {
+      changes: { default_output_size: '4K' },
     });
+    expect(get(settings).output_size).toBe('4K');
   });
 
-  it('persists temperature as string', async () => {
-    vi.mocked(invoke).mockResolvedValue(undefined);
+  it('persists temperature as a number', async () => {
+    vi.mocked(invoke).mockResolvedValueOnce({ ...saved, default_temperature: 1.5 });
 
     await settings.update({ temperature: 1.5 });
 
-    expect(invoke).toHaveBeenCalledWith('save_setting', {
-      key: 'default_temperature',
-      value: '1.5',
+    expect(invoke).toHaveBeenCalledWith('update_settings', {
+      changes: { default_temperature: 1.5 },
     });
   });
 
@@ -91,8 +110,8 @@ describe('settings store', () => {
     expect(after).toEqual(before);
   });
 
-  it('resets to defaults and persists', async () => {
-    vi.mocked(invoke).mockResolvedValue(undefined);
+  it('resets to the backend defaults', async () => {
+    vi.mocked(invoke).mockResolvedValueOnce(saved);
 
     await settings.reset();
 
@@ -101,9 +120,13 @@ describe('settings store', () => {
     expect(s.aspect_ratio).toBe('16:9');
     expect(s.temperature).toBe(1);
 
-    expect(invoke).toHaveBeenCalledWith('save_setting', {
-      key: 'default_output_size',
-      value: '1K',
+    expect(invoke).toHaveBeenCalledWith('update_settings', {
+      changes: {
+        default_output_size: null,
+        default_aspect_ratio: null,
+        default_temperature: null,
+        default_model: null,
+      },
     });
   });
 });
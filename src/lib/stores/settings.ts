@@ -1,59 +1,65 @@
 // src/lib/stores/settings.ts
 import { writable } from 'svelte/store';
-import type { GenerationDefaults, OutputSize, AspectRatio } from '$lib/types';
-import { OUTPUT_SIZES, ASPECT_RATIOS } from '$lib/types';
+import type { GenerationDefaults, OutputSize, AspectRatio, Settings, SettingsChanges } from '$lib/types';
+import { OUTPUT_SIZES, ASPECT_RATIOS, DEFAULT_MODEL } from '$lib/types';
 import * as cmd from '$lib/utils/commands';
 
-const defaultSettings: GenerationDefaults = {
+// Shown until the backend's settings load; the real defaults are defined there.
+const placeholder: GenerationDefaults = {
   output_size: '1K',
   aspect_ratio: '16:9',
   temperature: 1,
+  model: DEFAULT_MODEL,
 };
 
-function asOutputSize(v: string | undefined, fallback: OutputSize): OutputSize {
-  return v && v in OUTPUT_SIZES ? (v as OutputSize) : fallback;
+function asOutputSize(v: string, fallback: OutputSize): OutputSize {
+  return v in OUTPUT_SIZES ? (v as OutputSize) : fallback;
 }
 
-function asAspectRatio(v: string | undefined, fallback: AspectRatio): AspectRatio {
-  return v && v in ASPECT_RATIOS ? (v as AspectRatio) : fallback;
+function asAspectRatio(v: string, fallback: AspectRatio): AspectRatio {
+  return v in ASPECT_RATIOS ? (v as AspectRatio) : fallback;
+}
+
+function generationDefaults(s: Settings): GenerationDefaults {
+  // The backend accepts sizes and ratios the pickers don't list.
+  return {
+    output_size: asOutputSize(s.default_output_size, placeholder.output_size),
+    aspect_ratio: asAspectRatio(s.default_aspect_ratio, placeholder.aspect_ratio),
+    temperature: s.default_temperature,
+    model: s.default_model,
+  };
 }
 
 function createSettingsStore() {
-  const { subscribe, set, update } = writable<GenerationDefaults>(defaultSettings);
+  const { subscribe, set } = writable<GenerationDefaults>(placeholder);
 
   return {
     subscribe,
     async load() {
       try {
-        const all = await cmd.getAllSettings();
-        const loaded: GenerationDefaults = {
-          output_size: asOutputSize(all['default_output_size'], defaultSettings.output_size),
-          aspect_ratio: asAspectRatio(all['default_aspect_ratio'], defaultSettings.aspect_ratio),
-          temperature: all['default_temperature']
-            ? Number(all['default_temperature'])
-            : defaultSettings.temperature,
-        };
-        set(loaded);
+        set(generationDefaults(await cmd.getSettings()));
       } catch {
-        set(defaultSettings);
+        // Keep what we have; the backend falls back to defaults itself.
       }
     },
     async update(partial: Partial<GenerationDefaults>) {
       // Persist first so the store only reflects values that made it to DB.
       // Callers can catch and show an error if persistence fails.
-      const writes: Promise<void>[] = [];
-      if (partial.output_size) writes.push(cmd.saveSetting('default_output_size', partial.output_size));
-      if (partial.aspect_ratio) writes.push(cmd.saveSetting('default_aspect_ratio', partial.aspect_ratio));
-      if (partial.temperature !== undefined)
-        writes.push(cmd.saveSetting('default_temperature', String(partial.temperature)));
-      await Promise.all(writes);
-      update((current) => ({ ...current, ...partial }));
+      const changes: SettingsChanges = {};
+      if (partial.output_size) changes.default_output_size = partial.output_size;
+      if (partial.aspect_ratio) changes.default_aspect_ratio = partial.aspect_ratio;
+      if (partial.temperature !== undefined) changes.default_temperature = partial.temperature;
+      if (partial.model) changes.default_model = partial.model;
+      set(generationDefaults(await cmd.updateSettings(changes)));
     },
     async reset() {
-      set(defaultSettings);
-      await cmd.saveSetting('default_output_size', defaultSettings.output_size);
-      await cmd.saveSetting('default_aspect_ratio', defaultSettings.aspect_ratio);
-      await cmd.saveSetting('default_temperature', String(defaultSettings.temperature));
+      const saved = await cmd.updateSettings({
+        default_output_size: null,
+        default_aspect_ratio: null,
+        default_temperature: null,
+        default_model: null,
+      });
+      set(generationDefaults(saved));
     },
   };
 }
@@ -66,6 +66,30 @@ describe('toastError', () => {
     expect(get(toasts)[0].message).toBe('Something went wrong');
   });
 
+  it('surfaces the message of command errors', () => {
+    toastError({ kind: 'not_found', message: 'Job not found: abc', details: null });
+    expect(get(toasts)[0].message).toBe('Job not found: abc');
+  });
+
+  it('points auth errors at the settings page', () => {
+    toastError({ kind: 'auth', message: 'API key not configured', details: null });
+    expect(get(toasts)[0].message).toBe(
+      'API key not configured. Re-enter your API key in Settings.',
+    );
+  });
+
+  it('points budget errors at the settings page', () => {
+    toastError({
+      kind: 'budget',
+      message: 'This job is estimated at $0.07, more than the $0.05 left in the daily budget.',
+      details: null,
+    });
+    expect(get(toasts)[0].message).toBe(
+      'This job is estimated at $0.07, more than the $0.05 left in the daily budget. ' +
+        'Raise the budget in Settings to submit it.',
+    );
+  });
+
   it('always uses the error variant', () => {
     toastError('x');
     expect(get(toasts)[0].variant).toBe('error');
@@ -1,5 +1,6 @@
 // src/lib/stores/toasts.ts
 import { writable } from 'svelte/store';
+import { isAppError } from '$lib/types';
 
 export type ToastVariant = 'info' | 'success' | 'error';
 
@@ -45,7 +46,15 @@ function createToastsStore() {
 
 export const toasts = createToastsStore();
 
+export function errorMessage(err: unknown, fallback = 'Something went wrong'): string {
+  if (isAppError(err)) {
+    if (err.kind === 'auth') return `${err.message}. Re-enter your API key in Settings.`;
+    if (err.kind === 'budget') return `${err.message} Raise the budget in Settings to submit it.`;
+    return err.message;
+  }
+  return err instanceof Error ? err.message : typeof err === 'string' ? err : fallback;
+}
+
 export function toastError(err: unknown, fallback = 'Something went wrong'): void {
-  const message = err instanceof Error ? err.message : typeof err === 'string' ? err : fallback;
-  toasts.error(message);
+  toasts.error(errorMessage(err, fallback));
 }
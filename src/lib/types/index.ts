@@ -1,7 +1,16 @@
 // src/lib/types/index.ts
 
-export type JobStatus = 'pending' | 'processing' | 'completed' | 'failed' | 'cancelled';
+export type JobStatus =
+  | 'pending'
+  | 'processing'
+  | 'downloading'
+  | 'completed'
+  | 'failed'
+  | 'cancelled';
+export type ItemStatus = 'pending' | 'completed' | 'failed';
 export type JobMode = 'text-to-image' | 'image-to-image';
+/** 'interactive' jobs ran through generate_now at full (non-batch) price. */
+export type JobDelivery = 'batch' | 'interactive';
 export type OutputSize = '1K' | '2K' | '4K';
 export type AspectRatio = '1:1' | '16:9' | '9:16' | '4:3' | '3:4';
 export type Theme = 'light' | 'dark' | 'system';
@@ -15,11 +24,19 @@ export interface Job {
   temperature: number;
   aspect_ratio: AspectRatio;
   batch_job_name: string | null;
+  delivery: JobDelivery;
+  model: string;
   total_items: number;
   completed_items: number;
   failed_items: number;
   created_at: string;
   updated_at: string;
+  /** Expected cost from the backend's price table; null for an unpriced model. */
+  estimated_cost: number | null;
+  /** What the responses billed so far; null until any come back. */
+  actual_cost: number | null;
+  /** Key profile that submitted the job; null until submitted. */
+  profile_id: string | null;
 }
 
 export interface JobItem {
@@ -28,15 +45,61 @@ export interface JobItem {
   input_prompt: string | null;
   input_image_path: string | null;
   output_image_path: string | null;
-  status: JobStatus;
+  status: ItemStatus;
   error: string | null;
   created_at: string;
   updated_at: string;
+  /** Text parts of the response: commentary, or why the model declined. */
+  response_text: string | null;
+  /** `finishReason` of the first candidate, e.g. `STOP` or `IMAGE_SAFETY`. */
+  finish_reason: string | null;
+  /** Set when the prompt itself was blocked and no candidates came back. */
+  block_reason: string | null;
+  safety_ratings: SafetyRating[];
+  usage: TokenUsage | null;
+  cost: number | null;
+  /** Every saved image, in response order; `output_image_path` is the first. */
+  outputs: JobItemOutput[];
+}
+
+export interface TokenUsage {
+  prompt_tokens: number;
+  text_output_tokens: number;
+  image_output_tokens: number;
+  thoughts_tokens: number;
+}
+
+export interface SafetyRating {
+  category: string;
+  probability: string;
+  blocked: boolean;
+}
+
+/** One saved image of an item, from response candidate `candidate_index`. */
+export interface JobItemOutput {
+  item_id: string;
+  candidate_index: number;
+  path: string;
+  mime_type: string;
+  created_at: string;
+}
+
+/** One Gemini batch submitted for a job; large jobs are split across several. */
+export interface JobBatch {
+  batch_name: string;
+  job_id: string;
+  status: JobStatus;
+  item_count: number;
+  completed_requests: number;
+  failed_requests: number;
+  created_at: string;
+  updated_at: string;
 }
 
 export interface JobWithItems {
   job: Job;
   items: JobItem[];
+  batches: JobBatch[];
 }
 
 export interface ConfigStatus {
@@ -44,6 +107,28 @@ export interface ConfigStatus {
   masked: string | null;
 }
 
+/** Whether the key can use one model. */
+export interface ModelAccess {
+  model: string;
+  accessible: boolean;
+  http_status: number | null;
+  reason: string | null;
+  latency_ms: number;
+}
+
+/** What checking an API key found; the top-level fields describe the requested model. */
+export interface KeyValidation {
+  valid: boolean;
+  /** False when the API could not be reached at all. */
+  reachable: boolean;
+  http_status: number | null;
+  /** Google's reason, e.g. API_KEY_INVALID, SERVICE_DISABLED, RESOURCE_EXHAUSTED. */
+  reason: string | null;
+  message: string | null;
+  latency_ms: number;
+  models: ModelAccess[];
+}
+
 export interface UploadedFile {
   id: string;
   path: string;
@@ -55,6 +140,7 @@ export interface CreateT2IJobRequest {
   output_size: OutputSize;
   temperature: number;
   aspect_ratio: AspectRatio;
+  model?: string;
 }
 
 export interface CreateI2IJobRequest {
@@ -63,6 +149,53 @@ export interface CreateI2IJobRequest {
   output_size: OutputSize;
   temperature: number;
   aspect_ratio: AspectRatio;
+  model?: string;
+}
+
+export interface ImageModel {
+  id: string;
+  display_name: string;
+  description: string | null;
+  supports_batch: boolean;
+}
+
+/** Spend in USD for the current local day and month. Null budgets are unlimited. */
+export interface SpendSummary {
+  spent_today: number;
+  spent_this_month: number;
+  /** Estimate for submitted items not billed yet, counted in both periods. */
+  in_flight: number;
+  daily_budget: number | null;
+  monthly_budget: number | null;
+  daily_remaining: number | null;
+  monthly_remaining: number | null;
+}
+
+/** Jobs created on one local day with the same model, size and mode. */
+export interface UsageRow {
+  /** YYYY-MM-DD */
+  day: string;
+  model: string;
+  output_size: string;
+  mode: JobMode;
+  jobs: number;
+  items: number;
+  completed_items: number;
+  failed_items: number;
+  success_rate: number;
+  failure_rate: number;
+  estimated_cost: number | null;
+  actual_cost: number | null;
+}
+
+/** A named API key. Only the masked form reaches the frontend. */
+export interface KeyProfile {
+  id: string;
+  name: string;
+  masked: string | null;
+  /** New submissions use the active profile. */
+  active: boolean;
+  created_at: string;
 }
 
 export type GeminiBatchState =
@@ -80,12 +213,69 @@ export interface BatchStatus {
   failed_requests: number;
 }
 
+export type AppErrorKind =
+  | 'db'
+  | 'io'
+  | 'http'
+  | 'api'
+  | 'validation'
+  | 'not_found'
+  | 'conflict'
+  | 'auth'
+  | 'budget';
+
+/** What every command rejects with. `details` is set for `api` errors. */
+export interface AppError {
+  kind: AppErrorKind;
+  message: string;
+  details: { status: number; code: string | null } | null;
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return (
+    typeof err === 'object' &&
+    err !== null &&
+    typeof (err as AppError).kind === 'string' &&
+    typeof (err as AppError).message === 'string'
+  );
+}
+
+/** User settings as the backend validates and stores them; defaults live there too. */
+export interface Settings {
+  /** Any size or ratio the API supports, which may be more than the UI offers. */
+  default_output_size: string;
+  default_aspect_ratio: string;
+  /** 0 to 2. */
+  default_temperature: number;
+  default_model: string;
+  /** null saves to Pictures/Nana Studio. */
+  results_dir: string | null;
+  uploads_dir: string | null;
+  gemini_base_url: string;
+  /** USD; null is unlimited. */
+  daily_budget: number | null;
+  monthly_budget: number | null;
+  https_proxy: string | null;
+  /** Comma-separated hosts that bypass the proxy. */
+  no_proxy: string | null;
+  /** Absolute path to a PEM bundle of extra CA certificates. */
+  ca_bundle_path: string | null;
+  connect_timeout_secs: number;
+  request_timeout_secs: number;
+}
+
+/** Fields to change; null restores a field's default. */
+export type SettingsChanges = { [K in keyof Settings]?: Settings[K] | null };
+
 export interface GenerationDefaults {
   output_size: OutputSize;
   aspect_ratio: AspectRatio;
   temperature: number;
+  model: string;
 }
 
+export const DEFAULT_MODEL = 'gemini-3.1-flash-image-preview';
+
 export const OUTPUT_SIZES: Record<OutputSize, { label: string; price: number }> = {
   '1K': { label: '1K ($0.02)', price: 0.02 },
   '2K': { label: '2K ($0.07)', price: 0.07 },
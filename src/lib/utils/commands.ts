@@ -8,6 +8,13 @@ import type {
   CreateI2IJobRequest,
   UploadedFile,
   BatchStatus,
+  ImageModel,
+  KeyProfile,
+  KeyValidation,
+  Settings,
+  SettingsChanges,
+  SpendSummary,
+  UsageRow,
 } from '$lib/types';
 
 // --- Jobs ---
@@ -42,8 +49,30 @@ export async function downloadResults(batchName: string, jobId: string): Promise
   return invoke<void>('download_results', { batchName, jobId });
 }
 
-export async function retryJob(jobId: string): Promise<void> {
-  return invoke<void>('submit_batch', { jobId });
+/** Rejects with a `budget` error over budget unless `overrideBudget` is set. */
+export async function retryJob(jobId: string, overrideBudget?: boolean): Promise<void> {
+  return invoke<void>('submit_batch', { jobId, overrideBudget });
+}
+
+/** Runs a job of up to 4 items synchronously instead of as a batch. */
+export async function generateNow(jobId: string, overrideBudget?: boolean): Promise<void> {
+  return invoke<void>('generate_now', { jobId, overrideBudget });
+}
+
+export async function getSpendSummary(): Promise<SpendSummary> {
+  return invoke<SpendSummary>('get_spend_summary');
+}
+
+// --- Usage ---
+
+/** `from` and `to` are inclusive YYYY-MM-DD local dates. */
+export async function getUsageReport(from?: string, to?: string): Promise<UsageRow[]> {
+  return invoke<UsageRow[]>('get_usage_report', { from, to });
+}
+
+/** Writes the report as CSV to an absolute path; resolves to the row count. */
+export async function exportUsageCsv(path: string, from?: string, to?: string): Promise<number> {
+  return invoke<number>('export_usage_csv', { path, from, to });
 }
 
 // --- Config ---
@@ -60,8 +89,35 @@ export async function deleteConfig(): Promise<void> {
   return invoke<void>('delete_config');
 }
 
-export async function validateApiKey(apiKey: string): Promise<boolean> {
-  return invoke<boolean>('validate_api_key', { apiKey });
+export async function validateApiKey(apiKey: string, model?: string): Promise<KeyValidation> {
+  return invoke<KeyValidation>('validate_api_key', { apiKey, model });
+}
+
+export async function listModels(): Promise<ImageModel[]> {
+  return invoke<ImageModel[]>('list_models');
+}
+
+// --- Key profiles ---
+
+export async function listProfiles(): Promise<KeyProfile[]> {
+  return invoke<KeyProfile[]>('list_profiles');
+}
+
+export async function addProfile(name: string, apiKey: string): Promise<KeyProfile> {
+  return invoke<KeyProfile>('add_profile', { name, apiKey });
+}
+
+export async function renameProfile(id: string, name: string): Promise<KeyProfile> {
+  return invoke<KeyProfile>('rename_profile', { id, name });
+}
+
+/** Rejects with a `conflict` error while jobs submitted with it are running. */
+export async function deleteProfile(id: string): Promise<void> {
+  return invoke<void>('delete_profile', { id });
+}
+
+export async function setActiveProfile(id: string): Promise<KeyProfile> {
+  return invoke<KeyProfile>('set_active_profile', { id });
 }
 
 // --- Files ---
@@ -80,16 +136,13 @@ export async function deleteUpload(path: string): Promise<void> {
 
 // --- Settings ---
 
-export async function getSetting(key: string): Promise<string | null> {
-  return invoke<string | null>('get_setting', { key });
-}
-
-export async function saveSetting(key: string, value: string): Promise<void> {
-  return invoke<void>('save_setting', { key, value });
+export async function getSettings(): Promise<Settings> {
+  return invoke<Settings>('get_settings');
 }
 
-export async function getAllSettings(): Promise<Record<string, string>> {
-  return invoke<Record<string, string>>('get_all_settings');
+/** Saves all of `changes` or, if any is invalid, none of them. */
+export async function updateSettings(changes: SettingsChanges): Promise<Settings> {
+  return invoke<Settings>('update_settings', { changes });
 }
 
 export async function getDefaultResultsDir(): Promise<string> {
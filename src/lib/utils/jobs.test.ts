@@ -12,6 +12,8 @@ function makeJob(status: string): Job {
     temperature: 1,
     aspect_ratio: '1:1',
     batch_job_name: null,
+    delivery: 'batch',
+    model: 'gemini-3.1-flash-image-preview',
     total_items: 1,
     completed_items: 0,
     failed_items: 0,
@@ -29,6 +31,10 @@ describe('isActiveJob', () => {
     expect(isActiveJob(makeJob('processing'))).toBe(true);
   });
 
+  it('returns true for jobs whose results are downloading', () => {
+    expect(isActiveJob(makeJob('downloading'))).toBe(true);
+  });
+
   it('returns false for completed jobs', () => {
     expect(isActiveJob(makeJob('completed'))).toBe(false);
   });
@@ -1,5 +1,7 @@
 import type { Job } from '$lib/types';
 
 export function isActiveJob(job: Job): boolean {
-  return job.status === 'pending' || job.status === 'processing';
+  return (
+    job.status === 'pending' || job.status === 'processing' || job.status === 'downloading'
+  );
 }
@@ -1,5 +1,5 @@
 // src/lib/utils/mock-data.ts
-import type { Job, JobItem } from '$lib/types';
+import type { Job, JobItem, JobItemOutput } from '$lib/types';
 
 export function createMockJobs(): Job[] {
   const now = new Date().toISOString();
@@ -15,11 +15,16 @@ export function createMockJobs(): Job[] {
       temperature: 1,
       aspect_ratio: '16:9',
       batch_job_name: null,
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 3,
       completed_items: 0,
       failed_items: 0,
       created_at: now,
       updated_at: now,
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
     },
     // Processing job with progress
     {
@@ -31,11 +36,16 @@ export function createMockJobs(): Job[] {
       temperature: 1.5,
       aspect_ratio: '16:9',
       batch_job_name: 'batches/mock-batch-1',
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 5,
       completed_items: 3,
       failed_items: 0,
       created_at: new Date(Date.now() - 120000).toISOString(),
       updated_at: now,
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
     },
     // Completed job
     {
@@ -47,11 +57,16 @@ export function createMockJobs(): Job[] {
       temperature: 0.5,
       aspect_ratio: '1:1',
       batch_job_name: 'batches/mock-batch-2',
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 2,
       completed_items: 2,
       failed_items: 0,
       created_at: new Date(Date.now() - 300000).toISOString(),
       updated_at: new Date(Date.now() - 240000).toISOString(),
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
     },
     // Another completed job (I2I)
     {
@@ -63,11 +78,16 @@ export function createMockJobs(): Job[] {
       temperature: 1,
       aspect_ratio: '4:3',
       batch_job_name: 'batches/mock-batch-3',
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 3,
       completed_items: 3,
       failed_items: 0,
       created_at: new Date(Date.now() - 600000).toISOString(),
       updated_at: new Date(Date.now() - 500000).toISOString(),
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
     },
     // Failed job
     {
@@ -79,11 +99,16 @@ export function createMockJobs(): Job[] {
       temperature: 2,
       aspect_ratio: '3:4',
       batch_job_name: 'batches/mock-batch-4',
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 4,
       completed_items: 1,
       failed_items: 3,
       created_at: new Date(Date.now() - 900000).toISOString(),
       updated_at: new Date(Date.now() - 800000).toISOString(),
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
     },
     // Cancelled job
     {
@@ -95,11 +120,28 @@ export function createMockJobs(): Job[] {
       temperature: 1,
       aspect_ratio: '16:9',
       batch_job_name: 'batches/mock-batch-5',
+      delivery: 'batch',
+      model: 'gemini-3.1-flash-image-preview',
       total_items: 6,
       completed_items: 2,
       failed_items: 0,
       created_at: new Date(Date.now() - 1200000).toISOString(),
       updated_at: new Date(Date.now() - 1100000).toISOString(),
+      estimated_cost: null,
+      actual_cost: null,
+      profile_id: null,
+    },
+  ];
+}
+
+function placeholderOutputs(itemId: string, now: string): JobItemOutput[] {
+  return [
+    {
+      item_id: itemId,
+      candidate_index: 0,
+      path: 'mock://placeholder',
+      mime_type: 'image/png',
+      created_at: now,
     },
   ];
 }
@@ -120,6 +162,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-1a', now),
       },
       {
         id: 'mock-item-1b',
@@ -131,6 +180,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-1b', now),
       },
     ];
   }
@@ -147,6 +203,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-2a', now),
       },
       {
         id: 'mock-item-2b',
@@ -158,6 +221,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-2b', now),
       },
       {
         id: 'mock-item-2c',
@@ -169,6 +239,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-2c', now),
       },
     ];
   }
@@ -185,6 +262,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: null,
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: placeholderOutputs('mock-item-3a', now),
       },
       {
         id: 'mock-item-3b',
@@ -196,6 +280,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: 'Content policy violation: image generation blocked',
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: [],
       },
       {
         id: 'mock-item-3c',
@@ -207,6 +298,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: 'Rate limit exceeded',
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: [],
       },
       {
         id: 'mock-item-3d',
@@ -218,6 +316,13 @@ export function createMockJobItems(jobId: string): JobItem[] {
         error: 'Internal server error',
         created_at: now,
         updated_at: now,
+        response_text: null,
+        finish_reason: null,
+        block_reason: null,
+        safety_ratings: [],
+        usage: null,
+        cost: null,
+        outputs: [],
       },
     ];
   }
@@ -15,14 +15,19 @@ const baseJob: Job = {
   temperature: 1,
   aspect_ratio: '16:9',
   batch_job_name: null,
+  delivery: 'batch',
+  model: 'gemini-3.1-flash-image-preview',
   total_items: 1,
   completed_items: 0,
   failed_items: 0,
   created_at: '',
   updated_at: '',
+  estimated_cost: null,
+  actual_cost: null,
+  profile_id: null,
 };
 
-const jobResult: JobWithItems = { job: baseJob, items: [] };
+const jobResult: JobWithItems = { job: baseJob, items: [], batches: [] };
 
 describe('submitJob', () => {
   beforeEach(() => {
@@ -34,7 +39,7 @@ describe('submitJob', () => {
     for (const j of get(jobs)) {
       jobs.removeJob(j.id);
     }
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('routes text-to-image mode to create_t2i_job with prompts only', async () => {
@@ -103,7 +108,7 @@ describe('submitAndTrack', () => {
     for (const j of get(jobs)) {
       jobs.removeJob(j.id);
     }
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('adds the job to the store and dispatches submit_batch', async () => {
@@ -111,7 +116,7 @@ describe('submitAndTrack', () => {
     submitAndTrack(baseJob);
     expect(get(jobs)).toContainEqual(baseJob);
     expect(invoke).toHaveBeenCalledWith('submit_batch', { jobId: 'job-1' });
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 
   it('flips the job to failed and toasts on submit_batch failure', async () => {
@@ -128,6 +133,6 @@ describe('submitAndTrack', () => {
     const toast = get(toasts)[0];
     expect(toast?.variant).toBe('error');
     expect(toast?.message).toBe('API key invalid');
-    jobs.stopPolling();
+    jobs.stopListening();
   });
 });
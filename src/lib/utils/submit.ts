@@ -13,6 +13,7 @@ export interface SubmitParams {
   output_size: OutputSize;
   aspect_ratio: AspectRatio;
   temperature: number;
+  model?: string;
 }
 
 /**
@@ -30,6 +31,7 @@ export async function submitJob(params: SubmitParams): Promise<void> {
           output_size: params.output_size,
           temperature: params.temperature,
           aspect_ratio: params.aspect_ratio,
+          model: params.model,
         })
       : await createI2IJob({
           prompt: params.i2iPrompt,
@@ -37,6 +39,7 @@ export async function submitJob(params: SubmitParams): Promise<void> {
           output_size: params.output_size,
           temperature: params.temperature,
           aspect_ratio: params.aspect_ratio,
+          model: params.model,
         });
 
   submitAndTrack(result.job);
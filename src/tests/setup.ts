@@ -15,6 +15,12 @@ vi.mock('@tauri-apps/api/core', () => ({
   invoke: vi.fn(),
 }));
 
+// Mock Tauri event API. listen() resolves to a no-op unlisten function;
+// tests that need to fire events grab the handler from the mock's calls.
+vi.mock('@tauri-apps/api/event', () => ({
+  listen: vi.fn().mockResolvedValue(() => {}),
+}));
+
 // Mock Tauri dialog plugin
 vi.mock('@tauri-apps/plugin-dialog', () => ({
   open: vi.fn(),